@@ -16,3 +16,11 @@ pub fn parse_draw(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],mut color:[f32;4]
     }
 }
 
+
+//material a brush paints with until one is picked with /material
+pub fn default_material(id: i32) -> &'static str{
+    match id{
+        1 => "fire",
+        _ => "object",
+    }
+}
@@ -2,6 +2,11 @@ use cgmath::*;
 use iced_winit::winit::{event::*, dpi::PhysicalPosition};
 use std::time::Duration;
 
+use crate::frustum::Frustum;
+use crate::camera_mode::{CameraInput, CameraMode, IsometricMode, FreeFlyMode, FirstPersonMode, Transition, View};
+use crate::camera_path::{CameraPath, Keyframe, Playback};
+use crate::input::{Action, InputMap, Target};
+
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
@@ -9,6 +14,30 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     0.0, 0.0, 0.5, 0.0,
     0.0, 0.0, 0.5, 1.0,
 );
+
+//vertical field of view in degrees for the perspective projection
+pub const DEFAULT_FOV: f32 = 15.0;
+pub const MAX_FOV: f32 = 120.0;
+
+//the depth pass shades from this far in front of the target to this far behind it
+const DEPTH_BEFORE_TARGET: f32 = 1700.0;
+const DEPTH_BEHIND_TARGET: f32 = 1500.0;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Projection {
+    Orthographic,
+    Perspective,
+}
+
+impl Projection {
+    pub fn to_str(&self) -> &str {
+        match self {
+            Projection::Orthographic => "ortho",
+            Projection::Perspective => "perspective",
+        }
+    }
+}
+
 pub struct Camera {
     pub eye: cgmath::Point3<f32>,
     pub target: cgmath::Point3<f32>,
@@ -20,15 +49,71 @@ pub struct Camera {
     pub fovy: f32,
     pub znear: f32,
     pub zfar: f32,
+    pub projection: Projection,
+    //degrees, perspective only
+    pub fov: f32,
 }
 
 impl Camera {
     pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
         let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
-        let proj = cgmath::ortho(-self.aspect*self.fovy, self.aspect*self.fovy,-1.0*self.fovy ,1.0*self.fovy,self.znear,self.zfar);
-        //let proj = cgmath::perspective(cgmath::Deg(15.0), self.aspect, self.znear, self.zfar);
+        let proj = match self.projection{
+            Projection::Orthographic => cgmath::ortho(-self.aspect*self.fovy, self.aspect*self.fovy,-1.0*self.fovy ,1.0*self.fovy,self.znear,self.zfar),
+            Projection::Perspective => cgmath::perspective(cgmath::Deg(self.fov), self.aspect, self.znear, self.zfar),
+        };
         return OPENGL_TO_WGPU_MATRIX * proj * view
     }
+
+    //the near and far point under a position in normalized device coordinates, works for both projections
+    pub fn unproject(&self, ndc_x: f32, ndc_y: f32) -> (cgmath::Point3<f32>, cgmath::Point3<f32>) {
+        let inverse = self.build_view_projection_matrix().invert().unwrap_or(Matrix4::identity());
+        let near = inverse * cgmath::vec4(ndc_x, ndc_y, 0.0, 1.0);
+        let far = inverse * cgmath::vec4(ndc_x, ndc_y, 1.0, 1.0);
+        (Point3::from_homogeneous(near), Point3::from_homogeneous(far))
+    }
+
+    //distances from the eye the depth pass maps to black and white
+    pub fn depth_range(&self) -> [f32; 2] {
+        let distance = (self.target - self.eye).magnitude();
+        [(distance - DEPTH_BEFORE_TARGET).max(self.znear), distance + DEPTH_BEHIND_TARGET]
+    }
+
+    //pixel counts from the top left of a viewport sized window
+    //the offscreen image is shown sample_ratio times larger around the center, see Vertex_tex
+    pub fn screen_ray(&self, pixel: [f32; 2], viewport: [f32; 2], sample_ratio: f32) -> Ray {
+        let ndc_x = (pixel[0] - viewport[0] / 2.0) / (viewport[0] / 2.0) / sample_ratio;
+        let ndc_y = -(pixel[1] - viewport[1] / 2.0) / (viewport[1] / 2.0) / sample_ratio;
+        let (near, far) = self.unproject(ndc_x, ndc_y);
+        Ray { origin: near, direction: (far - near).normalize() }
+    }
+
+    //the pixel a world point lands on, none when it is behind a perspective eye
+    pub fn world_to_screen(&self, point: Point3<f32>, viewport: [f32; 2], sample_ratio: f32) -> Option<[f32; 2]> {
+        let clip = self.build_view_projection_matrix() * point.to_homogeneous();
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc_x = clip.x / clip.w * sample_ratio;
+        let ndc_y = clip.y / clip.w * sample_ratio;
+        Some([(ndc_x + 1.0) * viewport[0] / 2.0, (1.0 - ndc_y) * viewport[1] / 2.0])
+    }
+}
+
+//starts on the near plane, direction is normalized
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    pub fn closest_point(&self, point: Point3<f32>) -> Point3<f32> {
+        self.at((point - self.origin).dot(self.direction))
+    }
 }
 
 #[repr(C)]
@@ -63,9 +148,10 @@ impl Uniform {
 pub struct CameraUniform {
     view_proj: [[f32;4];4],
     position:[f32;3],
-    _padding0: u32,
+    //packed into the vec3 padding
+    depth_near: f32,
     eye:[f32;3],
-    _padding1: u32,
+    depth_far: f32,
 }
 
 impl CameraUniform {
@@ -73,9 +159,9 @@ impl CameraUniform {
         Self {
             view_proj: cgmath::Matrix4::identity().into(),
             position:Default::default(),
-            _padding0:0,
+            depth_near:0.0,
             eye:Default::default(),
-            _padding1:0
+            depth_far:1.0,
         }
     }
 
@@ -83,6 +169,11 @@ impl CameraUniform {
         self.view_proj = camera.build_view_projection_matrix().into();
         self.position = [camera.position.x,camera.position.y,camera.position.z];
         self.eye = [camera.eye.x,camera.eye.y,camera.eye.z];
+        [self.depth_near, self.depth_far] = camera.depth_range();
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.view_proj.into())
     }
 }
 
@@ -92,7 +183,6 @@ pub struct CameraController {
 
     scr_width: f32,
     scr_height: f32,
-    aspect: f32,
     speed: f32,
     sensitivity: f32,
     
@@ -109,16 +199,25 @@ pub struct CameraController {
 
     pub is_control_pressed: bool,
 
-    forward_count:f32,
-    left_count:f32,
+    pub is_fast_pressed: bool,
+    pub is_slow_pressed: bool,
 
     rotate_horizontal: f32,
     rotate_vertical: f32,
-    radius:f32,
-    pos_x: f32,
-    pos_y: f32,
-    pos_z: f32,
-    yaw:f32,
+
+    //isometric, fly and walk, see camera_mode
+    mode_list: Vec<Box<dyn CameraMode>>,
+    pub mode_index: usize,
+    //eased move to a saved view, see /view and /tp
+    transition: Option<Transition>,
+    //keyframes for flythroughs, see /path
+    pub path: CameraPath,
+    playback: Option<Playback>,
+
+    //keys, buttons and modifiers to actions, see input
+    pub input_map: InputMap,
+    //commands bound to keys, run by the state like typed ones
+    pub command_queue: Vec<String>,
 
     pub is_cli_released:bool,
     pub is_cli_pressed: bool,
@@ -126,8 +225,6 @@ pub struct CameraController {
     pub mouse_right_pressed: bool,
     pub scroll:f32,
 
-    x_current: f32,
-    y_current: f32,
     x_offset: f32,
     y_offset: f32,
 }
@@ -139,7 +236,6 @@ impl CameraController {
             scr_edge_flag: false,
             scr_width,
             scr_height,
-            aspect: 0.0,
             
             speed,
             sensitivity,
@@ -155,13 +251,16 @@ impl CameraController {
             is_prior_pressed:false,
             is_next_pressed:false,
 
+            input_map: InputMap::new(),
+            command_queue: Default::default(),
+
             is_cli_pressed:false,
             is_cli_released:false,
 
             is_control_pressed:false,
 
-            forward_count:0.0,
-            left_count:0.0,
+            is_fast_pressed:false,
+            is_slow_pressed:false,
 
             is_left_pressed: false,
             is_right_pressed: false,
@@ -170,109 +269,56 @@ impl CameraController {
             mouse_right_pressed: false,
             scroll: 0.0,
 
-            x_current:0.0,
-            y_current:0.0,
             x_offset: 0.0,
             y_offset: 0.0,
             
             rotate_horizontal: 0.0,
             rotate_vertical: 0.0,
-            radius:2828.427125,
-            pos_x: 0.0,
-            pos_y: 1000.0,
-            pos_z: 0.0,
-            yaw:0.0,
+            mode_list: vec![Box::new(IsometricMode::new()), Box::new(FreeFlyMode::new()), Box::new(FirstPersonMode::new())],
+            mode_index: 0,
+            transition: None,
+            path: CameraPath::new(),
+            playback: None,
         }
     }
 
     pub fn process_events(&mut self, event: &WindowEvent) -> bool {
-        match event {
-
-
-            WindowEvent::MouseInput {
-                button: MouseButton::Left,
-                state,
-                ..
-            } => {
-                self.mouse_left_pressed = *state == ElementState::Pressed;
+        match self.input_map.process(event) {
+            Some((Target::Action(action), is_pressed)) => {
+                self.set_action(action, is_pressed);
                 true
             }
-
-            WindowEvent::MouseInput {
-                button: MouseButton::Right,
-                state,
-                ..
-            } => {
-                self.mouse_right_pressed = *state == ElementState::Pressed;
+            Some((Target::Command(command), is_pressed)) => {
+                if is_pressed {
+                    self.command_queue.push(command);
+                }
                 true
             }
+            None => false,
+        }
+    }
 
-            WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        state,
-                        virtual_keycode: Some(keycode),
-                        ..
-                    },
-                ..
-            } => {
-                let is_pressed = *state == ElementState::Pressed;
-                let is_released = *state == ElementState::Released;
-                match keycode {
-
-                    VirtualKeyCode::F3 => {
-                        self.is_cli_pressed = is_pressed;
-                        self.is_cli_released = is_released;
-                        true
-                    }
-                    VirtualKeyCode::Space => {
-                        self.is_up_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::LShift => {
-                        self.is_down_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::LControl=> {
-                        self.is_control_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::Tab=> {
-                        self.is_tab_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::LAlt=> {
-                        self.is_alt_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::W => {
-                        self.is_forward_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::A => {
-                        self.is_left_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::S => {
-                        self.is_backward_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::D => {
-                        self.is_right_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::Q => {
-                        self.is_prior_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::E => {
-                        self.is_next_pressed = is_pressed;
-                        true
-                    }
-                    _ => false,
-                }
+    fn set_action(&mut self, action: Action, is_pressed: bool) {
+        match action {
+            Action::MoveForward => self.is_forward_pressed = is_pressed,
+            Action::MoveBackward => self.is_backward_pressed = is_pressed,
+            Action::MoveLeft => self.is_left_pressed = is_pressed,
+            Action::MoveRight => self.is_right_pressed = is_pressed,
+            Action::MoveUp => self.is_up_pressed = is_pressed,
+            Action::MoveDown => self.is_down_pressed = is_pressed,
+            Action::CycleMode => self.is_tab_pressed = is_pressed,
+            Action::PriorModel => self.is_prior_pressed = is_pressed,
+            Action::NextModel => self.is_next_pressed = is_pressed,
+            Action::ToggleConsole => {
+                self.is_cli_pressed = is_pressed;
+                self.is_cli_released = !is_pressed;
             }
-            _ => false,
+            Action::Delete => self.is_control_pressed = is_pressed,
+            Action::Snap => self.is_alt_pressed = is_pressed,
+            Action::Place => self.mouse_left_pressed = is_pressed,
+            Action::Look => self.mouse_right_pressed = is_pressed,
+            Action::FastMove => self.is_fast_pressed = is_pressed,
+            Action::SlowMove => self.is_slow_pressed = is_pressed,
         }
     }
 
@@ -319,95 +365,202 @@ impl CameraController {
 
     }
 
-    pub fn update_camera(&mut self, camera: &mut Camera ,dt: Duration) {
+    pub fn input(&self) -> CameraInput {
+        CameraInput {
+            forward: self.is_forward_pressed,
+            backward: self.is_backward_pressed,
+            left: self.is_left_pressed,
+            right: self.is_right_pressed,
+            up: self.is_up_pressed,
+            down: self.is_down_pressed,
+            fast: self.is_fast_pressed,
+            slow: self.is_slow_pressed,
+            look: self.mouse_right_pressed,
+            look_delta: [self.rotate_horizontal, self.rotate_vertical],
+            edge_offset: [self.x_offset, self.y_offset],
+            aspect: self.scr_width / self.scr_height,
+            speed: self.speed,
+            sensitivity: self.sensitivity,
+        }
+    }
 
-        let dt = dt.as_secs_f32();
+    pub fn mode_name(&self) -> &str {
+        self.mode_list[self.mode_index].name()
+    }
 
-        //rotate when right pressed
-        if self.mouse_right_pressed {
-            self.yaw += self.rotate_horizontal;
-        }
+    pub fn mode_name_list(&self) -> Vec<&str> {
+        self.mode_list.iter().map(|m| m.name()).collect()
+    }
 
-        self.pos_x = Rad::sin(Rad(self.yaw*self.sensitivity))*self.radius;
-        self.pos_z = Rad::cos(Rad(self.yaw*self.sensitivity))*self.radius;
+    pub fn set_mode(&mut self, name: &str, camera: &mut Camera, is_solid: &dyn Fn([i32; 3]) -> bool) -> Result<(), String> {
+        let index = self.mode_list.iter().position(|m| m.name() == name).ok_or("Unknown camera mode")?;
+        self.mode_index = index;
+        self.mode_list[index].enter(camera, is_solid);
+        Ok(())
+    }
 
-        camera.forward = Vector3::new(self.pos_x, 0.0, self.pos_z).normalize();
-        camera.left = camera.up.cross(camera.forward).normalize();
+    pub fn view(&self, camera: &Camera, zoom: f32) -> View {
+        View { zoom, ..self.mode_list[self.mode_index].view(camera) }
+    }
 
-        let mut camera_smooth_x = 0.0;
-        let mut camera_smooth_y = 0.0;
+    //eases from the current view, a new call replaces a running transition
+    pub fn go_to(&mut self, view: View, camera: &Camera, zoom: f32) {
+        self.transition = Some(Transition::new(self.view(camera, zoom), view));
+    }
 
-        if self.is_forward_pressed{
-            self.forward_count -= dt* self.speed;
-            camera_smooth_y = -3.0;
-        } 
-        if self.is_backward_pressed{
-            self.forward_count += dt* self.speed;
-            camera_smooth_y = 3.0;
-        } 
+    pub fn set_speed(&mut self, speed: f32, sensitivity: f32) {
+        self.speed = speed;
+        self.sensitivity = sensitivity;
+    }
 
-        if self.is_left_pressed{
-            self.left_count -=dt* self.speed;
-            camera_smooth_x = 10.0;
-        }
-        if self.is_right_pressed{
-            self.left_count += dt* self.speed;
-            camera_smooth_x = -10.0;
+    //the screen edge scrolling follows the window
+    pub fn resize(&mut self, scr_width: f32, scr_height: f32) {
+        self.scr_width = scr_width;
+        self.scr_height = scr_height;
+    }
+
+    pub fn add_keyframe(&mut self, camera: &Camera, zoom: f32) {
+        let view = self.view(camera, zoom);
+        self.path.keyframe_list.push(Keyframe { view, projection: camera.projection });
+    }
+
+    //step is fixed seconds per frame, none follows the frame time
+    pub fn play(&mut self, step: Option<f32>) -> Result<(), String> {
+        if self.path.keyframe_list.len() < 2 {
+            return Err("A path needs at least 2 keyframes".to_string());
         }
-        if self.is_up_pressed{
-            camera.position.y += 3.0 * 1.0 / 2.82842 * ((dt * self.speed) - (dt * self.speed) % 1.0);
-            camera_smooth_y = -3.0;
+        self.transition = None;
+        self.playback = Some(Playback::new(step));
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.playback = None;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    //is_solid lets the walking mode collide with the world
+    //returns the zoom while a transition or path is running
+    pub fn update_camera(&mut self, camera: &mut Camera ,dt: Duration, is_solid: &dyn Fn([i32; 3]) -> bool) -> Option<f32> {
+
+        let mut zoom = None;
+        let mut input = self.input();
+
+        if let Some(playback) = &mut self.playback {
+            match playback.advance(dt.as_secs_f32(), self.path.duration()).and_then(|time| self.path.sample(time)) {
+                Some(keyframe) => {
+                    self.mode_list[self.mode_index].set_view(&keyframe.view, camera);
+                    camera.projection = keyframe.projection;
+                    zoom = Some(keyframe.view.zoom);
+                    //the mode still places the camera, but keys and mouse do not move it
+                    input = CameraInput { aspect: input.aspect, speed: input.speed, sensitivity: input.sensitivity, ..Default::default() };
+                }
+                None => self.playback = None,
+            }
         }
-        if self.is_down_pressed{
-            camera.position.y -= 3.0 * 1.0 / 2.82842 * ((dt * self.speed) - (dt * self.speed) % 1.0);
-            camera_smooth_y = 3.0;
+        else if let Some(transition) = &mut self.transition {
+            let view = transition.step(dt.as_secs_f32());
+            self.mode_list[self.mode_index].set_view(&view, camera);
+            zoom = Some(view.zoom);
+            if transition.finished() {
+                self.transition = None;
+            }
         }
 
-        //pixel glitch fix
-        camera.position += (self.forward_count-self.forward_count%3.0) * camera.forward;
-        camera.position += (self.left_count-self.left_count%1.0) * camera.left;
+        self.mode_list[self.mode_index].update(&input, camera, dt.as_secs_f32(), is_solid);
 
-        self.forward_count %= 3.0;
-        self.left_count %= 1.0;
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
 
-        self.aspect = self.scr_width /self.scr_height;
+        zoom
+    }
+}
 
-        
-        //camera accelerate calulate
+//the isometric view the game starts with, looking down at the origin
+#[cfg(test)]
+pub(crate) fn test_camera(projection: Projection, aspect: f32, fovy: f32) -> Camera {
+    Camera {
+        eye: (1000.0, 1000.0, 1000.0).into(),
+        target: (0.0, 0.0, 0.0).into(),
+        position: (0.0, 0.0, 0.0).into(),
+        up: Vector3::unit_y(),
+        forward: Vector3::unit_y(),
+        aspect,
+        fovy,
+        znear: 0.1,
+        zfar: 10100.0,
+        left: Vector3::unit_y(),
+        projection,
+        fov: DEFAULT_FOV,
+    }
+}
 
-        let x_target = self.x_offset / 2.0 + camera_smooth_x;
-        let y_target = self.y_offset * self.aspect / 2.0 + camera_smooth_y;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        
-        if  (x_target - self.x_current).abs().sqrt() > 1.0 { //avoid glitching loop
-            if self.x_current < x_target{
-                self.x_current += (x_target - self.x_current).sqrt()*dt*50.0;
-            }
-            else {
-                self.x_current -= (self.x_current - x_target).sqrt()*dt*50.0;
-            }
-        }
+    const VIEWPORT: [f32; 2] = [1600.0, 900.0];
 
-        if (y_target - self.y_current).abs().sqrt() > 1.0 {
+    //one offscreen pixel per voxel across the view, like State::new sets it up
+    fn camera(projection: Projection) -> Camera {
+        let mut camera = test_camera(projection, VIEWPORT[0] / VIEWPORT[1], VIEWPORT[1] / 2.0);
+        //a near plane this close loses the depth precision the round trips need
+        camera.znear = 10.0;
+        camera
+    }
 
-            if self.y_current < y_target{
-                self.y_current += (y_target - self.y_current).sqrt()*dt*50.0;
-            }
-            else {
-                self.y_current -= (self.y_current - y_target).sqrt()*dt*50.0;
-            }
+    //screen right for an eye on the diagonal looking at the origin
+    fn right() -> Vector3<f32> {
+        vec3(1.0, 0.0, -1.0).normalize()
+    }
 
-        }
+    #[test]
+    fn ortho_rays_are_parallel_and_scale_with_sample_ratio() {
+        let camera = camera(Projection::Orthographic);
+        let target = camera.target;
+        let center = camera.screen_ray([800.0, 450.0], VIEWPORT, 1.0);
+        assert!((center.closest_point(target) - target).magnitude() < 0.05);
 
-        //pixel glitch fix
-        camera.target = camera.position + (self.x_current - self.x_current % 1.0) * camera.left + (self.y_current - self.y_current%3.0) * camera.forward;
+        let side = camera.screen_ray([900.0, 450.0], VIEWPORT, 1.0);
+        assert!((side.direction - center.direction).magnitude() < 1e-4);
+        assert!((side.closest_point(target) - (target + right() * 100.0)).magnitude() < 0.05);
 
-        camera.eye = cgmath::Point3::new(self.pos_x,self.pos_y,self.pos_z)+(
-            camera.target-cgmath::Point3::new(0.0,0.0,0.0)
-        ); 
-        
-        self.rotate_horizontal = 0.0;
-        self.rotate_vertical = 0.0;
+        //the image is shown twice as large, so a pixel covers half a voxel
+        let zoomed = camera.screen_ray([900.0, 450.0], VIEWPORT, 2.0);
+        assert!((zoomed.closest_point(target) - (target + right() * 50.0)).magnitude() < 0.05);
+    }
+
+    #[test]
+    fn perspective_rays_fan_out_from_the_eye() {
+        let camera = camera(Projection::Perspective);
+        let center = camera.screen_ray([800.0, 450.0], VIEWPORT, 1.0);
+        let side = camera.screen_ray([1200.0, 450.0], VIEWPORT, 1.0);
+
+        let forward = (camera.target - camera.eye).normalize();
+        assert!((center.direction - forward).magnitude() < 1e-4);
+        assert!((center.origin - camera.eye).magnitude() < camera.znear + 0.1);
+        //both rays come back through the eye but leave in different directions
+        assert!((side.at(-(side.origin - camera.eye).magnitude()) - camera.eye).magnitude() < 0.05);
+        assert!(side.direction.dot(right()) > 0.0);
+    }
+
+    #[test]
+    fn world_to_screen_undoes_screen_ray() {
+        for projection in [Projection::Orthographic, Projection::Perspective] {
+            let camera = camera(projection);
+            for sample_ratio in [1.0, 2.5] {
+                let pixel = [1100.0, 300.0];
+                let point = camera.screen_ray(pixel, VIEWPORT, sample_ratio).at(1500.0);
+                let screen = camera.world_to_screen(point, VIEWPORT, sample_ratio).unwrap();
+                assert!((screen[0] - pixel[0]).abs() < 0.05 && (screen[1] - pixel[1]).abs() < 0.05);
+            }
+        }
 
+        //behind the eye has no pixel in perspective
+        let camera = camera(Projection::Perspective);
+        assert!(camera.world_to_screen((2000.0, 2000.0, 2000.0).into(), VIEWPORT, 1.0).is_none());
     }
 }
@@ -0,0 +1,665 @@
+use cgmath::*;
+
+use crate::camera::{Camera, Projection};
+use crate::settings::DEFAULT_CAMERA_SPEED;
+
+//the free modes keep the target this far ahead of the eye, streaming, lod and the depth pass follow it
+const LOOK_DISTANCE: f32 = 256.0;
+//just short of straight up or down, where look_at has no defined left
+const MAX_PITCH: f32 = 1.55;
+
+const FLY_SPEED: f32 = 120.0;
+const FAST_FACTOR: f32 = 4.0;
+const SLOW_FACTOR: f32 = 0.25;
+
+//first person body in voxels, the feet sit on the bottom face of the box
+const BODY_WIDTH: f32 = 4.0;
+const BODY_HEIGHT: f32 = 12.0;
+const EYE_HEIGHT: f32 = 11.0;
+//ledges up to this high are walked onto instead of blocking
+const STEP_HEIGHT: f32 = 2.0;
+const WALK_SPEED: f32 = 40.0;
+const GRAVITY: f32 = 200.0;
+const JUMP_SPEED: f32 = 70.0;
+const MAX_FALL_SPEED: f32 = 400.0;
+//how far up enter looks for free space when the target is buried
+const MAX_SPAWN_CLIMB: i32 = 256;
+
+//transitions take longer the further they go, between these in seconds
+const MIN_TRANSITION_TIME: f32 = 0.4;
+const MAX_TRANSITION_TIME: f32 = 2.0;
+//voxels covered per second on top of the minimum
+const TRANSITION_SPEED: f32 = 2000.0;
+
+//what the controller gathered from the keyboard and mouse this frame
+#[derive(Copy, Clone, Default)]
+pub struct CameraInput {
+    pub forward: bool,
+    pub backward: bool,
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    pub fast: bool,
+    pub slow: bool,
+    //held right button, the modes only turn while it is down
+    pub look: bool,
+    //raw mouse motion since the last frame
+    pub look_delta: [f32; 2],
+    //distance of the cursor past the screen edge, zero when inside
+    pub edge_offset: [f32; 2],
+    pub aspect: f32,
+    pub speed: f32,
+    pub sensitivity: f32,
+}
+
+//a camera placement that can be saved and returned to, see /view
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct View {
+    //pan anchor for the isometric mode, eye or feet for the others
+    pub position: [f32; 3],
+    //radians
+    pub yaw: f32,
+    pub pitch: f32,
+    //sample ratio
+    pub zoom: f32,
+}
+
+//slow at both ends
+fn ease(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+}
+
+impl View {
+    pub fn lerp(&self, other: &View, t: f32) -> View {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        //turn the short way round
+        let turn = (other.yaw - self.yaw + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        View {
+            position: [0, 1, 2].map(|i| mix(self.position[i], other.position[i])),
+            yaw: self.yaw + turn * t,
+            pitch: mix(self.pitch, other.pitch),
+            zoom: mix(self.zoom, other.zoom),
+        }
+    }
+}
+
+pub struct Transition {
+    from: View,
+    to: View,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl Transition {
+    pub fn new(from: View, to: View) -> Self {
+        let distance = (0..3).map(|i| (to.position[i] - from.position[i]).powi(2)).sum::<f32>().sqrt();
+        let duration = (MIN_TRANSITION_TIME + distance / TRANSITION_SPEED).min(MAX_TRANSITION_TIME);
+        Self { from, to, elapsed: 0.0, duration }
+    }
+
+    pub fn step(&mut self, dt: f32) -> View {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        self.from.lerp(&self.to, ease(self.elapsed / self.duration))
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+//moves the camera from input each frame, see CameraController
+pub trait CameraMode {
+
+    fn name(&self) -> &'static str;
+
+    //called when the mode becomes active, so it can start from where the camera is now
+    fn enter(&mut self, camera: &mut Camera, is_solid: &dyn Fn([i32; 3]) -> bool);
+
+    fn update(&mut self, input: &CameraInput, camera: &mut Camera, dt: f32, is_solid: &dyn Fn([i32; 3]) -> bool);
+
+    //the zoom is left at zero for the controller to fill in
+    fn view(&self, camera: &Camera) -> View;
+
+    //moves straight to the view, the next update places the camera
+    fn set_view(&mut self, view: &View, camera: &mut Camera);
+}
+
+//the orbiting pixel art view, panned with the keys and the screen edges
+pub struct IsometricMode {
+    forward_count: f32,
+    left_count: f32,
+
+    radius: f32,
+    pos_x: f32,
+    pos_y: f32,
+    pos_z: f32,
+    yaw: f32,
+
+    x_current: f32,
+    y_current: f32,
+}
+
+impl IsometricMode {
+    pub fn new() -> Self {
+        Self {
+            forward_count: 0.0,
+            left_count: 0.0,
+            radius: 2828.427125,
+            pos_x: 0.0,
+            pos_y: 1000.0,
+            pos_z: 0.0,
+            yaw: 0.0,
+            x_current: 0.0,
+            y_current: 0.0,
+        }
+    }
+}
+
+impl Default for IsometricMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraMode for IsometricMode {
+
+    fn name(&self) -> &'static str {
+        "isometric"
+    }
+
+    fn enter(&mut self, camera: &mut Camera, _is_solid: &dyn Fn([i32; 3]) -> bool) {
+        camera.projection = Projection::Orthographic;
+        camera.up = Vector3::unit_y();
+        //keep panning from where the free modes were looking
+        camera.position = Point3::new(camera.target.x.round(), camera.target.y.round(), camera.target.z.round());
+        self.x_current = 0.0;
+        self.y_current = 0.0;
+    }
+
+    fn update(&mut self, input: &CameraInput, camera: &mut Camera, dt: f32, _is_solid: &dyn Fn([i32; 3]) -> bool) {
+
+        //rotate when right pressed
+        if input.look {
+            self.yaw += input.look_delta[0] * input.sensitivity;
+        }
+
+        self.pos_x = Rad::sin(Rad(self.yaw))*self.radius;
+        self.pos_z = Rad::cos(Rad(self.yaw))*self.radius;
+
+        camera.forward = Vector3::new(self.pos_x, 0.0, self.pos_z).normalize();
+        camera.left = camera.up.cross(camera.forward).normalize();
+
+        let mut camera_smooth_x = 0.0;
+        let mut camera_smooth_y = 0.0;
+
+        if input.forward{
+            self.forward_count -= dt* input.speed;
+            camera_smooth_y = -3.0;
+        }
+        if input.backward{
+            self.forward_count += dt* input.speed;
+            camera_smooth_y = 3.0;
+        }
+
+        if input.left{
+            self.left_count -=dt* input.speed;
+            camera_smooth_x = 10.0;
+        }
+        if input.right{
+            self.left_count += dt* input.speed;
+            camera_smooth_x = -10.0;
+        }
+        if input.up{
+            camera.position.y += 3.0 * 1.0 / 2.82842 * ((dt * input.speed) - (dt * input.speed) % 1.0);
+            camera_smooth_y = -3.0;
+        }
+        if input.down{
+            camera.position.y -= 3.0 * 1.0 / 2.82842 * ((dt * input.speed) - (dt * input.speed) % 1.0);
+            camera_smooth_y = 3.0;
+        }
+
+        //pixel glitch fix
+        camera.position += (self.forward_count-self.forward_count%3.0) * camera.forward;
+        camera.position += (self.left_count-self.left_count%1.0) * camera.left;
+
+        self.forward_count %= 3.0;
+        self.left_count %= 1.0;
+
+        //camera accelerate calulate
+
+        let x_target = input.edge_offset[0] / 2.0 + camera_smooth_x;
+        let y_target = input.edge_offset[1] * input.aspect / 2.0 + camera_smooth_y;
+
+
+        if  (x_target - self.x_current).abs().sqrt() > 1.0 { //avoid glitching loop
+            if self.x_current < x_target{
+                self.x_current += (x_target - self.x_current).sqrt()*dt*50.0;
+            }
+            else {
+                self.x_current -= (self.x_current - x_target).sqrt()*dt*50.0;
+            }
+        }
+
+        if (y_target - self.y_current).abs().sqrt() > 1.0 {
+
+            if self.y_current < y_target{
+                self.y_current += (y_target - self.y_current).sqrt()*dt*50.0;
+            }
+            else {
+                self.y_current -= (self.y_current - y_target).sqrt()*dt*50.0;
+            }
+
+        }
+
+        //pixel glitch fix
+        camera.target = camera.position + (self.x_current - self.x_current % 1.0) * camera.left + (self.y_current - self.y_current%3.0) * camera.forward;
+
+        camera.eye = cgmath::Point3::new(self.pos_x,self.pos_y,self.pos_z)+(
+            camera.target-cgmath::Point3::new(0.0,0.0,0.0)
+        );
+    }
+
+    fn view(&self, camera: &Camera) -> View {
+        View { position: camera.position.into(), yaw: self.yaw, pitch: 0.0, zoom: 0.0 }
+    }
+
+    fn set_view(&mut self, view: &View, camera: &mut Camera) {
+        camera.position = view.position.into();
+        self.yaw = view.yaw;
+        self.x_current = 0.0;
+        self.y_current = 0.0;
+    }
+}
+
+//yaw and pitch shared by the free modes
+struct Look {
+    yaw: f32,
+    pitch: f32,
+}
+
+impl Look {
+    fn from_camera(camera: &Camera) -> Self {
+        let dir = (camera.target - camera.eye).normalize();
+        Self { yaw: dir.z.atan2(dir.x), pitch: dir.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH) }
+    }
+
+    fn turn(&mut self, input: &CameraInput) {
+        if input.look {
+            self.yaw += input.look_delta[0] * input.sensitivity;
+            self.pitch = (self.pitch - input.look_delta[1] * input.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+    }
+
+    fn dir(&self) -> Vector3<f32> {
+        Vector3::new(self.yaw.cos() * self.pitch.cos(), self.pitch.sin(), self.yaw.sin() * self.pitch.cos())
+    }
+
+    //level with the ground, used for walking and for the camera forward
+    fn flat_dir(&self) -> Vector3<f32> {
+        Vector3::new(self.yaw.cos(), 0.0, self.yaw.sin())
+    }
+
+    //points the camera from eye along the look direction
+    fn apply(&self, eye: Point3<f32>, camera: &mut Camera) {
+        camera.eye = eye;
+        camera.target = eye + self.dir() * LOOK_DISTANCE;
+        camera.position = camera.target;
+        camera.up = Vector3::unit_y();
+        //forward points away from the view like in the isometric mode
+        camera.forward = -self.flat_dir();
+        camera.left = camera.up.cross(camera.forward).normalize();
+    }
+}
+
+//wish direction from the movement keys, in the xz plane
+fn walk_dir(input: &CameraInput, forward: Vector3<f32>) -> Vector3<f32> {
+    let right = forward.cross(Vector3::unit_y()).normalize();
+    let mut dir = Vector3::zero();
+    if input.forward { dir += forward; }
+    if input.backward { dir -= forward; }
+    if input.right { dir += right; }
+    if input.left { dir -= right; }
+    if dir.magnitude2() > 0.0 { dir.normalize() } else { dir }
+}
+
+//the camera_speed setting scales the fly and walk speeds, which are tuned for its default
+fn speed_factor(input: &CameraInput) -> f32 {
+    let modifier = if input.fast { FAST_FACTOR } else if input.slow { SLOW_FACTOR } else { 1.0 };
+    modifier * input.speed / DEFAULT_CAMERA_SPEED
+}
+
+//flies through everything, for getting anywhere quickly
+pub struct FreeFlyMode {
+    eye: Point3<f32>,
+    look: Look,
+}
+
+impl FreeFlyMode {
+    pub fn new() -> Self {
+        Self { eye: Point3::origin(), look: Look { yaw: 0.0, pitch: 0.0 } }
+    }
+}
+
+impl Default for FreeFlyMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraMode for FreeFlyMode {
+
+    fn name(&self) -> &'static str {
+        "fly"
+    }
+
+    fn enter(&mut self, camera: &mut Camera, _is_solid: &dyn Fn([i32; 3]) -> bool) {
+        camera.projection = Projection::Perspective;
+        self.eye = camera.eye;
+        self.look = Look::from_camera(camera);
+        self.look.apply(self.eye, camera);
+    }
+
+    fn update(&mut self, input: &CameraInput, camera: &mut Camera, dt: f32, _is_solid: &dyn Fn([i32; 3]) -> bool) {
+
+        self.look.turn(input);
+
+        //the movement keys follow the full look direction, space and shift move straight up and down
+        let dir = self.look.dir();
+        let right = dir.cross(Vector3::unit_y()).normalize();
+        let mut velocity = Vector3::zero();
+        if input.forward { velocity += dir; }
+        if input.backward { velocity -= dir; }
+        if input.right { velocity += right; }
+        if input.left { velocity -= right; }
+        if input.up { velocity += Vector3::unit_y(); }
+        if input.down { velocity -= Vector3::unit_y(); }
+        if velocity.magnitude2() > 0.0 {
+            self.eye += velocity.normalize() * FLY_SPEED * speed_factor(input) * dt;
+        }
+
+        self.look.apply(self.eye, camera);
+    }
+
+    fn view(&self, _camera: &Camera) -> View {
+        View { position: self.eye.into(), yaw: self.look.yaw, pitch: self.look.pitch, zoom: 0.0 }
+    }
+
+    fn set_view(&mut self, view: &View, camera: &mut Camera) {
+        self.eye = view.position.into();
+        self.look = Look { yaw: view.yaw, pitch: view.pitch.clamp(-MAX_PITCH, MAX_PITCH) };
+        self.look.apply(self.eye, camera);
+    }
+}
+
+//walks on voxels with gravity, for looking inside builds
+pub struct FirstPersonMode {
+    //bottom center of the body
+    feet: Point3<f32>,
+    look: Look,
+    vertical_speed: f32,
+    on_ground: bool,
+}
+
+impl FirstPersonMode {
+    pub fn new() -> Self {
+        Self { feet: Point3::origin(), look: Look { yaw: 0.0, pitch: 0.0 }, vertical_speed: 0.0, on_ground: false }
+    }
+
+    fn eye(&self) -> Point3<f32> {
+        self.feet + Vector3::unit_y() * EYE_HEIGHT
+    }
+}
+
+impl Default for FirstPersonMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//voxel p fills the unit cube centered on it
+fn body_blocked(feet: Point3<f32>, is_solid: &dyn Fn([i32; 3]) -> bool) -> bool {
+
+    //a body touching a face does not count as inside
+    let e = 0.001;
+    let half = BODY_WIDTH / 2.0;
+    let first = [feet.x - half + e, feet.y + e, feet.z - half + e].map(|v| (v + 0.5).floor() as i32);
+    let last = [feet.x + half - e, feet.y + BODY_HEIGHT - e, feet.z + half - e].map(|v| (v + 0.5).floor() as i32);
+
+    for x in first[0]..=last[0] {
+        for y in first[1]..=last[1] {
+            for z in first[2]..=last[2] {
+                if is_solid([x, y, z]) {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+//moves along one axis in steps short enough not to skip a voxel, stopping at the first contact
+fn sweep(feet: &mut Point3<f32>, axis: usize, distance: f32, is_solid: &dyn Fn([i32; 3]) -> bool) -> bool {
+
+    let step_count = (distance.abs() / 0.5).ceil().max(1.0) as i32;
+    let step = distance / step_count as f32;
+
+    for _ in 0..step_count {
+        let mut next = *feet;
+        next[axis] += step;
+        if body_blocked(next, is_solid) {
+            return true;
+        }
+        *feet = next;
+    }
+    false
+}
+
+impl CameraMode for FirstPersonMode {
+
+    fn name(&self) -> &'static str {
+        "walk"
+    }
+
+    fn enter(&mut self, camera: &mut Camera, is_solid: &dyn Fn([i32; 3]) -> bool) {
+
+        camera.projection = Projection::Perspective;
+
+        //stand where the camera was looking, climbing out of anything solid
+        self.feet = Point3::new(camera.target.x, camera.target.y, camera.target.z);
+        for _ in 0..MAX_SPAWN_CLIMB {
+            if !body_blocked(self.feet, is_solid) {
+                break;
+            }
+            self.feet.y += 1.0;
+        }
+
+        self.look = Look::from_camera(camera);
+        self.look.pitch = 0.0;
+        self.vertical_speed = 0.0;
+        self.on_ground = false;
+        self.look.apply(self.eye(), camera);
+    }
+
+    fn update(&mut self, input: &CameraInput, camera: &mut Camera, dt: f32, is_solid: &dyn Fn([i32; 3]) -> bool) {
+
+        self.look.turn(input);
+
+        let walk = walk_dir(input, self.look.flat_dir()) * WALK_SPEED * speed_factor(input) * dt;
+
+        for (axis, distance) in [(0, walk.x), (2, walk.z)] {
+            if distance == 0.0 {
+                continue;
+            }
+            let before = self.feet;
+            if sweep(&mut self.feet, axis, distance, is_solid) && self.on_ground {
+                //try again standing a little higher, which walks up a ledge
+                let mut raised = before;
+                if !sweep(&mut raised, 1, STEP_HEIGHT, is_solid) && !sweep(&mut raised, axis, distance, is_solid) {
+                    self.feet = raised;
+                    sweep(&mut self.feet, 1, -STEP_HEIGHT, is_solid);
+                }
+            }
+        }
+
+        if input.up && self.on_ground {
+            self.vertical_speed = JUMP_SPEED;
+        }
+        self.vertical_speed = (self.vertical_speed - GRAVITY * dt).max(-MAX_FALL_SPEED);
+
+        let blocked = sweep(&mut self.feet, 1, self.vertical_speed * dt, is_solid);
+        self.on_ground = blocked && self.vertical_speed < 0.0;
+        if blocked {
+            self.vertical_speed = 0.0;
+        }
+
+        self.look.apply(self.eye(), camera);
+    }
+
+    fn view(&self, _camera: &Camera) -> View {
+        View { position: self.feet.into(), yaw: self.look.yaw, pitch: self.look.pitch, zoom: 0.0 }
+    }
+
+    //the body is carried along without collision, it falls from wherever the view ends
+    fn set_view(&mut self, view: &View, camera: &mut Camera) {
+        self.feet = view.position.into();
+        self.look = Look { yaw: view.yaw, pitch: view.pitch.clamp(-MAX_PITCH, MAX_PITCH) };
+        self.vertical_speed = 0.0;
+        self.look.apply(self.eye(), camera);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    use crate::camera::test_camera;
+
+    fn camera() -> Camera {
+        let mut camera = test_camera(Projection::Orthographic, 1.0, 200.0);
+        camera.eye = (0.0, 40.0, -100.0).into();
+        camera
+    }
+
+    //a flat floor at y = 0 with a wall across x = 10
+    fn world() -> HashSet<[i32; 3]> {
+        let mut solid: HashSet<[i32; 3]> = Default::default();
+        for x in -20..=20 {
+            for z in -20..=20 {
+                solid.insert([x, 0, z]);
+            }
+        }
+        for y in 1..=20 {
+            for z in -20..=20 {
+                solid.insert([10, y, z]);
+            }
+        }
+        //a one voxel ledge to step onto
+        for x in -20..=-8 {
+            for z in -20..=20 {
+                solid.insert([x, 1, z]);
+            }
+        }
+        solid
+    }
+
+    #[test]
+    fn walks_on_the_floor_and_stops_at_walls() {
+        let solid = world();
+        let is_solid = |p: [i32; 3]| solid.contains(&p);
+        let mut camera = camera();
+        let mut mode = FirstPersonMode::new();
+        mode.enter(&mut camera, &is_solid);
+
+        //buried in the floor at first, climbs out
+        assert!(!body_blocked(mode.feet, &is_solid));
+
+        let idle = CameraInput::default();
+        for _ in 0..120 {
+            mode.update(&idle, &mut camera, 1.0 / 60.0, &is_solid);
+        }
+        assert!(mode.on_ground);
+        assert!((mode.feet.y - 0.5).abs() < 0.01);
+
+        //face +x and walk into the wall
+        mode.look.yaw = 0.0;
+        let walk = CameraInput { forward: true, speed: DEFAULT_CAMERA_SPEED, ..Default::default() };
+        for _ in 0..240 {
+            mode.update(&walk, &mut camera, 1.0 / 60.0, &is_solid);
+        }
+        assert!(mode.feet.x < 10.0 - 0.5 - BODY_WIDTH / 2.0 + 0.01);
+        assert!(mode.feet.x > 6.0);
+        assert!(camera.projection == Projection::Perspective);
+        assert!((camera.eye.y - mode.feet.y - EYE_HEIGHT).abs() < 1e-4);
+    }
+
+    #[test]
+    fn steps_onto_low_ledges() {
+        let solid = world();
+        let is_solid = |p: [i32; 3]| solid.contains(&p);
+        let mut camera = camera();
+        let mut mode = FirstPersonMode::new();
+        mode.enter(&mut camera, &is_solid);
+
+        let idle = CameraInput::default();
+        for _ in 0..120 {
+            mode.update(&idle, &mut camera, 1.0 / 60.0, &is_solid);
+        }
+
+        //face -x towards the ledge
+        mode.look.yaw = std::f32::consts::PI;
+        let walk = CameraInput { forward: true, speed: DEFAULT_CAMERA_SPEED, ..Default::default() };
+        for _ in 0..25 {
+            mode.update(&walk, &mut camera, 1.0 / 60.0, &is_solid);
+        }
+        assert!(mode.feet.x < -10.0);
+        assert!((mode.feet.y - 1.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn transitions_ease_to_the_view() {
+        let from = View { position: [0.0, 0.0, 0.0], yaw: 3.0, pitch: 0.0, zoom: 1.0 };
+        let to = View { position: [1000.0, 0.0, 0.0], yaw: -3.0, pitch: 0.0, zoom: 3.0 };
+        let mut transition = Transition::new(from, to);
+        assert!((transition.duration - (MIN_TRANSITION_TIME + 0.5)).abs() < 1e-6);
+
+        //slow start, half way at half time
+        let early = transition.step(transition.duration * 0.1);
+        assert!(early.position[0] < 100.0);
+        let half = transition.step(transition.duration * 0.4);
+        assert!((half.position[0] - 500.0).abs() < 1e-2);
+        assert!((half.zoom - 2.0).abs() < 1e-4);
+        //from 3 to -3 radians is shorter through pi than through zero
+        assert!(half.yaw > 3.0);
+
+        let end = transition.step(10.0);
+        assert!(transition.finished());
+        assert_eq!(end.position, to.position);
+        assert!(((end.yaw - to.yaw).rem_euclid(std::f32::consts::TAU)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn fly_looks_with_clamped_pitch() {
+        let nothing = |_: [i32; 3]| false;
+        let mut camera = camera();
+        let mut mode = FreeFlyMode::new();
+        mode.enter(&mut camera, &nothing);
+        assert!((camera.eye - Point3::new(0.0, 40.0, -100.0)).magnitude() < 1e-4);
+
+        let look_up = CameraInput { look: true, look_delta: [0.0, -10_000.0], sensitivity: 0.002, ..Default::default() };
+        mode.update(&look_up, &mut camera, 0.0, &nothing);
+        assert!((mode.look.pitch - MAX_PITCH).abs() < 1e-6);
+
+        let fly = CameraInput { up: true, fast: true, speed: DEFAULT_CAMERA_SPEED, ..Default::default() };
+        mode.update(&fly, &mut camera, 0.5, &nothing);
+        assert!((camera.eye.y - (40.0 + FLY_SPEED * FAST_FACTOR * 0.5)).abs() < 1e-3);
+
+        //twice the camera_speed setting covers twice the distance
+        let fly = CameraInput { down: true, speed: DEFAULT_CAMERA_SPEED * 2.0, ..Default::default() };
+        let y = camera.eye.y;
+        mode.update(&fly, &mut camera, 0.5, &nothing);
+        assert!((y - camera.eye.y - FLY_SPEED).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,217 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+use crate::camera::Projection;
+use crate::camera_mode::View;
+
+//seconds between two keyframes during playback
+pub const DEFAULT_SEGMENT_TIME: f32 = 2.0;
+
+//frame rate used by /path record when none is given
+pub const DEFAULT_RECORD_FPS: f32 = 30.0;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    pub view: View,
+    pub projection: Projection,
+}
+
+//keyframes recorded with /path add, played back through a catmull-rom spline
+pub struct CameraPath {
+    pub keyframe_list: Vec<Keyframe>,
+    pub segment_time: f32,
+}
+
+//passes through p1 at t 0 and p2 at t 1, p0 and p3 shape the tangents
+fn catmull_rom(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self { keyframe_list: Default::default(), segment_time: DEFAULT_SEGMENT_TIME }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframe_list.len().saturating_sub(1) as f32 * self.segment_time
+    }
+
+    //the camera at time seconds into the path, clamped to both ends
+    pub fn sample(&self, time: f32) -> Option<Keyframe> {
+
+        let last = self.keyframe_list.len().checked_sub(1)?;
+        if last == 0 || self.segment_time <= 0.0 {
+            return self.keyframe_list.first().copied();
+        }
+
+        let s = (time / self.segment_time).clamp(0.0, last as f32);
+        let i = (s.floor() as usize).min(last - 1);
+        let t = s - i as f32;
+
+        //the end points are repeated so the curve stops on the first and last keyframe
+        let index = |k: isize| (i as isize + k).clamp(0, last as isize) as usize;
+        let p = [index(-1), index(0), index(1), index(2)].map(|k| self.keyframe_list[k].view);
+
+        //yaw is unwrapped against its neighbour so a spline never spins the long way round
+        let mut yaw = [p[0].yaw, p[1].yaw, p[2].yaw, p[3].yaw];
+        (1..4).for_each(|k| {
+            let turn = (yaw[k] - yaw[k - 1] + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+            yaw[k] = yaw[k - 1] + turn;
+        });
+
+        let spline = |f: &dyn Fn(&View) -> f32| catmull_rom(f(&p[0]), f(&p[1]), f(&p[2]), f(&p[3]), t);
+
+        let view = View {
+            position: [0, 1, 2].map(|k| spline(&|v| v.position[k])),
+            yaw: catmull_rom(yaw[0], yaw[1], yaw[2], yaw[3], t),
+            pitch: spline(&|v| v.pitch),
+            //overshoot past the sample ratio range is clamped by the state
+            zoom: spline(&|v| v.zoom),
+        };
+
+        //projection cannot be blended, it switches when the next keyframe is reached
+        let projection = self.keyframe_list[if t >= 1.0 { i + 1 } else { i }].projection;
+
+        Some(Keyframe { view, projection })
+    }
+
+    //one line per keyframe, the same style as world.txt
+    pub fn to_text(&self) -> String {
+        let mut text = format!("time {}\n", self.segment_time);
+        self.keyframe_list.iter().for_each(|k| {
+            let p = k.view.position;
+            text += &format!(
+                "key {} {} {} {} {} {} {}\n",
+                p[0], p[1], p[2], k.view.yaw, k.view.pitch, k.view.zoom, k.projection.to_str()
+            );
+        });
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+
+        let mut path = Self::new();
+
+        for line in text.lines() {
+            let v: Vec<&str> = line.split_whitespace().collect();
+
+            match v.first() {
+                Some(&"time") if v.len() > 1 => {
+                    path.segment_time = v[1].parse::<f32>().map_err(|e| e.to_string())?;
+                }
+                Some(&"key") if v.len() > 7 => {
+                    let l: Vec<f32> = v[1..7].iter().map(|s| s.parse::<f32>().map_err(|e| e.to_string())).collect::<Result<_, _>>()?;
+                    let projection = match v[7] {
+                        "perspective" => Projection::Perspective,
+                        _ => Projection::Orthographic,
+                    };
+                    path.keyframe_list.push(Keyframe {
+                        view: View { position: [l[0], l[1], l[2]], yaw: l[3], pitch: l[4], zoom: l[5] },
+                        projection,
+                    });
+                }
+                Some(&"key") => return Err("Insufficient args in ".to_string() + line),
+                //unknown keys are left for newer versions
+                _ => {}
+            }
+        }
+
+        Ok(path)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_text()).map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::from_text(&fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//time driven motion along a CameraPath, see CameraController::play
+pub struct Playback {
+    time: f32,
+    //fixed seconds per frame while recording, so every frame of the sequence is evenly spaced
+    step: Option<f32>,
+}
+
+impl Playback {
+    pub fn new(step: Option<f32>) -> Self {
+        Self { time: 0.0, step }
+    }
+
+    //the time to show this frame, none once the end has been shown
+    pub fn advance(&mut self, dt: f32, duration: f32) -> Option<f32> {
+        if self.time > duration {
+            return None;
+        }
+        let time = self.time;
+        //the last step lands past the end so the final keyframe is always shown
+        self.time = if time < duration { (time + self.step.unwrap_or(dt)).min(duration) } else { duration + 1.0 };
+        Some(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(x: f32, yaw: f32, projection: Projection) -> Keyframe {
+        Keyframe { view: View { position: [x, 0.0, 0.0], yaw, pitch: 0.0, zoom: 2.0 }, projection }
+    }
+
+    #[test]
+    fn spline_passes_through_keyframes() {
+        let mut path = CameraPath::new();
+        path.segment_time = 1.0;
+        path.keyframe_list = vec![
+            keyframe(0.0, 0.0, Projection::Orthographic),
+            keyframe(100.0, 3.0, Projection::Orthographic),
+            keyframe(200.0, -3.0, Projection::Perspective),
+        ];
+        assert_eq!(path.duration(), 2.0);
+
+        let at = |time: f32| path.sample(time).unwrap();
+        assert!((at(1.0).view.position[0] - 100.0).abs() < 1e-4);
+        assert!((at(2.0).view.position[0] - 200.0).abs() < 1e-4);
+        //eases out of the first keyframe, then keeps moving forward
+        let x = [0.25, 0.5, 0.75, 1.25].map(|time| at(time).view.position[0]);
+        assert!(x[0] > 0.0 && x[0] < 25.0);
+        assert!(x.windows(2).all(|w| w[0] < w[1]));
+        //from 3 to -3 radians turns through pi
+        assert!(at(1.5).view.yaw > 3.0);
+        assert!(at(1.5).projection == Projection::Orthographic);
+        assert!(at(2.0).projection == Projection::Perspective);
+        assert!((at(-1.0).view.position[0]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut path = CameraPath::new();
+        path.segment_time = 0.5;
+        path.keyframe_list = vec![keyframe(1.5, 0.25, Projection::Perspective), keyframe(-8.0, 1.0, Projection::Orthographic)];
+
+        let loaded = CameraPath::from_text(&path.to_text()).unwrap();
+        assert_eq!(loaded.segment_time, 0.5);
+        assert!(loaded.keyframe_list == path.keyframe_list);
+    }
+
+    #[test]
+    fn fixed_step_playback_ends_on_the_last_keyframe() {
+        let mut playback = Playback::new(Some(0.4));
+        let time_list: Vec<f32> = std::iter::from_fn(|| playback.advance(10.0, 1.0)).collect();
+        assert_eq!(time_list.len(), 4);
+        assert!((time_list[2] - 0.8).abs() < 1e-6);
+        assert_eq!(time_list[3], 1.0);
+    }
+}
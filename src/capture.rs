@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use iced_wgpu::wgpu;
+
+//an offscreen copy of the final image without the ui, written out as png, see /path record
+pub struct FrameCapture {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    size: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+    //rows in the buffer are padded to what copy_texture_to_buffer needs
+    padded_row: u32,
+}
+
+//drops the row padding and puts channels in png order
+pub fn unpad(data: &[u8], width: u32, height: u32, padded_row: u32, bgra: bool) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    (0..height as usize).for_each(|y| {
+        let row = &data[y * padded_row as usize..y * padded_row as usize + width as usize * 4];
+        row.chunks_exact(4).for_each(|p| {
+            if bgra {
+                rgba.extend_from_slice(&[p[2], p[1], p[0], p[3]]);
+            }
+            else {
+                rgba.extend_from_slice(p);
+            }
+        });
+    });
+    rgba
+}
+
+impl FrameCapture {
+
+    //format has to match the surface so the blend pipeline can draw into it
+    pub fn new(device: &wgpu::Device, size: wgpu::Extent3d, format: wgpu::TextureFormat) -> Result<Self, String> {
+
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Bgra8UnormSrgb | wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb | wgpu::TextureFormat::Rgba8Unorm
+        ) {
+            return Err("Unsupported surface format for capture".to_string());
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            label: Some("capture_texture"),
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = (size.width * 4 + align - 1) / align * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("capture_buffer"),
+            size: (padded_row * size.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self { texture, view, buffer, size, format, padded_row })
+    }
+
+    //recorded after the frame has been drawn into view
+    pub fn copy(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(self.padded_row),
+                    rows_per_image: None,
+                },
+            },
+            self.size,
+        );
+    }
+
+    //waits for the gpu, call once the encoder from copy has been submitted
+    pub fn save(&self, device: &wgpu::Device, path: &Path) -> Result<(), String> {
+
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().map_err(|e| e.to_string())?.map_err(|e| e.to_string())?;
+
+        let bgra = matches!(self.format, wgpu::TextureFormat::Bgra8UnormSrgb | wgpu::TextureFormat::Bgra8Unorm);
+        let rgba = unpad(&slice.get_mapped_range(), self.size.width, self.size.height, self.padded_row, bgra);
+        self.buffer.unmap();
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let file = fs::File::create(path).map_err(|e| e.to_string())?;
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), self.size.width, self.size.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer.write_image_data(&rgba).map_err(|e| e.to_string())
+    }
+}
+
+//numbered frames written while a camera path plays
+pub struct Recording {
+    pub capture: FrameCapture,
+    pub dir: PathBuf,
+    pub frame: usize,
+}
+
+impl Recording {
+    pub fn new(capture: FrameCapture, dir: &str) -> Self {
+        Self { capture, dir: PathBuf::from(dir), frame: 0 }
+    }
+
+    pub fn save_frame(&mut self, device: &wgpu::Device) -> Result<(), String> {
+        self.capture.save(device, &self.dir.join(format!("frame_{:05}.png", self.frame)))?;
+        self.frame += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_padding_and_swaps_channels() {
+        //two pixels per row padded to twelve bytes
+        let data = [
+            1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0,
+            9, 10, 11, 12, 13, 14, 15, 16, 0, 0, 0, 0,
+        ];
+        assert_eq!(unpad(&data, 2, 2, 12, false), vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+        assert_eq!(unpad(&data, 2, 1, 12, true), vec![3, 2, 1, 4, 7, 6, 5, 8]);
+    }
+}
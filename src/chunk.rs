@@ -1,4 +1,4 @@
-use std::collections::{HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::time::Duration;
 use iced_wgpu::wgpu;
 use iced_wgpu::wgpu::util::DeviceExt;
@@ -12,24 +12,32 @@ use winit::{
     window::{Window, WindowBuilder,Fullscreen},
 };
 
-use crate::{Instance,InstanceRaw, shell, VoxelType};
+use crate::{Instance,InstanceRaw, shell};
 use crate::camera::*;
+use crate::camera_mode::View;
+use crate::settings::Settings;
 use crate::shell::Message::*;
 use crate::brush_list;
 use crate::model_list;
+use crate::vegetation;
+use crate::stream::ChunkStreamer;
+use crate::lod::{self, LodSettings};
+use crate::material_list::{MaterialList, DEFAULT_MATERIAL};
+use crate::light::{Light, LightHandle, LightManager, DEFAULT_LIGHT_RADIUS};
+use crate::query::VoxelQuery;
+use crate::job::JobSystem;
 
 const RADIUS_CHUNK:i32 = 16;
 const RADIUS_VOXEL:i32 = 256;
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct Light {
-    pub position: [f32; 3],
-    // uniforms requiring 16 byte (4 float) spacing, we need to use a padding field here
-    _padding: u32,
-    pub color: [f32; 4],
-}
+//downsampling a full chunk is slow, the rest wait for later frames at their current level
+const MAX_LOD_BUILD_PER_FRAME:usize = 2;
+
+//voxels are drawn as lines reaching past their cell, and lod voxels are bigger still
+const CULL_MARGIN:f32 = 8.0;
 
+//how far the cursor ray looks for voxels
+const PICK_DISTANCE:f32 = (RADIUS_VOXEL * 32) as f32;
 
 #[derive(Clone, Copy)]
 pub struct BrushState {
@@ -38,6 +46,8 @@ pub struct BrushState {
     pub radius:i32,
     pub color:[f32;4],
     pub max_id:i32,
+    //None keeps the brush default, see brush_list::default_material
+    pub material:Option<usize>,
 
 }
 
@@ -56,7 +66,7 @@ impl BrushState {
     }
 
     pub fn new() -> Self{
-        Self { id: 0, radius: RADIUS_VOXEL/4, color: [1.0,1.0,1.0,1.0] ,max_id:1}
+        Self { id: 0, radius: RADIUS_VOXEL/4, color: [1.0,1.0,1.0,1.0] ,max_id:1, material:None}
     }
 }
 pub struct ModelState {
@@ -67,6 +77,7 @@ pub struct ModelState {
     pub radius: i32,
     pub name: String,
     pub color: [f32;4],
+    pub material: usize,
 }
 
 
@@ -106,13 +117,28 @@ impl ModelState {
                 self.height = RADIUS_VOXEL / self.scale;
                 self.radius = RADIUS_VOXEL / self.scale;
             }
+            vegetation::TREE_MODEL => {
+                self.name = "TREE".to_string();
+                self.height = RADIUS_VOXEL / self.scale;
+                self.radius = RADIUS_VOXEL / self.scale / 2;
+            }
+            vegetation::BUSH_MODEL => {
+                self.name = "BUSH".to_string();
+                self.height = RADIUS_VOXEL / self.scale / 4;
+                self.radius = RADIUS_VOXEL / self.scale / 2;
+            }
+            vegetation::GRASS_MODEL => {
+                self.name = "GRASS".to_string();
+                self.height = RADIUS_VOXEL / self.scale / 8;
+                self.radius = RADIUS_VOXEL / self.scale / 8;
+            }
 
             _=>{}
         }
     }
 
     pub fn new() -> Self{
-        Self { id: 0, scale:1,max_id:14,height: 1,radius:Default::default(), name:"CHESS".to_owned(), color: [0.7,0.7,0.7,1.0]}
+        Self { id: 0, scale:1,max_id:vegetation::GRASS_MODEL,height: 1,radius:Default::default(), name:"CHESS".to_owned(), color: [0.7,0.7,0.7,1.0], material:DEFAULT_MATERIAL}
     }
 }
 
@@ -144,8 +170,11 @@ impl IndicatorState {
 pub struct ChunkManager{
 
     pub chunk_list:Vec<Chunk>,
+    //index into chunk_list for default chunks, keyed by chunk position
+    pub chunk_hash:HashMap<[i32;3],usize>,
 
-    pub point_light_list:Vec<Light>,
+    pub light_manager:LightManager,
+    pub material_list:MaterialList,
 
     pub debug_mode:bool,
     pub w:f32,
@@ -159,6 +188,8 @@ pub struct ChunkManager{
 
     pub pervious_indicator_first:[i32;3],
     pub pervious_indicator_last:[i32;3],
+    //where the cursor pointed last, kept when it points at nothing
+    pub pervious_target:[f32;3],
 
     pub next_overdose:bool,
     pub prior_overdose:bool,
@@ -166,6 +197,17 @@ pub struct ChunkManager{
     pub pervious_mouse_left:bool,
 
     pub duplicate:bool,
+
+    //boxes edited since the simulation last looked, see logic_list
+    pub wake_list:Vec<([i32;3],[i32;3])>,
+
+    //default chunks far from the camera are packed away here, see stream
+    pub streamer:ChunkStreamer,
+
+    pub lod_settings:LodSettings,
+
+    //named camera views saved with the world, see /view
+    pub view_hash:BTreeMap<String,View>,
     
 }
 #[derive(Copy, Clone, PartialEq)]
@@ -182,20 +224,6 @@ impl ChunkManager{
     pub fn new(device:&wgpu::Device) -> Self{
 
         let mut chunk_list:Vec<Chunk> = Default::default();
-        let mut light_vector:Vec<Light> = Default::default();
-
-
-
-        for _x in 0..512{
-            light_vector.push(
-                Light {
-                    position: [0.0, 0.0, 0.0],
-                    _padding: 0,
-                    color: [0.0,0.0,0.0,0.0],
-                }
-            );
-        }
-
         let debug_mode = true;
 
         let w = 0.0;   
@@ -203,6 +231,7 @@ impl ChunkManager{
         Self{
 
             chunk_list,
+            chunk_hash:Default::default(),
             debug_mode,
             w,
 
@@ -216,19 +245,25 @@ impl ChunkManager{
 
             pervious_indicator_first:Default::default(),
             pervious_indicator_last:Default::default(), 
+            pervious_target:Default::default(),
 
             next_overdose:Default::default(),
             prior_overdose:Default::default(),
 
             duplicate: false,
             pervious_mouse_left: false,
-            point_light_list: light_vector,
+            light_manager: LightManager::new(),
+            material_list: MaterialList::new(),
+            wake_list: Default::default(),
+            streamer: ChunkStreamer::new(),
+            lod_settings: LodSettings::new(),
+            view_hash: Default::default(),
 
         }
     }
 
 
-    pub fn update(&mut self,device:&wgpu::Device,dt:Duration,camera:&Camera,camera_controller:&mut CameraController,mouse_pos_x:f64,mouse_pos_y:f64,texture_size: wgpu::Extent3d, iced_state: &mut program::State<shell::Controls>, sample_ratio:&mut f32){
+    pub fn update(&mut self,device:&wgpu::Device,dt:Duration,camera:&Camera,camera_controller:&mut CameraController,mouse_pos_x:f64,mouse_pos_y:f64,texture_size: wgpu::Extent3d, iced_state: &mut program::State<shell::Controls>, sample_ratio:&mut f32, settings:&Settings){
         
         if self.w < -0.5{
             self.w =  1.0;
@@ -246,7 +281,6 @@ impl ChunkManager{
         let shell_color_config = iced_state.program().color;
         let mut color = [shell_color_config.r,shell_color_config.g,shell_color_config.b,self.w];
 
-        self.point_light_list[0].color = [shell_color_config.r,shell_color_config.g,shell_color_config.b,1.0];
 
         if camera_controller.is_control_pressed{
             delete = true;
@@ -258,14 +292,14 @@ impl ChunkManager{
         if camera_controller.is_control_pressed{
             if camera_controller.scroll < 0.0{
                 *sample_ratio += 0.1;
-                if *sample_ratio > 4.0{
-                    *sample_ratio = 4.0;
+                if *sample_ratio > settings.sample_ratio_max{
+                    *sample_ratio = settings.sample_ratio_max;
                 }
             }
             else if camera_controller.scroll > 0.0{
                 *sample_ratio -= 0.1;
-                if *sample_ratio < 1.0{
-                    *sample_ratio = 1.0;
+                if *sample_ratio < settings.sample_ratio_min{
+                    *sample_ratio = settings.sample_ratio_min;
                 }
             }
         }
@@ -277,14 +311,14 @@ impl ChunkManager{
                 IndicatorState::Normal => {
                     if camera_controller.scroll > 0.0{
                         self.brush_state.radius += 1;
-                        if self.brush_state.radius > 64{
-                            self.brush_state.radius = 64;
+                        if self.brush_state.radius > settings.brush_radius_max{
+                            self.brush_state.radius = settings.brush_radius_max;
                         }
                     }
                     else if camera_controller.scroll < 0.0{
                         self.brush_state.radius -= 1;
-                        if self.brush_state.radius < 1{
-                            self.brush_state.radius = 1;
+                        if self.brush_state.radius < settings.brush_radius_min{
+                            self.brush_state.radius = settings.brush_radius_min;
                         }
                     }
                     if !delete{
@@ -292,7 +326,9 @@ impl ChunkManager{
                             self.indicator_state.to_str() + 
                             &self.brush_state.id.to_string() + 
                             &" pix:".to_string() +
-                            &self.brush_state.radius.to_string()
+                            &self.brush_state.radius.to_string() +
+                            &" ".to_string() +
+                            &self.brush_material_name()
                             , self.tab_overdose));
                         
                     }else{
@@ -303,15 +339,15 @@ impl ChunkManager{
                 IndicatorState::Brush => {
                     if camera_controller.scroll > 0.0{
                         self.brush_state.radius += 1;
-                        if self.brush_state.radius > 64{
-                            self.brush_state.radius = 64;
+                        if self.brush_state.radius > settings.brush_radius_max{
+                            self.brush_state.radius = settings.brush_radius_max;
                         }
                         self.brush_state.update();
                     }
                     else if camera_controller.scroll < 0.0{
                         self.brush_state.radius -= 1;
-                        if self.brush_state.radius < 1{
-                            self.brush_state.radius = 1;
+                        if self.brush_state.radius < settings.brush_radius_min{
+                            self.brush_state.radius = settings.brush_radius_min;
                         }
                         self.brush_state.update();
                     }
@@ -339,7 +375,9 @@ impl ChunkManager{
                             self.indicator_state.to_str() + 
                             &self.brush_state.id.to_string() + 
                             &" pix:".to_string() +
-                            &self.brush_state.radius.to_string()
+                            &self.brush_state.radius.to_string() +
+                            &" ".to_string() +
+                            &self.brush_material_name()
                             , self.tab_overdose));
                         
                     }else{
@@ -350,14 +388,14 @@ impl ChunkManager{
 
                     if camera_controller.scroll > 0.0{
                         self.model_state.scale *= 2;
-                        if self.model_state.scale > 8{
-                            self.model_state.scale = 8;
+                        if self.model_state.scale > settings.model_scale_max{
+                            self.model_state.scale = settings.model_scale_max;
                         }
                     }
                     else if camera_controller.scroll < 0.0{
                         self.model_state.scale /= 2;
-                        if self.model_state.scale < 1{
-                            self.model_state.scale = 1;
+                        if self.model_state.scale < settings.model_scale_min{
+                            self.model_state.scale = settings.model_scale_min;
                         }
                     }
 
@@ -380,7 +418,12 @@ impl ChunkManager{
                     }
     
                     if !delete{
-                        iced_state.queue_message(UsrIndicator(self.indicator_state.to_str() + &self.model_state.id.to_string(),self.tab_overdose));
+                        iced_state.queue_message(UsrIndicator(
+                            self.indicator_state.to_str() +
+                            &self.model_state.id.to_string() +
+                            &" ".to_string() +
+                            &self.material_list.get(self.model_state.material).name
+                            ,self.tab_overdose));
                         
                     }else{
                         iced_state.queue_message(UsrIndicator(self.indicator_state.to_str() + "_DELETE" , self.tab_overdose));
@@ -394,109 +437,77 @@ impl ChunkManager{
         self.model_state.update();
         self.brush_state.update();
 
-        let mouse_x = mouse_pos_x / *sample_ratio as f64 - texture_size.width as f64 / 2.0 / *sample_ratio as f64;
-        let mouse_y = mouse_pos_y / *sample_ratio as f64 * 3.0 - texture_size.height as f64 / 2.0 / *sample_ratio as f64 * 3.0;
-
-        let camera_mouse_eye = camera.eye + (camera.forward * mouse_y as f32)  + (camera.left * mouse_x as f32);
-        let camera_mouse_target = camera.target + (camera.forward * mouse_y as f32) + (camera.left * mouse_x as f32);
+        let viewport = [texture_size.width as f32, texture_size.height as f32];
+        let ray = camera.screen_ray([mouse_pos_x as f32, mouse_pos_y as f32], viewport, *sample_ratio);
 
-        let mut camera_target_y = (RADIUS_VOXEL/2 - 1 + (RADIUS_VOXEL * 8)) as f32;
-
-        let mut camera_target_z = (camera_target_y - camera_mouse_eye.y) / (camera_mouse_eye.y - camera_mouse_target.y) * (camera_mouse_eye.z - camera_mouse_target.z) + camera_mouse_eye.z;
-        let mut camera_target_x = (camera_target_y - camera_mouse_eye.y) / (camera_mouse_eye.y - camera_mouse_target.y) * (camera_mouse_eye.x - camera_mouse_target.x) + camera_mouse_eye.x;
-
-        let mut voxel_founded = false;
+        let camera_target_x;
+        let camera_target_y;
+        let camera_target_z;
 
         let snap_radius = RADIUS_VOXEL/self.model_state.scale;
 
-        while camera_target_y >= (RADIUS_VOXEL/2 - 1 - (RADIUS_VOXEL * 8)) as f32&& !voxel_founded{
-
-            camera_target_x = (camera_target_y - camera_mouse_eye.y) / (camera_mouse_eye.y - camera_mouse_target.y) * (camera_mouse_eye.x - camera_mouse_target.x) + camera_mouse_eye.x;
-            camera_target_z = (camera_target_y - camera_mouse_eye.y) / (camera_mouse_eye.y - camera_mouse_target.y) * (camera_mouse_eye.z - camera_mouse_target.z) + camera_mouse_eye.z;
-            
-            camera_target_y = camera_target_y - 0.3 as f32;
-
-            self.chunk_list.iter_mut().filter(|c|c.current_type != ChunkType::UsrIndicator).for_each(|c|{
+        match self.raycast(ray.origin, ray.direction, PICK_DISTANCE){
 
-                if !voxel_founded{
+            Some(hit) => {
 
-                    let v_range_x_min = c.position[0] * RADIUS_VOXEL - RADIUS_VOXEL/2;
-                    let v_range_x_max = c.position[0] * RADIUS_VOXEL + RADIUS_VOXEL/2 - 1;
+                let index = self.chunk_hash[&hit.chunk];
+                self.chunk_list[index].is_selected = true;
 
-                    let v_range_y_min = c.position[1] * RADIUS_VOXEL - RADIUS_VOXEL/2;
-                    let v_range_y_max = c.position[1] * RADIUS_VOXEL + RADIUS_VOXEL/2 - 1;
+                let mut target = hit.voxel;
 
-                    let v_range_z_min = c.position[2] * RADIUS_VOXEL - RADIUS_VOXEL/2;
-                    let v_range_z_max = c.position[2] * RADIUS_VOXEL + RADIUS_VOXEL/2 - 1;
-
-                    if camera_target_x >= v_range_x_min as f32 && camera_target_x <= v_range_x_max as f32 &&  
-                        camera_target_y >= v_range_y_min as f32 && camera_target_y <= v_range_y_max as f32 &&  
-                        camera_target_z >= v_range_z_min as f32 && camera_target_z <= v_range_z_max as f32
-                    {
+                if self.indicator_state == IndicatorState::Place && !camera_controller.is_control_pressed{
+                    target[0] += hit.face_normal[0];
+                    target[1] += hit.face_normal[1];
+                    target[2] += hit.face_normal[2];
+                }
 
-                        c.is_selected = true;
-                        
-                        camera_target_z = (camera_target_y - camera_mouse_eye.y) / (camera_mouse_eye.y - camera_mouse_target.y) * (camera_mouse_eye.z - camera_mouse_target.z) + camera_mouse_eye.z;
-                        camera_target_x = (camera_target_y - camera_mouse_eye.y) / (camera_mouse_eye.y - camera_mouse_target.y) * (camera_mouse_eye.x - camera_mouse_target.x) + camera_mouse_eye.x;
-
-                        let mut voxel_normal:[f32;3] = Default::default();
-
-                        match c.position_hash.get(&[
-                            camera_target_x as i32,
-                            camera_target_y as i32,
-                            camera_target_z as i32,
-                        ])
-                        {
-                            Some(usize) => {
-                                voxel_founded = true;
-                                voxel_normal = c.voxel_data[*usize].normal.into();
-                            },
-                            None => {},
-                        }
+                if camera_controller.is_alt_pressed{
+                    for i in 0..3{
+                        target[i] = ((target[i] + snap_radius/2) as f32/ snap_radius as f32).floor() as i32 * snap_radius;
+                    }
+                }
 
-                        if voxel_founded && c.current_type == ChunkType::Default{
+                camera_target_x = target[0] as f32;
+                camera_target_y = target[1] as f32;
+                camera_target_z = target[2] as f32;
+            }
 
-                            if self.indicator_state == IndicatorState::Place && !camera_controller.is_control_pressed{
-                                camera_target_x = camera_target_x + voxel_normal[0];
-                                camera_target_y = camera_target_y + voxel_normal[1];
-                                camera_target_z = camera_target_z + voxel_normal[2];
-                            }
+            None => {
 
+                //nothing under the cursor, fall back to a plane below the camera target
+                let plane_y = camera.target.y - RADIUS_VOXEL as f32;
+                let distance = (plane_y - ray.origin.y) / ray.direction.y;
 
-                            if camera_controller.is_alt_pressed{
-                                let chunk_pos_x = ((camera_target_x as i32 + snap_radius/2) as f32/ snap_radius as f32).floor() as i32;
-                                let chunk_pos_y = ((camera_target_y as i32 + snap_radius/2) as f32/ snap_radius as f32).floor() as i32;
-                                let chunk_pos_z = ((camera_target_z as i32 + snap_radius/2) as f32/ snap_radius as f32).floor() as i32;
-                
-                                camera_target_x = (chunk_pos_x * snap_radius) as f32;
-                                camera_target_y = (chunk_pos_y * snap_radius) as f32;
-                                camera_target_z = (chunk_pos_z * snap_radius) as f32;
-                                
-                            }
-                        }
-                    }
-                }
-            });
-        }
+                //a ray going level or up never meets the plane, the indicator stays where it was
+                if ray.direction.y < -1e-4 && distance > 0.0{
 
-        if !voxel_founded{
+                    let hit = ray.at(distance);
+                    let mut x = hit.x;
+                    let mut z = hit.z;
 
-            camera_target_y = camera.target.y - RADIUS_VOXEL as f32;
-        
-            camera_target_x = (camera_target_y - camera_mouse_eye.y) / (camera_mouse_eye.y - camera_mouse_target.y) * (camera_mouse_eye.x - camera_mouse_target.x) + camera_mouse_eye.x;
-            camera_target_z = (camera_target_y - camera_mouse_eye.y) / (camera_mouse_eye.y - camera_mouse_target.y) * (camera_mouse_eye.z - camera_mouse_target.z) + camera_mouse_eye.z;
+                    if camera_controller.is_alt_pressed{
 
-            if camera_controller.is_alt_pressed{
+                        let chunk_pos_x = ((x as i32 + snap_radius/2) as f32/ snap_radius as f32).floor() as i32;
+                        let chunk_pos_z = ((z as i32 + snap_radius/2) as f32/ snap_radius as f32).floor() as i32;
 
-                let chunk_pos_x = ((camera_target_x as i32 + snap_radius/2) as f32/ snap_radius as f32).floor() as i32;
-                let chunk_pos_z = ((camera_target_z as i32 + snap_radius/2) as f32/ snap_radius as f32).floor() as i32;
+                        x = (chunk_pos_x * snap_radius) as f32;
+                        z = (chunk_pos_z * snap_radius) as f32;
 
-                camera_target_x = (chunk_pos_x * snap_radius) as f32;
-                camera_target_z = (chunk_pos_z * snap_radius) as f32;
+                    }
 
+                    camera_target_x = x;
+                    camera_target_y = plane_y;
+                    camera_target_z = z;
+                }
+                else{
+                    camera_target_x = self.pervious_target[0];
+                    camera_target_y = self.pervious_target[1];
+                    camera_target_z = self.pervious_target[2];
+                }
             }
         }
-           
+
+        self.pervious_target = [camera_target_x,camera_target_y,camera_target_z];
 
         //iced_state.queue_message(Coordinate([camera_target_x,camera_target_y,camera_target_z]));
 
@@ -551,6 +562,7 @@ impl ChunkManager{
                     device,
                     ChunkType::UsrIndicator,
                     indicator_brush_model_id,
+                    DEFAULT_MATERIAL,
                     iced_state
                 );
 
@@ -595,6 +607,7 @@ impl ChunkManager{
                     device,
                     ChunkType::UsrIndicator,
                     id,
+                    DEFAULT_MATERIAL,
                     iced_state
                 );
 
@@ -659,6 +672,12 @@ impl ChunkManager{
 
                 + &id.to_string()+ &' '.to_string();
 
+                let material = match &self.indicator_state{
+                    IndicatorState::Brush => self.brush_material_name(),
+                    _ => self.material_list.get(self.model_state.material).name.to_owned(),
+                };
+                let t = t + &material;
+
                 if head == "/draw"{
                     iced_state.queue_message(shell::Message::CommandChanged(t.to_owned()));
                     iced_state.queue_message(shell::Message::Parse);
@@ -734,51 +753,97 @@ impl ChunkManager{
         
     }
 
-    pub fn draw(&mut self,first:[i32;3],last:[i32;3],color:[f32;4],id:i32,device:&wgpu::Device){
-
-        let chunk_pos_x_first = ((first[0] + RADIUS_VOXEL/2) as f32/ RADIUS_VOXEL as f32).floor() as i32;
-        let chunk_pos_x_last= ((last[0] + RADIUS_VOXEL/2) as f32/ RADIUS_VOXEL as f32).floor() as i32;
+    pub fn brush_material_name(&self) -> String{
+        match self.brush_state.material{
+            Some(material) => self.material_list.get(material).name.to_owned(),
+            None => brush_list::default_material(self.brush_state.id).to_owned(),
+        }
+    }
 
-        let chunk_pos_y_first = ((first[1] + RADIUS_VOXEL/2) as f32/ RADIUS_VOXEL as f32).floor() as i32;
-        let chunk_pos_y_last= ((last[1] + RADIUS_VOXEL/2) as f32/ RADIUS_VOXEL as f32).floor() as i32;
-        
-        let chunk_pos_z_first = ((first[2] + RADIUS_VOXEL/2) as f32/ RADIUS_VOXEL as f32).floor() as i32;
-        let chunk_pos_z_last= ((last[2] + RADIUS_VOXEL/2) as f32/ RADIUS_VOXEL as f32).floor() as i32;
+    pub fn draw(&mut self,first:[i32;3],last:[i32;3],color:[f32;4],id:i32,material:usize,device:&wgpu::Device){
 
-        for xx in chunk_pos_x_first..chunk_pos_x_last + 1{
-            for yy in chunk_pos_y_first..chunk_pos_y_last + 1{
-                for zz in chunk_pos_z_first..chunk_pos_z_last + 1{
+        let casts_light = self.material_list.get(material).casts_light;
+        self.wake_list.push((first,last));
+        self.restore_range(first, last, device);
 
-                    self.chunk_list.iter_mut().filter(|c|c.current_type == ChunkType::Default).for_each(|c|{
-                        if c.position[0] == xx && c.position[1] == yy && c.position[2] == zz{
+        //the brush only recolors voxels already in the box
+        let mut bucket:HashMap<[i32;3],Vec<([i32;3],[f32;4])>> = Default::default();
+        self.iter_box(first, last).for_each(|v|{
+            let position = v.grid_position();
+            if let Some(c) = brush_list::parse_draw(position[0],position[1],position[2],first,last,color,id){
+                bucket.entry(chunk_position(position)).or_default().push((position,c));
+            }
+        });
 
-                            let mut x_first = xx * RADIUS_VOXEL - RADIUS_VOXEL/2;
-                            let mut x_last =  xx * RADIUS_VOXEL + RADIUS_VOXEL/2 - 1;
+        bucket.into_iter().for_each(|(position,paint_list)|{
+            let index = self.chunk_hash[&position];
+            self.chunk_list[index].draw(paint_list, device, material, casts_light, &mut self.light_manager);
+        });
+    }
+    //place prebuilt voxels, replacing whatever already sits at their positions
+    pub fn place_instances(&mut self,instance_list:Vec<Instance>,device:&wgpu::Device){
+
+        let mut bucket:HashMap<[i32;3],Vec<Instance>> = Default::default();
+        let mut first = [i32::MAX;3];
+        let mut last = [i32::MIN;3];
+
+        instance_list.into_iter().for_each(|ins|{
+            let position = [ins.position[0] as i32,ins.position[1] as i32,ins.position[2] as i32];
+            for i in 0..3{
+                first[i] = first[i].min(position[i]);
+                last[i] = last[i].max(position[i]);
+            }
+            bucket.entry(chunk_position(position)).or_default().push(ins);
+        });
 
-                            let mut y_first = yy * RADIUS_VOXEL - RADIUS_VOXEL/2;
-                            let mut y_last =  yy * RADIUS_VOXEL + RADIUS_VOXEL/2 - 1;
+        if bucket.is_empty(){
+            return;
+        }
+        self.wake_list.push((first,last));
+        bucket.keys().copied().collect::<Vec<_>>().into_iter().for_each(|p|self.restore_chunk(p, device));
+
+        bucket.into_iter().for_each(|(position,instance_list)|{
+            match self.chunk_hash.get(&position){
+                Some(index) => self.chunk_list[*index].place_instances(instance_list, &self.material_list, &mut self.light_manager, device),
+                None => {
+                    let mut chunk = Chunk::empty(position[0], position[1], position[2], true, device, ChunkType::Default);
+                    chunk.place_instances(instance_list, &self.material_list, &mut self.light_manager, device);
+                    self.chunk_list.push(chunk);
+                }
+            }
+        });
+        self.rebuild_chunk_hash();
+    }
 
-                            let mut z_first = zz * RADIUS_VOXEL - RADIUS_VOXEL/2;
-                            let mut z_last =  zz * RADIUS_VOXEL + RADIUS_VOXEL/2 - 1;
+    //removes single voxels, freeing the lights they own
+    pub fn remove_voxels(&mut self,position_list:Vec<[i32;3]>,device:&wgpu::Device){
 
-                            if x_first < first[0]   {x_first = first[0]}
-                            if x_last  > last[0]    {x_last = last[0]}
-                            if y_first < first[1]   {y_first = first[1]}
-                            if y_last  > last[1]    {y_last = last[1]}
-                            if z_first < first[2]   {z_first = first[2]}
-                            if z_last  > last[2]    {z_last = last[2]}
+        let mut bucket:HashMap<[i32;3],Vec<[i32;3]>> = Default::default();
 
-                            let c_first = [x_first,y_first,z_first];
-                            let c_last = [x_last,y_last,z_last ];
+        position_list.into_iter().for_each(|position|{
+            bucket.entry(chunk_position(position)).or_default().push(position);
+        });
 
-                            c.draw(c_first, c_last, color, device, id , &mut self.point_light_list)
-                        }
-                    })
-                }
+        bucket.into_iter().for_each(|(position,position_list)|{
+            self.restore_chunk(position, device);
+            if let Some(index) = self.chunk_hash.get(&position){
+                self.chunk_list[*index].remove_voxels(position_list, &mut self.light_manager, device);
             }
-        }
+        });
     }
-    pub fn place(&mut self,first:[i32;3],last:[i32;3],color:[f32;4],delete:bool,device:&wgpu::Device,chunk_type: ChunkType,id:i32,iced_state: &mut program::State<shell::Controls>){
+
+    pub fn place(&mut self,first:[i32;3],last:[i32;3],color:[f32;4],delete:bool,device:&wgpu::Device,chunk_type: ChunkType,id:i32,material:usize,iced_state: &mut program::State<shell::Controls>){
+
+        if chunk_type == ChunkType::Default{
+            self.wake_list.push((first,last));
+            self.restore_range(first, last, device);
+        }
+
+        //plants are grown once for the whole box, the chunks only pick their share
+        let plant_hash = match vegetation::Plant::from_model(id){
+            Some(plant) if !delete => Some(vegetation::grow(plant, vegetation::hash(0, [first[0], 0, first[2]]), first, last, material, &self.material_list)),
+            _ => None,
+        };
 
         //chunk offset
         let c_first = first;
@@ -821,23 +886,147 @@ impl ChunkManager{
 
                     self.chunk_list.iter_mut().filter(|c| c.current_type == chunk_type).for_each(|c|{
                         if c.position[0] == xx && c.position[1] == yy && c.position[2] == zz{
-                            c.place(c_first, c_last,first,last,color, delete , device, id);
+                            c.place(c_first, c_last,first,last,color, delete , device, id, material, plant_hash.as_ref(), &self.material_list, &mut self.light_manager);
                             chunk_modified_flag = true;
                         }
                     });
                     
                     if !chunk_modified_flag{
                         let mut chunk = Chunk::empty(xx, yy, zz, true, device, chunk_type);
-                        chunk.place(c_first, c_last,first,last,color, delete, device, id);
+                        chunk.place(c_first, c_last,first,last,color, delete, device, id, material, plant_hash.as_ref(), &self.material_list, &mut self.light_manager);
                         self.chunk_list.push(chunk);
                     }
                 }
             }
         }
+        self.rebuild_chunk_hash();
+    }
+
+    pub fn rebuild_chunk_hash(&mut self){
+        self.chunk_hash = self.chunk_list.iter().enumerate()
+            .filter(|(_,c)|c.current_type == ChunkType::Default)
+            .map(|(i,c)|(c.position,i))
+            .collect();
+    }
+
+    //drops every default chunk together with all user and voxel lights, and the jobs still building into them
+    pub fn clear_world(&mut self,job_system:&mut JobSystem){
+        job_system.cancel_all();
+        self.chunk_list.retain(|c|c.current_type != ChunkType::Default);
+        self.light_manager.clear();
+        self.streamer.clear();
+        self.view_hash.clear();
+        self.rebuild_chunk_hash();
+    }
+
+    //keeps the chunks around center resident and packs away the least recently seen ones over budget
+    pub fn stream(&mut self,center:[f32;3],device:&wgpu::Device) -> Result<(),String>{
+
+        if !self.streamer.enabled{
+            return Ok(());
+        }
+
+        let center = chunk_position([center[0].floor() as i32,center[1].floor() as i32,center[2].floor() as i32]);
+        let resident_list:Vec<([i32;3],usize)> = self.chunk_list.iter()
+            .filter(|c|c.current_type == ChunkType::Default)
+            .map(|c|(c.position,c.voxel_data.len()))
+            .collect();
+
+        let plan = self.streamer.plan(center, &resident_list);
+
+        for position in plan.evict_list{
+            self.evict_chunk(position)?;
+        }
+        for position in plan.reload_list{
+            self.restore_chunk(position, device);
+        }
+        Ok(())
+    }
+
+    //picks a detail level for every default chunk, building at most a few missing levels per frame
+    pub fn update_lod(&mut self,center:[f32;3],sample_ratio:f32,device:&wgpu::Device){
+
+        let settings = &self.lod_settings;
+        let mut build_count = 0;
+
+        self.chunk_list.iter_mut().filter(|c|c.current_type == ChunkType::Default).for_each(|c|{
+
+            let distance = ((0..3).map(|i|(c.position[i] * RADIUS_VOXEL) as f32 - center[i]).map(|d|d * d).sum::<f32>()).sqrt();
+            let level = settings.select(c.lod_level, lod::effective_distance(distance, sample_ratio));
+
+            if level == 0 || c.has_lod(level){
+                c.lod_level = level;
+            }
+            else if build_count < MAX_LOD_BUILD_PER_FRAME{
+                c.build_lod(level, settings.color_mode, device);
+                c.lod_level = level;
+                build_count += 1;
+            }
+        });
+    }
+
+    //writes a default chunk out and frees its buffer and the lights it owns
+    pub fn evict_chunk(&mut self,position:[i32;3]) -> Result<(),String>{
+
+        let index = match self.chunk_hash.get(&position){
+            Some(index) => *index,
+            None => return Ok(()),
+        };
+        self.streamer.put(&self.chunk_list[index])?;
+
+        let chunk = self.chunk_list.remove(index);
+        chunk.light_hash.values().for_each(|handle|{
+            self.light_manager.remove(*handle);
+        });
+        self.rebuild_chunk_hash();
+        Ok(())
+    }
+
+    pub fn restore_chunk(&mut self,position:[i32;3],device:&wgpu::Device){
+
+        if !self.streamer.is_evicted(position){
+            return;
+        }
+        match self.streamer.take(position){
+            Ok(instance_list) => {
+                let mut chunk = Chunk::empty(position[0], position[1], position[2], true, device, ChunkType::Default);
+                chunk.place_instances(instance_list, &self.material_list, &mut self.light_manager, device);
+                self.chunk_list.push(chunk);
+                self.rebuild_chunk_hash();
+            }
+            Err(e) => log::warn!("failed to restore chunk {:?}: {}", position, e),
+        }
+    }
+
+    //edits need the real voxels, so evicted chunks touching the box come back first
+    fn restore_range(&mut self,first:[i32;3],last:[i32;3],device:&wgpu::Device){
+        let c_first = chunk_position(first);
+        let c_last = chunk_position(last);
+        self.streamer.evicted_list().into_iter()
+            .filter(|p|(0..3).all(|i|p[i] >= c_first[i].min(c_last[i]) && p[i] <= c_first[i].max(c_last[i])))
+            .for_each(|p|self.restore_chunk(p, device));
+    }
+
+    pub fn owned_lights(&self) -> HashSet<LightHandle>{
+        self.chunk_list.iter().flat_map(|c|c.light_hash.values().copied()).collect()
+    }
+
+    //indicator chunks live for a single frame
+    pub fn clear_indicators(&mut self){
+        self.chunk_list.retain(|c|c.current_type != ChunkType::UsrIndicator);
+        self.rebuild_chunk_hash();
     }
 }
 
 
+pub fn chunk_position(position:[i32;3]) -> [i32;3]{
+    [
+        (position[0] + RADIUS_VOXEL/2).div_euclid(RADIUS_VOXEL),
+        (position[1] + RADIUS_VOXEL/2).div_euclid(RADIUS_VOXEL),
+        (position[2] + RADIUS_VOXEL/2).div_euclid(RADIUS_VOXEL),
+    ]
+}
+
 pub struct Chunk{
     pub position:[i32;3],
     pub voxel_data:Vec<Instance>,
@@ -849,6 +1038,13 @@ pub struct Chunk{
     pub need_update: bool,
     pub current_type :ChunkType,
     pub position_hash: HashMap<[i32;3],usize>,
+    //lights owned by voxels of this chunk, freed when the voxel goes away
+    pub light_hash: HashMap<[i32;3],LightHandle>,
+
+    //0 draws every voxel, see lod
+    pub lod_level:usize,
+    //downsampled buffers by level, dropped whenever the voxels change
+    lod_cache:HashMap<usize,(wgpu::Buffer,u32)>,
 }
 
 impl Chunk{
@@ -879,36 +1075,73 @@ impl Chunk{
             need_update:false,
             current_type:chunk_type,
             position_hash:Default::default(),
+            light_hash:Default::default(),
+            lod_level:0,
+            lod_cache:Default::default(),
         }
     }
 
-    pub fn draw(&mut self,first:[i32;3],last:[i32;3],color:[f32;4],device:&wgpu::Device,id: i32, point_light_list:&mut Vec<Light>){
-
-        self.voxel_data.iter_mut().for_each(|v|{
-
-            if v.position[0] >= first[0] as f32&& v.position[0] <= last[0] as f32{
-                if v.position[1] >= first[1] as f32&& v.position[1] <= last[1] as f32{
-                    if v.position[2] >= first[2] as f32&& v.position[2] <= last[2] as f32{
-
-                        let color = brush_list::parse_draw(v.position[0] as i32,v.position[1] as i32,v.position[2] as i32,first,last,color,id);
-                        match color{
-                            Some(c) =>{
-                                v.color = cgmath::vec4(c[0], c[1], c[2], c[3]);
-                                if id == 1{
-                                    v.current_type = VoxelType::Fire;
-                                    for i in 2..512{
-                                        if point_light_list[i].color[3] == 0.0{
-                                            point_light_list[i].color = v.color.into();
-                                            point_light_list[i].position = v.position.into();
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            _ =>{}
-                        }
+    //the space the chunk can draw into, for frustum culling
+    pub fn bounds(&self) -> ([f32;3],[f32;3]){
+        let half = (RADIUS_VOXEL / 2) as f32 + CULL_MARGIN;
+        let center = self.position.map(|p|(p * RADIUS_VOXEL) as f32);
+        (center.map(|c|c - half), center.map(|c|c + half))
+    }
+
+    //the buffer and instance count to draw at the current level
+    pub fn draw_buffer(&self) -> (&wgpu::Buffer,u32){
+        match self.lod_cache.get(&self.lod_level){
+            Some((buffer,len)) => (buffer,*len),
+            None => (&self.buffer_data,self.instance_len),
+        }
+    }
+
+    pub fn has_lod(&self,level:usize) -> bool{
+        self.lod_cache.contains_key(&level)
+    }
+
+    pub fn build_lod(&mut self,level:usize,color_mode:lod::ColorMode,device:&wgpu::Device){
+
+        let instance_data = lod::downsample(&self.voxel_data, lod::LOD_SCALE_LIST[level - 1], color_mode);
+        let buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+            label: Some("Lod Instance Buffer"),
+            contents: bytemuck::cast_slice(&instance_data),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        self.lod_cache.insert(level,(buffer,instance_data.len() as u32));
+    }
+
+    pub fn draw(&mut self,paint_list:Vec<([i32;3],[f32;4])>,device:&wgpu::Device,material:usize,casts_light:bool, light_manager:&mut LightManager){
+
+        paint_list.into_iter().for_each(|(position,c)|{
+
+            let index = match self.position_hash.get(&position){
+                Some(index) => *index,
+                None => return,
+            };
+            let v = &mut self.voxel_data[index];
+            v.color = cgmath::vec4(c[0], c[1], c[2], c[3]);
+            v.material = material;
+
+            let light = Light::point(v.position.into(), [c[0], c[1], c[2]], c[3], DEFAULT_LIGHT_RADIUS);
+
+            match (casts_light, self.light_hash.get(&position)){
+                //repainting a lit voxel only recolors its light
+                (true, Some(handle)) if light.is_active() => {
+                    if let Some(l) = light_manager.get_mut(*handle){
+                        *l = light;
+                    }
+                }
+                (true, None) => {
+                    if let Some(handle) = light_manager.add(light){
+                        self.light_hash.insert(position, handle);
                     }
                 }
+                (_, Some(_)) => {
+                    light_manager.remove(self.light_hash.remove(&position).unwrap());
+                }
+                (false, None) => {}
             }
         });
 
@@ -919,13 +1152,82 @@ impl Chunk{
             contents: bytemuck::cast_slice(&self.instance_data),
             usage: wgpu::BufferUsages::VERTEX|wgpu::BufferUsages::COPY_DST,
         });
+        self.lod_cache.clear();
         self.current_type = ChunkType::Default;
         self.instance_len = self.instance_data.len() as u32;
 
     }
 
 
-    pub fn place(&mut self,first:[i32;3],last:[i32;3],place_first:[i32;3],place_last:[i32;3],color:[f32;4],delete:bool,device:&wgpu::Device,id:i32){
+    pub fn place_instances(&mut self,instance_list:Vec<Instance>,material_list:&MaterialList,light_manager:&mut LightManager,device:&wgpu::Device){
+
+        instance_list.into_iter().for_each(|ins|{
+            let position = [ins.position[0] as i32,ins.position[1] as i32,ins.position[2] as i32];
+            if let Some(handle) = self.light_hash.remove(&position){
+                light_manager.remove(handle);
+            }
+            if material_list.get(ins.material).casts_light{
+                let light = Light::point(ins.position.into(), [ins.color[0], ins.color[1], ins.color[2]], ins.color[3], DEFAULT_LIGHT_RADIUS);
+                if let Some(handle) = light_manager.add(light){
+                    self.light_hash.insert(position, handle);
+                }
+            }
+            match self.position_hash.get(&position){
+                Some(index) => {
+                    self.voxel_data[*index] = ins;
+                },
+                None => {
+                    self.voxel_data.push(ins);
+                    self.position_hash.insert(position,self.voxel_data.len()-1);
+                },
+            }
+        });
+
+        self.instance_data = self.voxel_data.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        self.buffer_data = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&self.instance_data),
+            usage: wgpu::BufferUsages::VERTEX|wgpu::BufferUsages::COPY_DST,
+        });
+        self.lod_cache.clear();
+        self.instance_len = self.instance_data.len() as u32;
+    }
+
+    pub fn remove_voxels(&mut self,position_list:Vec<[i32;3]>,light_manager:&mut LightManager,device:&wgpu::Device){
+
+        let position_set:HashSet<[i32;3]> = position_list.into_iter().collect();
+
+        position_set.iter().for_each(|p|{
+            if let Some(handle) = self.light_hash.remove(p){
+                light_manager.remove(handle);
+            }
+        });
+
+        self.voxel_data.retain(|v|!position_set.contains(&v.grid_position()));
+
+        self.position_hash = self.voxel_data.iter().enumerate().map(|(i,v)|(v.grid_position(),i)).collect();
+
+        self.instance_data = self.voxel_data.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        self.buffer_data = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&self.instance_data),
+            usage: wgpu::BufferUsages::VERTEX|wgpu::BufferUsages::COPY_DST,
+        });
+        self.lod_cache.clear();
+        self.instance_len = self.instance_data.len() as u32;
+    }
+
+    pub fn place(&mut self,first:[i32;3],last:[i32;3],place_first:[i32;3],place_last:[i32;3],color:[f32;4],delete:bool,device:&wgpu::Device,id:i32,material:usize,plant_hash:Option<&HashMap<[i32;3],Instance>>,material_list:&MaterialList,light_manager:&mut LightManager){
+
+        self.light_hash.retain(|p,handle|{
+            let inside = (0..3).all(|i| p[i] >= first[i] && p[i] <= last[i]);
+            if inside{
+                light_manager.remove(*handle);
+            }
+            !inside
+        });
 
         let len = self.voxel_data.len();
         self.voxel_data
@@ -941,8 +1243,8 @@ impl Chunk{
         if retained_len != len{
 
             let mut hash_map:HashMap<[i32;3],usize> = Default::default();
-            self.voxel_data.iter().for_each(|v|{
-                hash_map.insert([v.position[0] as i32,v.position[1] as i32,v.position[2] as i32], self.voxel_data.len()-1);
+            self.voxel_data.iter().enumerate().for_each(|(i,v)|{
+                hash_map.insert([v.position[0] as i32,v.position[1] as i32,v.position[2] as i32], i);
             });
             self.position_hash = hash_map;
             //hash rebuilt
@@ -956,10 +1258,22 @@ impl Chunk{
 
                         
 
-                            let instance = model_list::parse_place(x, y, z, place_first,place_last, color, id);
+                            let instance = match plant_hash{
+                                Some(plant_hash) => plant_hash.get(&[x,y,z]).copied(),
+                                None => model_list::parse_place(x, y, z, place_first,place_last, color, id).map(|mut ins|{
+                                    ins.material = material;
+                                    ins
+                                }),
+                            };
 
                             match instance{
                                 Some(ins) => {
+                                    if material_list.get(ins.material).casts_light{
+                                        let light = Light::point(ins.position.into(), [ins.color[0], ins.color[1], ins.color[2]], ins.color[3], DEFAULT_LIGHT_RADIUS);
+                                        if let Some(handle) = light_manager.add(light){
+                                            self.light_hash.insert([x,y,z], handle);
+                                        }
+                                    }
                                     self.voxel_data.push(ins);
                                     self.position_hash.insert([x,y,z],self.voxel_data.len()-1);
                                 }
@@ -981,6 +1295,7 @@ impl Chunk{
             contents: bytemuck::cast_slice(&self.instance_data),
             usage: wgpu::BufferUsages::VERTEX|wgpu::BufferUsages::COPY_DST,
         });
+        self.lod_cache.clear();
 
         self.instance_len = self.instance_data.len() as u32;
 
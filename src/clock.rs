@@ -0,0 +1,223 @@
+use crate::light::LightManager;
+
+//real seconds for one full day at speed 1
+pub const DEFAULT_DAY_LENGTH: f64 = 1200.0;
+
+//highest point of the sun at noon, degrees
+const MAX_ELEVATION: f32 = 70.0;
+
+const SUNRISE: f64 = 6.0;
+const SUNSET: f64 = 18.0;
+
+const NIGHT_SKY: [f32; 3] = [0.01, 0.01, 0.02];
+const HORIZON_SKY: [f32; 3] = [0.55, 0.3, 0.2];
+const DAY_SKY: [f32; 3] = [0.35, 0.55, 0.85];
+
+const HORIZON_TINT: [f32; 3] = [1.0, 0.55, 0.3];
+const NOON_TINT: [f32; 3] = [1.0, 0.97, 0.92];
+
+//share of the ambient term that survives the night
+const NIGHT_AMBIENT: f32 = 0.25;
+
+#[derive(Copy, Clone)]
+pub struct WorldClock {
+    //hours in [0, 24)
+    pub time_of_day: f64,
+    //time of day at the previous tick, rendering blends between the two
+    previous_time: f64,
+    pub day_length: f64,
+    pub speed: f64,
+    pub paused: bool,
+    //off while the sun is placed by hand with /sun
+    pub drives_sun: bool,
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+//"hh:mm" into hours
+pub fn parse_time(s: &str) -> Option<f64> {
+    let (h, m) = s.split_once(':')?;
+    let h = h.parse::<u32>().ok()?;
+    let m = m.parse::<u32>().ok()?;
+    if h > 23 || m > 59 {
+        return None;
+    }
+    Some(h as f64 + m as f64 / 60.0)
+}
+
+impl WorldClock {
+    pub fn new() -> Self {
+        Self { time_of_day: 10.0, previous_time: 10.0, day_length: DEFAULT_DAY_LENGTH, speed: 1.0, paused: false, drives_sun: true }
+    }
+
+    pub fn advance(&mut self, dt: f64) {
+        self.previous_time = self.time_of_day;
+        if self.paused {
+            return;
+        }
+        self.time_of_day = (self.time_of_day + dt * self.speed * 24.0 / self.day_length).rem_euclid(24.0);
+    }
+
+    //jumps such as /time set are not blended
+    pub fn set_time(&mut self, time: f64) {
+        self.time_of_day = time.rem_euclid(24.0);
+        self.previous_time = self.time_of_day;
+    }
+
+    //the clock as seen alpha of the way from the previous tick to the current one
+    pub fn interpolate(&self, alpha: f64) -> Self {
+        let mut delta = self.time_of_day - self.previous_time;
+        if delta < -12.0 {
+            delta += 24.0;
+        }
+        Self { time_of_day: (self.previous_time + delta * alpha).rem_euclid(24.0), ..*self }
+    }
+
+    pub fn time_string(&self) -> String {
+        let minutes = (self.time_of_day * 60.0) as u32;
+        format!("{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+    }
+
+    //(azimuth, elevation) in degrees, the sun rises at +x and sets at -x
+    pub fn sun_angles(&self) -> (f32, f32) {
+        let arc = ((self.time_of_day - SUNRISE) / (SUNSET - SUNRISE)) as f32 * 180.0;
+        let azimuth = 90.0 + arc;
+        let elevation = arc.to_radians().sin() * MAX_ELEVATION;
+        (azimuth, elevation)
+    }
+
+    //0 at night, 1 once the sun is well above the horizon
+    pub fn daylight(&self) -> f32 {
+        smoothstep(-6.0, 10.0, self.sun_angles().1)
+    }
+
+    pub fn sky_color(&self) -> [f32; 3] {
+        let elevation = self.sun_angles().1;
+        let dusk = smoothstep(-6.0, 2.0, elevation);
+        let day = smoothstep(2.0, 20.0, elevation);
+        mix(mix(NIGHT_SKY, HORIZON_SKY, dusk), DAY_SKY, day)
+    }
+
+    pub fn sun_tint(&self) -> [f32; 3] {
+        mix(HORIZON_TINT, NOON_TINT, smoothstep(0.0, 35.0, self.sun_angles().1))
+    }
+
+    pub fn apply(&self, light_manager: &mut LightManager) {
+        if self.drives_sun {
+            let (azimuth, elevation) = self.sun_angles();
+            light_manager.sun_azimuth = azimuth;
+            light_manager.sun_elevation = elevation;
+            light_manager.sun_tint = self.sun_tint();
+            light_manager.sun_intensity = self.daylight();
+            light_manager.ambient_scale = NIGHT_AMBIENT + (1.0 - NIGHT_AMBIENT) * self.daylight();
+        } else {
+            light_manager.sun_tint = [1.0, 1.0, 1.0];
+            light_manager.sun_intensity = 1.0;
+            light_manager.ambient_scale = 1.0;
+        }
+    }
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sun_position(clock: &WorldClock) -> [f32; 3] {
+        let mut light_manager = LightManager::new();
+        clock.apply(&mut light_manager);
+        light_manager.update_sun();
+        light_manager.sun().position
+    }
+
+    #[test]
+    fn advance_wraps_past_midnight() {
+        let mut clock = WorldClock::new();
+        clock.set_time(23.5);
+        //one hour at speed 1
+        clock.advance(DEFAULT_DAY_LENGTH / 24.0);
+        assert!((clock.time_of_day - 0.5).abs() < 1e-9);
+        assert_eq!(clock.time_string(), "00:30");
+    }
+
+    #[test]
+    fn set_time_wraps_both_ways() {
+        let mut clock = WorldClock::new();
+        clock.set_time(25.0);
+        assert!((clock.time_of_day - 1.0).abs() < 1e-9);
+        clock.set_time(-2.0);
+        assert!((clock.time_of_day - 22.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn interpolate_takes_the_short_way_over_midnight() {
+        let mut clock = WorldClock::new();
+        clock.set_time(23.0);
+        clock.advance(DEFAULT_DAY_LENGTH / 12.0);
+        assert!((clock.time_of_day - 1.0).abs() < 1e-9);
+        let half = clock.interpolate(0.5);
+        assert!(half.time_of_day.abs() < 1e-9 || (half.time_of_day - 24.0).abs() < 1e-9);
+        let quarter = clock.interpolate(0.25);
+        assert!((quarter.time_of_day - 23.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sun_is_overhead_at_noon() {
+        let mut clock = WorldClock::new();
+        clock.set_time(12.0);
+        let (azimuth, elevation) = clock.sun_angles();
+        assert!((azimuth - 180.0).abs() < 1e-3);
+        assert!((elevation - MAX_ELEVATION).abs() < 1e-3);
+        assert!((clock.daylight() - 1.0).abs() < 1e-6);
+
+        //azimuth 180 puts the sun straight towards -z
+        let p = sun_position(&clock);
+        assert!(p[0].abs() < 1.0);
+        assert!(p[1] > 0.0);
+        assert!(p[2] < 0.0);
+        assert!((p[1] / -p[2] - MAX_ELEVATION.to_radians().tan()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn sun_is_below_the_horizon_at_midnight() {
+        let mut clock = WorldClock::new();
+        clock.set_time(0.0);
+        let (azimuth, elevation) = clock.sun_angles();
+        assert!(azimuth.abs() < 1e-3);
+        assert!((elevation + MAX_ELEVATION).abs() < 1e-3);
+        assert_eq!(clock.daylight(), 0.0);
+
+        let mut light_manager = LightManager::new();
+        clock.apply(&mut light_manager);
+        assert_eq!(light_manager.sun_intensity, 0.0);
+        assert_eq!(light_manager.ambient_scale, NIGHT_AMBIENT);
+
+        let p = sun_position(&clock);
+        assert!(p[0].abs() < 1.0);
+        assert!(p[1] < 0.0);
+        assert!(p[2] > 0.0);
+    }
+
+    #[test]
+    fn hand_placed_sun_is_left_alone() {
+        let mut clock = WorldClock::new();
+        clock.set_time(0.0);
+        clock.drives_sun = false;
+        let mut light_manager = LightManager::new();
+        clock.apply(&mut light_manager);
+        assert_eq!(light_manager.sun_intensity, 1.0);
+        assert_eq!(light_manager.sun_elevation, 26.6);
+    }
+}
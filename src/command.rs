@@ -1,7 +1,25 @@
-use crate::State;
+use crate::{Instance, State};
+use crate::brush_list;
+use crate::camera::{Projection, MAX_FOV};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{camera_path, capture};
+use crate::clock;
 use crate::chunk::ChunkType;
+use crate::font_list;
+use crate::import;
+use crate::input;
+use crate::job;
+use crate::lod;
+use crate::logic_list;
+use crate::light::{Light, DEFAULT_LIGHT_RADIUS};
+use crate::material_list::DEFAULT_MATERIAL;
+use crate::model_list;
+use crate::query::VoxelQuery;
+use crate::settings;
 use crate::shell;
-use shell::Message::{ServerLog,ChatMessage};
+use crate::terrain;
+use crate::world;
+use shell::Message::{ServerLog,ChatMessage,SettingsToggle};
 pub struct Descriptor{
     pub text: String,
 }
@@ -17,178 +35,925 @@ impl Descriptor{
         
         let mut s = String::from(&self.text);
 
-        let v: Vec<&str> = s.split(" ").collect();
+        let v: Vec<&str> = self.text.split(" ").collect();
 
         let ch = v[0].chars().nth(0);
 
         if ch == Some('/') {
-            let mut i = 1;
-
             match v[0] as &str{
 
                 "/place" =>{
 
-                    let mut first = [0,0,0];
-                    let mut last = [0,0,0];
-                    let mut color = [0.0,0.0,0.0,0.0];
-                    let mut id = 0;
-                    let delete = false;
+                    match parse_box(&v){
+                        Err(e) => s = e,
+                        Ok((first, last, color, id)) => {
 
-                    if v.len() < 12 {
-                        s = String::from("Insufficient args");
+                            s = "Model placed at ".to_owned() + &box_text(first, last, id);
+
+                            match parse_material(state, &v, 12, state.chunk_manager.model_state.material){
+                                //big boxes are cleared now and filled by the workers
+                                Some(material) if job::volume(first, last) > job::PLACE_JOB_VOLUME => {
+                                    state.chunk_manager.place(first, last, color, true, &state.device, ChunkType::Default,id,material, &mut state.iced_state);
+                                    let step_list = job::place_job(first, last, color, id, material, &state.chunk_manager.material_list);
+                                    state.job_system.submit("/place", step_list);
+                                    s = s + &"(in background)".to_owned();
+                                }
+                                Some(material) => state.chunk_manager.place(first, last, color, false, &state.device, ChunkType::Default,id,material, &mut state.iced_state),
+                                None => s = String::from("Unknown material"),
+                            }
+                        }
+                    }
+                }
+
+                "/delete" =>{
+
+                    match parse_box(&v){
+                        Err(e) => s = e,
+                        Ok((first, last, color, id)) => {
+                            s = "Model deleted at ".to_owned() + &box_text(first, last, id);
+                            state.chunk_manager.place(first, last, color, true, &state.device, ChunkType::Default, id, DEFAULT_MATERIAL,&mut state.iced_state);
+                        }
+                    }
+                }
+
+                "/draw" =>{
+
+                    match parse_box(&v){
+                        Err(e) => s = e,
+                        Ok((first, last, color, id)) => {
+                            s = "Model drawn at ".to_owned() + &box_text(first, last, id);
+                            let fallback = state.chunk_manager.material_list.find(brush_list::default_material(id)).unwrap_or(DEFAULT_MATERIAL);
+                            match parse_material(state, &v, 12, fallback){
+                                Some(material) => state.chunk_manager.draw(first, last, color,id,material,&state.device),
+                                None => s = String::from("Unknown material"),
+                            }
+                        }
+                    }
+                }
+
+                "/material" =>{
+
+                    if v.len() < 2 || v[1].is_empty() {
+                        s = "Materials: ".to_owned() + &state.chunk_manager.material_list.names();
                     }
                     else{
-                        while i < 12{
+                        match state.chunk_manager.material_list.find(v[1]){
+                            Some(material) => {
+                                state.chunk_manager.model_state.material = material;
+                                state.chunk_manager.brush_state.material = Some(material);
+                                s = "Material set to ".to_owned() + v[1];
+                            }
+                            None => {
+                                s = String::from("Unknown material");
+                            }
+                        }
+                    }
+                }
+                "/text" =>{
 
-                            if i >= 1 && i <=3{
-                                first[i-1] = v[i].parse::<i32>().unwrap();
+                    let v = split_quoted(&self.text);
+
+                    let mut style = font_list::TextStyle::new();
+                    let parsed = if v.len() < 5 {
+                        Err(String::from("Insufficient args"))
+                    }
+                    else{
+                        (|| {
+                            if v.len() > 5 {
+                                if let Some(font) = font_list::find(&v[5]){
+                                    style.font = font;
+                                }
+                            }
+                            if v.len() > 6 {
+                                style.size = parse::<i32>(&v[6])?.max(1);
+                            }
+                            if v.len() > 7 {
+                                style.axis = model_list::PlaneAxis::parse(&v[7]).unwrap_or(style.axis);
+                            }
+                            if v.len() > 8 {
+                                style.depth = parse::<i32>(&v[8])?.max(1);
                             }
+                            if v.len() > 9 {
+                                style.align = font_list::TextAlign::parse(&v[9]).unwrap_or(style.align);
+                            }
+                            parse_list::<i32, 3>(&v[2..5])
+                        })()
+                    };
+
+                    match parsed{
+                        Err(e) => s = e,
+                        Ok(origin) => {
+
+                            let shell_color = state.iced_state.program().color;
+                            let color = [shell_color.r,shell_color.g,shell_color.b,1.0];
+
+                            let mut instance_list = font_list::voxelize(&v[1], origin, &style, color);
+                            set_material(&mut instance_list, state.chunk_manager.model_state.material);
+
+                            s = "Text placed at ".to_owned()
+                            + &origin[0].to_string()+ &' '.to_string()
+                            + &origin[1].to_string()+ &' '.to_string()
+                            + &origin[2].to_string()+ &' '.to_string()
+                            + &" voxels = ".to_owned()
+                            + &instance_list.len().to_string();
+
+                            state.chunk_manager.place_instances(instance_list, &state.device);
+                        }
+                    }
+                }
+
+                "/import" =>{
+
+                    let v = split_quoted(&self.text);
+
+                    let origin = if v.len() < 6 { Err(String::from("Insufficient args")) } else { parse_list::<i32, 3>(&v[3..6]) };
+
+                    match origin{
+                        Err(e) => s = e,
+                        Ok(origin) => match (v[1].as_str(), import::load_png(&v[2])){
+
+                            ("image", Ok(image)) => {
+
+                                let mut style = import::ImageStyle::new();
+
+                                if v.len() > 6 {
+                                    style.axis = model_list::PlaneAxis::parse(&v[6]).unwrap_or(style.axis);
+                                }
+                                if v.len() > 8 {
+                                    style.palette = import::find_palette(&v[8]);
+                                }
+                                let parsed = (|| {
+                                    if v.len() > 7 {
+                                        style.scale = parse::<i32>(&v[7])?.max(1);
+                                    }
+                                    if v.len() > 9 {
+                                        style.cutout = parse::<f32>(&v[9])?;
+                                    }
+                                    Ok::<(), String>(())
+                                })();
 
-                            if i >= 4 && i <=6{
-                                last[i-4] = v[i].parse::<i32>().unwrap();
+                                match parsed{
+                                    Err(e) => s = e,
+                                    Ok(()) => {
+                                        let mut instance_list = import::mosaic(&image, origin, &style);
+                                        set_material(&mut instance_list, state.chunk_manager.model_state.material);
+                                        s = "Image imported, voxels = ".to_owned() + &instance_list.len().to_string();
+                                        state.chunk_manager.place_instances(instance_list, &state.device);
+                                    }
+                                }
                             }
 
-                            if i >= 7 && i <= 10{
-                                color[i-7] = v[i].parse::<f32>().unwrap();
+                            ("heightmap", Ok(image)) => {
+
+                                let parsed = (|| {
+                                    let scale = if v.len() > 6 { parse::<i32>(&v[6])?.max(1) } else { 1 };
+                                    let height = if v.len() > 7 { parse::<i32>(&v[7])?.max(1) } else { 32 };
+                                    Ok::<_, String>((scale, height))
+                                })();
+
+                                match parsed{
+                                    Err(e) => s = e,
+                                    Ok((scale, height)) => {
+                                        let shell_color = state.iced_state.program().color;
+                                        let color = [shell_color.r,shell_color.g,shell_color.b,1.0];
+
+                                        let mut instance_list = import::heightmap(&image, origin, scale, height, color);
+                                        set_material(&mut instance_list, state.chunk_manager.model_state.material);
+                                        s = "Heightmap imported, voxels = ".to_owned() + &instance_list.len().to_string();
+                                        state.chunk_manager.place_instances(instance_list, &state.device);
+                                    }
+                                }
                             }
 
-                            if i == 11{
-                                id = v[i].parse::<i32>().unwrap();
+                            (_, Err(e)) => {
+                                s = "Fail to load image: ".to_owned() + &e;
                             }
 
-                            i = i + 1;
+                            _ => {
+                                s = String::from("Unknown import mode");
+                            }
+                        },
+                    }
+                }
 
-                        }
+                "/get"=>{
 
-                        s = "Model placed at ".to_owned() 
+                    let position = if v.len() < 4 { Err(String::from("Insufficient args")) } else { parse_list::<i32, 3>(&v[1..4]) };
 
-                        + &first[0].to_string()+ &' '.to_string()
-                        + &first[1].to_string()+ &' '.to_string()
-                        + &first[2].to_string()+ &' '.to_string()
+                    s = match position.map(|p| state.chunk_manager.get(p)){
+                        Err(e) => e,
+                        Ok(Some(voxel)) => "Voxel color ".to_owned()
+                            + &voxel.color[0].to_string()+ &' '.to_string()
+                            + &voxel.color[1].to_string()+ &' '.to_string()
+                            + &voxel.color[2].to_string()+ &' '.to_string()
+                            + &voxel.color[3].to_string()
+                            + &" material ".to_owned()
+                            + &state.chunk_manager.material_list.get(voxel.material).name,
+                        Ok(None) => String::from("No voxel"),
+                    };
+                }
 
-                        + &"; ".to_owned() 
-            
-                        + &last[0].to_string()+ &' '.to_string()
-                        + &last[1].to_string()+ &' '.to_string()
-                        + &last[2].to_string()+ &' '.to_string()
+                "/count"=>{
 
-                        + &" ID =".to_owned() 
-                        + &id.to_string()+ &' '.to_string()
-                        
+                    let parsed = if v.len() < 7 {
+                        Err(String::from("Insufficient args"))
                     }
+                    else{
+                        parse_list::<i32, 3>(&v[1..4]).and_then(|first| Ok((first, parse_list::<i32, 3>(&v[4..7])?)))
+                    };
+
+                    s = match parsed{
+                        Ok((first, last)) => "Voxels in box: ".to_owned() + &state.chunk_manager.count(first, last, |_| true).to_string(),
+                        Err(e) => e,
+                    };
+                }
 
-                    state.chunk_manager.place(first, last, color, delete, &state.device, ChunkType::Default,id, &mut state.iced_state);
+                "/light"=>{
 
+                    match v.get(1).copied(){
+
+                        Some("add") if v.len() >= 9 => {
+                            let radius = match v.get(9) {
+                                Some(radius) if !radius.is_empty() => parse::<f32>(radius),
+                                _ => Ok(DEFAULT_LIGHT_RADIUS),
+                            };
+                            s = match parse_list::<f32, 7>(&v[2..9]).and_then(|l| Ok((l, radius?))){
+                                Ok((l, _)) if l[6] <= 0.0 => String::from("Light intensity has to be above 0"),
+                                Ok((l, radius)) => {
+                                    let light = Light::point([l[0],l[1],l[2]], [l[3],l[4],l[5]], l[6], radius);
+                                    match state.chunk_manager.light_manager.add(light){
+                                        Some(handle) => "Light added, id = ".to_owned() + &handle.index().to_string(),
+                                        None => String::from("Light list is full"),
+                                    }
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("list") => {
+                            s = "Lights: ".to_owned() + &state.chunk_manager.light_manager.count().to_string();
+                            state.chunk_manager.light_manager.iter().for_each(|(h,l)|{
+                                s += &format!("\n{}: {} {} {} color {} {} {} intensity {} radius {}",
+                                    h.index(), l.position[0], l.position[1], l.position[2],
+                                    l.color[0], l.color[1], l.color[2], l.color[3], l.radius);
+                            });
+                        }
+
+                        Some("remove") if v.len() > 2 => {
+                            let light_manager = &mut state.chunk_manager.light_manager;
+                            s = match parse::<usize>(v[2]).map(|index| (index, light_manager.find(index))){
+                                Ok((index, Some(handle))) => {
+                                    light_manager.remove(handle);
+                                    "Light removed, id = ".to_owned() + &index.to_string()
+                                }
+                                Ok((_, None)) => String::from("No light with this id"),
+                                Err(e) => e,
+                            };
+                        }
+
+                        _ => {
+                            s = String::from("Usage: /light add x y z r g b intensity [radius] | list | remove <id>");
+                        }
+                    }
                 }
 
-                "/delete" =>{
+                "/bind"=>{
 
-                    let mut first = [0,0,0];
-                    let mut last = [0,0,0];
-                    let mut color = [0.0,0.0,0.0,0.0];
-                    let mut id = 0;
-                    let delete = true;
+                    let input_map = &mut state.camera_controller.input_map;
 
-                    if v.len() < 12 {
-                        s = String::from("Insufficient args");
+                    match v.get(1).copied(){
+
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Some("save") => {
+                            s = match input_map.save(input::BINDING_FILE){
+                                Ok(()) => "Bindings saved to ".to_owned() + input::BINDING_FILE,
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("reset") => {
+                            input_map.reset();
+                            s = String::from("Bindings reset");
+                        }
+
+                        //the target may be a command with spaces of its own
+                        Some(binding) if v.len() > 2 => {
+                            let target = v[2..].join(" ");
+                            s = match input_map.bind(binding, &target){
+                                Ok(()) => format!("{} bound to {}", binding, target),
+                                Err(e) => e,
+                            };
+                        }
+
+                        _ => {
+                            s = input_map.binding_list().join(", ");
+                        }
                     }
-                    else{
-                        while i < 12{
+                }
 
-                            if i >= 1 && i <=3{
-                                first[i-1] = v[i].parse::<i32>().unwrap();
-                            }
+                "/camera"=>{
+
+                    let camera = &mut state.camera;
+
+                    match v.get(1).copied(){
+
+                        Some("ortho") => {
+                            camera.projection = Projection::Orthographic;
+                            s = String::from("Camera set to ortho");
+                        }
+
+                        Some("perspective") => {
+                            camera.projection = Projection::Perspective;
+                            s = String::from("Camera set to perspective");
+                        }
 
-                            if i >= 4 && i <=6{
-                                last[i-4] = v[i].parse::<i32>().unwrap();
+                        Some("mode") if v.len() > 2 => {
+                            let chunk_manager = &state.chunk_manager;
+                            match state.camera_controller.set_mode(v[2], &mut state.camera, &|p| chunk_manager.is_solid(p)){
+                                Ok(()) => s = "Camera mode set to ".to_owned() + v[2],
+                                Err(e) => s = e + ", try " + &state.camera_controller.mode_name_list().join(", "),
                             }
+                        }
+
+                        Some("fov") if v.len() > 2 => {
+                            s = match parse::<f32>(v[2]){
+                                Ok(fov) => {
+                                    camera.fov = fov.clamp(1.0, MAX_FOV);
+                                    "Camera fov set to ".to_owned() + &camera.fov.to_string()
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("clip") if v.len() > 3 => {
+                            s = match parse_list::<f32, 2>(&v[2..4]){
+                                Ok([near, far]) if near > 0.0 && near < far => {
+                                    camera.znear = near;
+                                    camera.zfar = far;
+                                    format!("Camera clip set to {} {}", near, far)
+                                }
+                                Ok(_) => String::from("Camera clip needs 0 < near < far"),
+                                Err(e) => e,
+                            };
+                        }
+
+                        _ => {
+                            s = format!(
+                                "Camera {} {}, fov {}, clip {} {}",
+                                state.camera_controller.mode_name(),
+                                camera.projection.to_str(),
+                                camera.fov,
+                                camera.znear,
+                                camera.zfar,
+                            );
+                        }
+                    }
+                }
+
+                "/view"=>{
+
+                    let view_hash = &mut state.chunk_manager.view_hash;
+
+                    match v.get(1).copied(){
 
-                            if i >= 7 && i <= 10{
-                                color[i-7] = v[i].parse::<f32>().unwrap();
+                        Some("save") if v.len() > 2 => {
+                            let view = state.camera_controller.view(&state.camera, state.sample_ratio);
+                            view_hash.insert(v[2].to_string(), view);
+                            s = "View saved as ".to_owned() + v[2];
+                        }
+
+                        Some("goto") if v.len() > 2 => {
+                            match view_hash.get(v[2]){
+                                Some(view) => {
+                                    state.camera_controller.go_to(*view, &state.camera, state.sample_ratio);
+                                    s = "Going to view ".to_owned() + v[2];
+                                }
+                                None => s = "Unknown view ".to_owned() + v[2],
                             }
+                        }
+
+                        Some("delete") if v.len() > 2 => {
+                            s = match view_hash.remove(v[2]){
+                                Some(_) => "View deleted ".to_owned() + v[2],
+                                None => "Unknown view ".to_owned() + v[2],
+                            };
+                        }
 
-                            if i == 11{
-                                id = v[i].parse::<i32>().unwrap();
+                        _ => {
+                            let name_list: Vec<&str> = view_hash.keys().map(|k| k.as_str()).collect();
+                            s = if name_list.is_empty(){
+                                String::from("Usage: /view save <name> | goto <name> | delete <name>")
                             }
+                            else{
+                                "Views: ".to_owned() + &name_list.join(", ")
+                            };
+                        }
+                    }
+                }
+
+                "/tp"=>{
+
+                    let position = if v.len() < 4 { Err(String::from("Insufficient args")) } else { parse_list::<f32, 3>(&v[1..4]) };
+
+                    s = match position{
+                        Ok(position) => {
+                            let mut view = state.camera_controller.view(&state.camera, state.sample_ratio);
+                            view.position = position;
+                            state.camera_controller.go_to(view, &state.camera, state.sample_ratio);
+                            format!("Moving to {} {} {}", view.position[0], view.position[1], view.position[2])
+                        }
+                        Err(e) => e,
+                    };
+                }
+
+                "/path"=>{
+
+                    let controller = &mut state.camera_controller;
+
+                    match v.get(1).copied(){
+
+                        Some("add") => {
+                            controller.add_keyframe(&state.camera, state.sample_ratio);
+                            s = "Keyframe ".to_owned() + &controller.path.keyframe_list.len().to_string() + " added";
+                        }
+
+                        Some("clear") => {
+                            controller.stop();
+                            controller.path.keyframe_list.clear();
+                            s = String::from("Path cleared");
+                        }
 
-                            i = i + 1;
+                        Some("time") if v.len() > 2 => {
+                            s = match parse::<f32>(v[2]){
+                                Ok(time) => {
+                                    controller.path.segment_time = time.max(0.1);
+                                    "Seconds per keyframe set to ".to_owned() + &controller.path.segment_time.to_string()
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("play") => {
+                            s = match controller.play(None){
+                                Ok(()) => format!("Playing {} keyframes over {}s", controller.path.keyframe_list.len(), controller.path.duration()),
+                                Err(e) => e,
+                            };
+                        }
+
+                        //fixed steps so the sequence plays back at fps however slow the frames are written
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Some("record") if v.len() > 2 => {
+                            let fps = match v.get(3) {
+                                Some(fps) if !fps.is_empty() => parse::<f32>(fps),
+                                _ => Ok(camera_path::DEFAULT_RECORD_FPS),
+                            }.map(|fps| fps.max(1.0));
+                            s = match fps.and_then(|fps| {
+                                let c = capture::FrameCapture::new(&state.device, state.texture_size, state.config.format)?;
+                                controller.play(Some(1.0 / fps))?;
+                                Ok((c, fps))
+                            })
+                            {
+                                Ok((c, fps)) => {
+                                    state.recording = Some(capture::Recording::new(c, v[2]));
+                                    format!("Recording {}s at {} fps to {}", controller.path.duration(), fps, v[2])
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("stop") => {
+                            controller.stop();
+                            s = String::from("Path stopped");
+                        }
 
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Some("save") if v.len() > 2 => {
+                            s = match controller.path.save(v[2]){
+                                Ok(()) => "Path saved to ".to_owned() + v[2],
+                                Err(e) => e,
+                            };
                         }
 
-                        s = "Model deleted at ".to_owned() 
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Some("load") if v.len() > 2 => {
+                            s = match camera_path::CameraPath::load(v[2]){
+                                Ok(path) => {
+                                    controller.stop();
+                                    controller.path = path;
+                                    format!("Path loaded with {} keyframes", controller.path.keyframe_list.len())
+                                }
+                                Err(e) => e,
+                            };
+                        }
 
-                        + &first[0].to_string()+ &' '.to_string()
-                        + &first[1].to_string()+ &' '.to_string()
-                        + &first[2].to_string()+ &' '.to_string()
+                        _ => {
+                            s = format!(
+                                "Path {} keyframes, {}s each, {}",
+                                controller.path.keyframe_list.len(),
+                                controller.path.segment_time,
+                                if controller.is_playing() { "playing" } else { "stopped" },
+                            );
+                        }
+                    }
+                }
 
-                        + &"; ".to_owned() 
-            
-                        + &last[0].to_string()+ &' '.to_string()
-                        + &last[1].to_string()+ &' '.to_string()
-                        + &last[2].to_string()+ &' '.to_string()
+                "/set"=>{
 
-                        + &" ID =".to_owned() 
-                        + &id.to_string()+ &' '.to_string()
-                        
+                    s = if v.len() > 2 {
+                        //values may not contain spaces, none of the keys need them
+                        match state.settings.set(v[1], v[2]){
+                            Ok(()) => format!("{} set to {}", v[1], state.settings.get(v[1]).unwrap_or_default()),
+                            Err(e) => e,
+                        }
                     }
+                    else{
+                        String::from("Usage: /set <key> <value>, see /settings list")
+                    };
+                    //also puts a rejected panel edit back to the current value
+                    state.apply_settings();
+                }
+
+                "/settings"=>{
 
-                    state.chunk_manager.place(first, last, color, delete, &state.device, ChunkType::Default, id,&mut state.iced_state);
+                    match v.get(1).copied(){
+
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Some("save") => {
+                            s = match state.settings.save(settings::SETTINGS_FILE){
+                                Ok(()) => "Settings saved to ".to_owned() + settings::SETTINGS_FILE,
+                                Err(e) => e,
+                            };
+                        }
 
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Some("load") => {
+                            s = match settings::Settings::load(settings::SETTINGS_FILE){
+                                Ok(loaded) => {
+                                    state.settings = loaded;
+                                    state.apply_settings();
+                                    "Settings loaded from ".to_owned() + settings::SETTINGS_FILE
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("reset") => {
+                            state.settings = settings::Settings::new();
+                            state.apply_settings();
+                            s = String::from("Settings reset");
+                        }
+
+                        Some("list") => {
+                            s = state.settings.list().iter().map(|(k, v)| format!("{} {}", k, v)).collect::<Vec<_>>().join(", ");
+                        }
+
+                        _ => {
+                            state.iced_state.queue_message(SettingsToggle);
+                            s = String::from("Settings panel toggled");
+                        }
+                    }
                 }
 
-                "/draw" =>{
+                "/time"=>{
 
-                    let mut first = [0,0,0];
-                    let mut last = [0,0,0];
-                    let mut color = [0.0,0.0,0.0,0.0];
-                    let mut id = 0;
+                    let clock = &mut state.clock;
 
-                    if v.len() < 12 {
-                        s = String::from("Insufficient args");
+                    match v.get(1).copied(){
+
+                        Some("set") if v.len() > 2 => {
+                            s = match clock::parse_time(v[2]){
+                                Some(time) => {
+                                    clock.set_time(time);
+                                    clock.drives_sun = true;
+                                    "Time set to ".to_owned() + &clock.time_string()
+                                }
+                                None => String::from("Time must be hh:mm"),
+                            };
+                        }
+
+                        Some("speed") if v.len() > 2 => {
+                            s = match parse::<f64>(v[2]){
+                                Ok(speed) => {
+                                    clock.speed = speed.max(0.0);
+                                    clock.drives_sun = true;
+                                    "Time speed set to ".to_owned() + &clock.speed.to_string()
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("length") if v.len() > 2 => {
+                            s = match parse::<f64>(v[2]){
+                                Ok(length) => {
+                                    clock.day_length = length.max(1.0);
+                                    "Day length set to ".to_owned() + &clock.day_length.to_string() + &"s".to_owned()
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("pause") => {
+                            clock.paused = !clock.paused;
+                            s = if clock.paused { String::from("Time paused") } else { String::from("Time resumed") };
+                        }
+
+                        _ => {
+                            s = "Time ".to_owned() + &clock.time_string();
+                        }
+                    }
+                }
+
+                "/gen"=>{
+
+                    if v.len() < 9 || v[1] != "terrain" {
+                        s = String::from("Usage: /gen terrain <seed> x1 y1 z1 x2 y2 z2 [preset] [water y|none]");
                     }
                     else{
-                        while i < 12{
+                        let seed = terrain::parse_seed(v[2]);
 
-                            if i >= 1 && i <=3{
-                                first[i-1] = v[i].parse::<i32>().unwrap();
+                        let preset = match v.get(9) {
+                            Some(name) if !name.is_empty() => terrain::find_preset(name),
+                            _ => Some(terrain::DEFAULT_PRESET),
+                        };
+
+                        let mut dry = false;
+                        let water = match v.get(10) {
+                            Some(&"none") => {
+                                dry = true;
+                                Ok(None)
                             }
+                            Some(level) if !level.is_empty() => parse::<i32>(level).map(Some),
+                            _ => Ok(None),
+                        };
+                        let parsed = parse_list::<i32, 3>(&v[3..6])
+                            .and_then(|first| Ok((first, parse_list::<i32, 3>(&v[6..9])?, water?)));
 
-                            if i >= 4 && i <=6{
-                                last[i-4] = v[i].parse::<i32>().unwrap();
+                        s = match (parsed, preset) {
+                            (Err(e), _) => e,
+                            (Ok((first, last, water)), Some(preset)) => {
+                                let preset = &terrain::PRESET_LIST[preset];
+                                let preset = terrain::Preset { water: if dry { None } else { preset.water }, ..*preset };
+                                match terrain::Terrain::new(seed, first, last, preset, water) {
+                                    Ok(terrain) => {
+                                        let step_list = job::terrain_job(terrain, &state.chunk_manager.material_list);
+                                        state.job_system.submit("/gen terrain", step_list);
+                                        format!("Generating terrain with seed {}", seed)
+                                    }
+                                    Err(e) => e,
+                                }
+                            }
+                            (Ok(_), None) => {
+                                let name_list: Vec<&str> = terrain::PRESET_LIST.iter().map(|p| p.name).collect();
+                                "Unknown preset, choose from ".to_owned() + &name_list.join(", ")
                             }
+                        };
+                    }
+                }
+
+                "/tick"=>{
+
+                    let tick_clock = &mut state.tick_clock;
+
+                    match v.get(1).copied(){
+
+                        Some("rate") if v.len() > 2 => {
+                            s = match parse::<u32>(v[2]){
+                                Ok(rate) => {
+                                    tick_clock.set_rate(rate);
+                                    "Tick rate set to ".to_owned() + &tick_clock.rate.to_string() + &" Hz".to_owned()
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        _ => {
+                            s = format!("Tick {} at {} Hz, measured {} TPS", tick_clock.tick, tick_clock.rate, tick_clock.tps);
+                        }
+                    }
+                }
+
+                "/stream"=>{
+
+                    let chunk_manager = &mut state.chunk_manager;
+
+                    match v.get(1).copied(){
 
-                            if i >= 7 && i <= 10{
-                                color[i-7] = v[i].parse::<f32>().unwrap();
+                        Some("on") => {
+                            chunk_manager.streamer.enabled = true;
+                            s = String::from("Chunk streaming on");
+                        }
+
+                        Some("off") => {
+                            //everything comes back so the whole world is editable again
+                            chunk_manager.streamer.enabled = false;
+                            for position in chunk_manager.streamer.evicted_list(){
+                                chunk_manager.restore_chunk(position, &state.device);
                             }
+                            s = String::from("Chunk streaming off");
+                        }
+
+                        Some("radius") if v.len() > 2 => {
+                            s = match parse::<i32>(v[2]){
+                                Ok(radius) => {
+                                    chunk_manager.streamer.view_radius = radius.max(0);
+                                    "View radius set to ".to_owned() + &chunk_manager.streamer.view_radius.to_string() + &" chunks".to_owned()
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("budget") if v.len() > 2 => {
+                            s = match parse::<usize>(v[2]){
+                                Ok(budget) => {
+                                    chunk_manager.streamer.budget = budget;
+                                    "Streaming budget set to ".to_owned() + &chunk_manager.streamer.budget.to_string() + &" voxels".to_owned()
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        _ => {
+                            let resident = chunk_manager.chunk_hash.len();
+                            let voxels: usize = chunk_manager.chunk_hash.values().map(|i| chunk_manager.chunk_list[*i].voxel_data.len()).sum();
+                            s = format!(
+                                "Streaming {}, {} chunks resident with {} voxels, {} evicted, radius {}, budget {}",
+                                if chunk_manager.streamer.enabled { "on" } else { "off" },
+                                resident,
+                                voxels,
+                                chunk_manager.streamer.evicted_list().len(),
+                                chunk_manager.streamer.view_radius,
+                                chunk_manager.streamer.budget,
+                            );
+                        }
+                    }
+                }
+
+                "/lod"=>{
+
+                    let settings = &mut state.chunk_manager.lod_settings;
+
+                    match v.get(1).copied(){
+
+                        Some("on") => {
+                            settings.enabled = true;
+                            s = String::from("Level of detail on");
+                        }
 
-                            if i == 11{
-                                id = v[i].parse::<i32>().unwrap();
+                        Some("off") => {
+                            settings.enabled = false;
+                            s = String::from("Level of detail off");
+                        }
+
+                        Some("distance") if v.len() > 4 => {
+                            s = match parse_list::<f32, 3>(&v[2..5]){
+                                Ok(distance_list) if distance_list[0] < distance_list[1] && distance_list[1] < distance_list[2] => {
+                                    settings.distance_list = distance_list;
+                                    format!("Lod distances set to {} {} {}", distance_list[0], distance_list[1], distance_list[2])
+                                }
+                                Ok(_) => String::from("Lod distances must increase"),
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("hysteresis") if v.len() > 2 => {
+                            s = match parse::<f32>(v[2]){
+                                Ok(hysteresis) => {
+                                    settings.hysteresis = hysteresis.max(0.0);
+                                    "Lod hysteresis set to ".to_owned() + &settings.hysteresis.to_string()
+                                }
+                                Err(e) => e,
+                            };
+                        }
+
+                        Some("color") if v.len() > 2 => {
+                            match v[2]{
+                                "majority" => { settings.color_mode = lod::ColorMode::Majority; s = String::from("Lod color set to majority"); }
+                                "average" => { settings.color_mode = lod::ColorMode::Average; s = String::from("Lod color set to average"); }
+                                _ => { s = String::from("Lod color is majority or average"); }
                             }
+                        }
+
+                        _ => {
+                            let mut level_count = [0; lod::LOD_SCALE_LIST.len() + 1];
+                            let mut instance_count = 0;
+                            state.chunk_manager.chunk_list.iter().filter(|c| c.current_type == ChunkType::Default).for_each(|c| {
+                                level_count[c.lod_level] += 1;
+                                instance_count += c.draw_buffer().1;
+                            });
+                            s = format!(
+                                "Lod {}, chunks per level {:?}, {} instances drawn",
+                                if state.chunk_manager.lod_settings.enabled { "on" } else { "off" },
+                                level_count,
+                                instance_count,
+                            );
+                        }
+                    }
+                }
+
+                "/sim"=>{
+
+                    let simulation = &mut state.simulation;
+
+                    match v.get(1).copied(){
+
+                        Some("pause") => {
+                            simulation.paused = !simulation.paused;
+                            s = if simulation.paused { String::from("Simulation paused") } else { String::from("Simulation resumed") };
+                        }
 
-                            i = i + 1;
+                        Some("step") => {
+                            let count = match v.get(2) {
+                                Some(n) if !n.is_empty() => parse::<u32>(n),
+                                _ => Ok(1),
+                            };
+                            s = match count{
+                                Ok(count) => {
+                                    let clamped = count.min(logic_list::MAX_MANUAL_STEP);
+                                    for _ in 0..clamped{
+                                        simulation.step(&mut state.chunk_manager, &state.device);
+                                    }
+                                    let s = "Simulation at tick ".to_owned() + &simulation.tick.to_string();
+                                    if clamped < count { s + &format!(" (clamped to {} steps)", clamped) } else { s }
+                                }
+                                Err(e) => e,
+                            };
+                        }
 
+                        Some("rate") if v.len() > 2 => {
+                            s = match parse::<f32>(v[2]){
+                                Ok(rate) => {
+                                    simulation.rate = rate.max(0.0);
+                                    "Simulation rate set to ".to_owned() + &simulation.rate.to_string() + &" ticks/s".to_owned()
+                                }
+                                Err(e) => e,
+                            };
                         }
 
-                        s = "Model drawn at ".to_owned() 
+                        _ => {
+                            s = format!(
+                                "Simulation {} at tick {}, {} active voxels, {} ticks/s",
+                                if simulation.paused { "paused" } else { "running" },
+                                simulation.tick,
+                                simulation.active_len(),
+                                simulation.rate,
+                            );
+                        }
+                    }
+                }
 
-                        + &first[0].to_string()+ &' '.to_string()
-                        + &first[1].to_string()+ &' '.to_string()
-                        + &first[2].to_string()+ &' '.to_string()
+                "/sun"=>{
 
-                        + &"; ".to_owned() 
-            
-                        + &last[0].to_string()+ &' '.to_string()
-                        + &last[1].to_string()+ &' '.to_string()
-                        + &last[2].to_string()+ &' '.to_string()
+                    if v.len() < 6 {
+                        s = String::from("Insufficient args");
+                    }
+                    else{
+                        s = match parse_list::<f32, 5>(&v[1..6]){
+                            Ok(l) => {
+                                let light_manager = &mut state.chunk_manager.light_manager;
+                                light_manager.sun_azimuth = l[0];
+                                light_manager.sun_elevation = l[1].clamp(-90.0, 90.0);
+                                light_manager.sun_color[..3].copy_from_slice(&l[2..5]);
+                                //a hand placed sun stays put until /time takes over again
+                                state.clock.drives_sun = false;
+                                String::from("Sun updated")
+                            }
+                            Err(e) => e,
+                        };
+                    }
+                }
 
-                        + &" ID =".to_owned() 
-                        + &id.to_string()+ &' '.to_string()
+                "/ambient"=>{
 
+                    if v.len() < 4 {
+                        s = String::from("Insufficient args");
+                    }
+                    else{
+                        s = match parse_list::<f32, 3>(&v[1..4]){
+                            Ok(l) => {
+                                state.chunk_manager.light_manager.ambient[..3].copy_from_slice(&l);
+                                String::from("Ambient updated")
+                            }
+                            Err(e) => e,
+                        };
                     }
-                    state.chunk_manager.draw(first, last, color,id,&state.device);
                 }
-                "/get"=>{
-                    s = String::from("");
+
+                "/save"=>{
+
+                    let path = v.get(1).copied().filter(|p|!p.is_empty()).unwrap_or(world::DEFAULT_WORLD).to_owned();
+                    s = match world::save(&path, &state.chunk_manager){
+                        Ok(count) => "World saved to ".to_owned() + &path + &", chunks =".to_owned() + &count.to_string(),
+                        Err(e) => "Fail to save world: ".to_owned() + &e,
+                    };
+                }
+
+                "/load"=>{
+
+                    let path = v.get(1).copied().filter(|p|!p.is_empty()).unwrap_or(world::DEFAULT_WORLD).to_owned();
+                    s = match world::load(&path, &mut state.chunk_manager, &mut state.job_system, &state.device){
+                        Ok(count) => "World loaded from ".to_owned() + &path + &", chunks =".to_owned() + &count.to_string(),
+                        Err(e) => "Fail to load world: ".to_owned() + &e,
+                    };
                 }
 
                 "/diffuse"=>{
@@ -229,11 +994,83 @@ impl Descriptor{
                 }
                 
             }
-            //state.iced_state.queue_message(ServerLog(s));
+            if !s.is_empty(){
+                state.iced_state.queue_message(ServerLog(s));
+            }
         }
         else {
-            state.iced_state.queue_message(ChatMessage);
+            state.iced_state.queue_message(ChatMessage(self.text.clone()));
+        }
+    }
+
+}
+
+//split on spaces while keeping "quoted text" as one argument
+pub fn split_quoted(text:&str) -> Vec<String>{
+
+    let mut v:Vec<String> = Default::default();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    text.chars().for_each(|c|{
+        match c {
+            '"' => {
+                if quoted{
+                    v.push(current.to_owned());
+                    current.clear();
+                }
+                quoted = !quoted;
+            }
+            ' ' if !quoted => {
+                if !current.is_empty(){
+                    v.push(current.to_owned());
+                    current.clear();
+                }
+            }
+            _ => current.push(c),
         }
+    });
+
+    if !current.is_empty(){
+        v.push(current);
+    }
+    v
+}
+
+//one command argument, the error goes straight into the reply instead of panicking
+fn parse<T: std::str::FromStr>(text:&str) -> Result<T,String>{
+    text.parse::<T>().map_err(|_| format!("Can not parse {}", text))
+}
+
+//n arguments of the same type, e.g. a position
+fn parse_list<T: std::str::FromStr, const N: usize>(v:&[impl AsRef<str>]) -> Result<[T;N],String>{
+    if v.len() < N {
+        return Err(String::from("Insufficient args"));
+    }
+    let list = v[..N].iter().map(|a| parse::<T>(a.as_ref())).collect::<Result<Vec<T>,String>>()?;
+    list.try_into().map_err(|_| String::from("Insufficient args"))
+}
+
+//x1 y1 z1 x2 y2 z2 r g b a id, shared by /place /delete and /draw
+fn parse_box(v:&[&str]) -> Result<([i32;3],[i32;3],[f32;4],i32),String>{
+    if v.len() < 12 {
+        return Err(String::from("Insufficient args"));
+    }
+    Ok((parse_list(&v[1..4])?, parse_list(&v[4..7])?, parse_list(&v[7..11])?, parse(v[11])?))
+}
+
+fn box_text(first:[i32;3], last:[i32;3], id:i32) -> String{
+    format!("{} {} {} ; {} {} {} ID = {} ", first[0], first[1], first[2], last[0], last[1], last[2], id)
+}
+
+//optional material name at index, commands generated by the indicator end with an empty argument
+fn parse_material(state:&State, v:&Vec<&str>, index:usize, fallback:usize) -> Option<usize>{
+    match v.get(index){
+        Some(name) if !name.is_empty() => state.chunk_manager.material_list.find(name),
+        _ => Some(fallback),
     }
+}
 
-}
\ No newline at end of file
+fn set_material(instance_list:&mut Vec<Instance>, material:usize){
+    instance_list.iter_mut().for_each(|ins| ins.material = material);
+}
@@ -0,0 +1,209 @@
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use std::collections::HashSet;
+
+use crate::Instance;
+use crate::material_list::DEFAULT_MATERIAL;
+use crate::model_list::PlaneAxis;
+
+const FONT_LIST: [(&str, &[u8]); 4] = [
+    ("unibody", include_bytes!("font/Unibody.ttf")),
+    ("dinkie", include_bytes!("font/DinkieBitmap-9px.ttf")),
+    ("lato", include_bytes!("font/Lato-Regular.ttf")),
+    ("fangsong", include_bytes!("font/仿宋_GB2312.ttf")),
+];
+
+//coverage a pixel needs before it becomes a voxel
+const COVERAGE_THRESHOLD: f32 = 0.5;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "left" => Some(TextAlign::Left),
+            "center" => Some(TextAlign::Center),
+            "right" => Some(TextAlign::Right),
+            _ => None,
+        }
+    }
+}
+
+pub struct TextStyle {
+    pub font: usize,
+    pub size: i32,
+    pub axis: PlaneAxis,
+    pub depth: i32,
+    pub align: TextAlign,
+}
+
+impl TextStyle {
+    pub fn new() -> Self {
+        Self { font: 0, size: 16, axis: PlaneAxis::X, depth: 1, align: TextAlign::Left }
+    }
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn find(name: &str) -> Option<usize> {
+    FONT_LIST.iter().position(|(n, _)| *n == name)
+}
+
+pub fn names() -> String {
+    FONT_LIST.iter().map(|(n, _)| *n).collect::<Vec<_>>().join("|")
+}
+
+//rasterize a single line into filled pixels, x to the right and y up from the baseline row 0
+pub fn rasterize(text: &str, font: usize, size: i32) -> (HashSet<[i32; 2]>, i32) {
+
+    let mut pixels: HashSet<[i32; 2]> = Default::default();
+
+    let font = match FontRef::try_from_slice(FONT_LIST[font].1) {
+        Ok(font) => font,
+        Err(_) => return (pixels, 0),
+    };
+
+    let scale = PxScale::from(size as f32);
+    let scaled = font.as_scaled(scale);
+    let ascent = scaled.ascent();
+
+    let mut caret = 0.0;
+    let mut previous = None;
+
+    for c in text.chars() {
+
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, id);
+        }
+
+        //snap glyph origins to whole voxels so bitmap fonts stay crisp
+        let glyph = id.with_scale_and_position(scale, point(caret.round(), ascent.round()));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|x, y, coverage| {
+                if coverage >= COVERAGE_THRESHOLD {
+                    let px = bounds.min.x as i32 + x as i32;
+                    let py = bounds.min.y as i32 + y as i32;
+                    //image rows grow downwards, flip them around the baseline
+                    pixels.insert([px, ascent.round() as i32 - 1 - py]);
+                }
+            });
+        }
+    }
+
+    (pixels, caret.ceil() as i32)
+}
+
+pub fn voxelize(text: &str, origin: [i32; 3], style: &TextStyle, color: [f32; 4]) -> Vec<Instance> {
+
+    let (pixels, width) = rasterize(text, style.font, style.size);
+
+    let shift = match style.align {
+        TextAlign::Left => 0,
+        TextAlign::Center => width / 2,
+        TextAlign::Right => width,
+    };
+
+    let (run, up, back) = style.axis.basis();
+
+    let depth = style.depth.max(1);
+    let mut instance_list: Vec<Instance> = Default::default();
+
+    pixels.iter().for_each(|p| {
+
+        //outline pixels take the normal of the open side so edges stay visible
+        let mut side = [0, 0];
+        if !pixels.contains(&[p[0] - 1, p[1]]) { side[0] = -1 }
+        else if !pixels.contains(&[p[0] + 1, p[1]]) { side[0] = 1 }
+        else if !pixels.contains(&[p[0], p[1] - 1]) { side[1] = -1 }
+        else if !pixels.contains(&[p[0], p[1] + 1]) { side[1] = 1 }
+
+        for d in 0..depth {
+
+            let u = p[0] - shift;
+            let v = p[1];
+
+            let position = [
+                origin[0] + u * run[0] + v * up[0] + d * back[0],
+                origin[1] + u * run[1] + v * up[1] + d * back[1],
+                origin[2] + u * run[2] + v * up[2] + d * back[2],
+            ];
+
+            let normal = if d == 0 {
+                [-back[0] as f32, -back[1] as f32, -back[2] as f32]
+            } else if d == depth - 1 {
+                [back[0] as f32, back[1] as f32, back[2] as f32]
+            } else {
+                [
+                    (side[0] * run[0] + side[1] * up[0]) as f32,
+                    (side[0] * run[1] + side[1] * up[1]) as f32,
+                    (side[0] * run[2] + side[1] * up[2]) as f32,
+                ]
+            };
+
+            instance_list.push(Instance::new(position, color, normal, DEFAULT_MATERIAL));
+        }
+    });
+
+    instance_list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //the 9px bitmap font draws I as a stem with a three pixel foot and a two pixel cap
+    const I_FOOTPRINT: [[i32; 2]; 9] = [[0, 0], [1, 0], [2, 0], [1, 1], [1, 2], [1, 3], [1, 4], [0, 5], [1, 5]];
+
+    fn footprint(voxel_list: &[Instance], z: i32) -> Vec<[i32; 2]> {
+        let mut list: Vec<[i32; 2]> = voxel_list
+            .iter()
+            .map(|v| v.grid_position())
+            .filter(|p| p[2] == z)
+            .map(|p| [p[0], p[1]])
+            .collect();
+        list.sort();
+        list
+    }
+
+    #[test]
+    fn voxelize_extrudes_the_glyph() {
+        let style = TextStyle { font: find("dinkie").unwrap(), size: 9, depth: 2, ..TextStyle::new() };
+        let voxel_list = voxelize("I", [0, 0, 0], &style, [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(voxel_list.len(), 18);
+
+        let mut expected = I_FOOTPRINT.to_vec();
+        expected.sort();
+        assert_eq!(footprint(&voxel_list, 0), expected);
+        assert_eq!(footprint(&voxel_list, -1), expected);
+
+        //front and back faces point out of the text
+        voxel_list.iter().for_each(|v| {
+            let z = if v.grid_position()[2] == 0 { 1.0 } else { -1.0 };
+            assert_eq!(v.normal, [0.0, 0.0, z].into());
+        });
+    }
+
+    #[test]
+    fn voxelize_aligns_on_the_origin() {
+        let style = TextStyle { font: find("dinkie").unwrap(), size: 9, align: TextAlign::Right, ..TextStyle::new() };
+        let (_, width) = rasterize("I", style.font, style.size);
+        let voxel_list = voxelize("I", [0, 0, 0], &style, [1.0, 1.0, 1.0, 1.0]);
+
+        let mut expected: Vec<[i32; 2]> = I_FOOTPRINT.iter().map(|p| [p[0] - width, p[1]]).collect();
+        expected.sort();
+        assert_eq!(footprint(&voxel_list, 0), expected);
+    }
+}
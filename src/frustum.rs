@@ -0,0 +1,83 @@
+use cgmath::{Matrix, Matrix4, Vector4};
+
+//a chunk is drawn if any part of its box is inside all six planes
+pub struct Frustum {
+    //xyz normal pointing inwards, w distance
+    plane_list: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    //planes of a wgpu view projection, clip z runs from 0 to w
+    pub fn from_matrix(view_proj: Matrix4<f32>) -> Self {
+        let r0 = view_proj.row(0);
+        let r1 = view_proj.row(1);
+        let r2 = view_proj.row(2);
+        let r3 = view_proj.row(3);
+
+        Self {
+            plane_list: [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2],
+        }
+    }
+
+    pub fn contains_point(&self, p: [f32; 3]) -> bool {
+        self.plane_list.iter().all(|n| n.x * p[0] + n.y * p[1] + n.z * p[2] + n.w >= 0.0)
+    }
+
+    //only the corner furthest along each plane normal has to be checked
+    pub fn intersects_aabb(&self, min: [f32; 3], max: [f32; 3]) -> bool {
+        self.plane_list.iter().all(|n| {
+            let x = if n.x >= 0.0 { max[0] } else { min[0] };
+            let y = if n.y >= 0.0 { max[1] } else { min[1] };
+            let z = if n.z >= 0.0 { max[2] } else { min[2] };
+            n.x * x + n.y * y + n.z * z + n.w >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{test_camera, Camera, Projection};
+
+    fn camera() -> Camera {
+        test_camera(Projection::Orthographic, 16.0 / 9.0, 200.0)
+    }
+
+    #[test]
+    fn keeps_boxes_around_the_target() {
+        let frustum = Frustum::from_matrix(camera().build_view_projection_matrix());
+        assert!(frustum.contains_point([0.0, 0.0, 0.0]));
+        assert!(frustum.intersects_aabb([-128.0, -128.0, -128.0], [127.0, 127.0, 127.0]));
+        //partly inside counts as visible
+        assert!(frustum.intersects_aabb([300.0, -10.0, -300.0], [600.0, 10.0, -100.0]));
+    }
+
+    #[test]
+    fn culls_boxes_off_screen_and_behind() {
+        let frustum = Frustum::from_matrix(camera().build_view_projection_matrix());
+        //far to the side of the view
+        assert!(!frustum.intersects_aabb([2000.0, -128.0, -2128.0], [2255.0, 127.0, -1873.0]));
+        //behind the eye
+        assert!(!frustum.intersects_aabb([1100.0, 1100.0, 1100.0], [1200.0, 1200.0, 1200.0]));
+        //past the far plane
+        assert!(!frustum.intersects_aabb([-7000.0, -7000.0, -7000.0], [-6900.0, -6900.0, -6900.0]));
+    }
+
+    #[test]
+    fn perspective_widens_with_distance() {
+        let mut camera = camera();
+        camera.projection = Projection::Perspective;
+        camera.znear = 10.0;
+        let frustum = Frustum::from_matrix(camera.build_view_projection_matrix());
+
+        //the same sideways offset is outside near the eye and inside far past the target
+        let right = [1.0 / 2f32.sqrt(), 0.0, -1.0 / 2f32.sqrt()];
+        let at = |depth: f32, side: f32| -> [f32; 3] {
+            let d = 1000.0 - depth / 3f32.sqrt();
+            [d + right[0] * side, d, d + right[2] * side]
+        };
+        assert!(!frustum.contains_point(at(200.0, 300.0)));
+        assert!(frustum.contains_point(at(5000.0, 300.0)));
+        assert!(frustum.contains_point(at(3000.0, 0.0)));
+    }
+}
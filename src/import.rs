@@ -0,0 +1,303 @@
+use std::fs::File;
+use std::io::Read;
+
+use crate::Instance;
+use crate::material_list::DEFAULT_MATERIAL;
+use crate::model_list::PlaneAxis;
+
+//largest image side accepted, keeps a stray photo from spawning millions of voxels
+const MAX_IMAGE_SIDE: u32 = 1024;
+
+const PALETTE_LIST: [(&str, &[[u8; 3]]); 3] = [
+    ("pico8", &[
+        [0x00, 0x00, 0x00], [0x1d, 0x2b, 0x53], [0x7e, 0x25, 0x53], [0x00, 0x87, 0x51],
+        [0xab, 0x52, 0x36], [0x5f, 0x57, 0x4f], [0xc2, 0xc3, 0xc7], [0xff, 0xf1, 0xe8],
+        [0xff, 0x00, 0x4d], [0xff, 0xa3, 0x00], [0xff, 0xec, 0x27], [0x00, 0xe4, 0x36],
+        [0x29, 0xad, 0xff], [0x83, 0x76, 0x9c], [0xff, 0x77, 0xa8], [0xff, 0xcc, 0xaa],
+    ]),
+    ("gameboy", &[
+        [0x0f, 0x38, 0x0f], [0x30, 0x62, 0x30], [0x8b, 0xac, 0x0f], [0x9b, 0xbc, 0x0f],
+    ]),
+    ("cga", &[
+        [0x00, 0x00, 0x00], [0x55, 0xff, 0xff], [0xff, 0x55, 0xff], [0xff, 0xff, 0xff],
+    ]),
+];
+
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.rgba[i], self.rgba[i + 1], self.rgba[i + 2], self.rgba[i + 3]]
+    }
+}
+
+pub struct ImageStyle {
+    pub axis: PlaneAxis,
+    pub scale: i32,
+    pub palette: Option<usize>,
+    pub cutout: f32,
+}
+
+impl ImageStyle {
+    pub fn new() -> Self {
+        Self { axis: PlaneAxis::X, scale: 1, palette: None, cutout: 0.5 }
+    }
+}
+
+impl Default for ImageStyle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn find_palette(name: &str) -> Option<usize> {
+    PALETTE_LIST.iter().position(|(n, _)| *n == name)
+}
+
+pub fn load_png(path: &str) -> Result<Image, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    decode_png(file)
+}
+
+pub fn decode_png<R: Read>(data: R) -> Result<Image, String> {
+
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+
+    //checked from the header, before the frame buffer is allocated
+    let header = reader.info();
+    if header.width > MAX_IMAGE_SIDE || header.height > MAX_IMAGE_SIDE {
+        return Err(format!("Image larger than {}x{}", MAX_IMAGE_SIDE, MAX_IMAGE_SIDE));
+    }
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+
+    let pixel_count = (info.width * info.height) as usize;
+    let mut rgba: Vec<u8> = Vec::with_capacity(pixel_count * 4);
+
+    for i in 0..pixel_count {
+        match info.color_type {
+            png::ColorType::Rgba => rgba.extend_from_slice(&buffer[i * 4..i * 4 + 4]),
+            png::ColorType::Rgb => {
+                rgba.extend_from_slice(&buffer[i * 3..i * 3 + 3]);
+                rgba.push(255);
+            }
+            png::ColorType::GrayscaleAlpha => {
+                let g = buffer[i * 2];
+                rgba.extend_from_slice(&[g, g, g, buffer[i * 2 + 1]]);
+            }
+            png::ColorType::Grayscale => {
+                let g = buffer[i];
+                rgba.extend_from_slice(&[g, g, g, 255]);
+            }
+            //indexed images are expanded to rgb by the decoder
+            png::ColorType::Indexed => return Err("Unsupported color type".to_string()),
+        }
+    }
+
+    Ok(Image { width: info.width, height: info.height, rgba })
+}
+
+//png stores srgb, the voxel colors are linear
+fn to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn quantize(color: [u8; 4], palette: usize) -> [u8; 4] {
+
+    let mut best = PALETTE_LIST[palette].1[0];
+    let mut best_distance = i32::MAX;
+
+    PALETTE_LIST[palette].1.iter().for_each(|p| {
+        let dr = p[0] as i32 - color[0] as i32;
+        let dg = p[1] as i32 - color[1] as i32;
+        let db = p[2] as i32 - color[2] as i32;
+        let distance = dr * dr + dg * dg + db * db;
+        if distance < best_distance {
+            best_distance = distance;
+            best = *p;
+        }
+    });
+
+    [best[0], best[1], best[2], color[3]]
+}
+
+fn voxel(position: [i32; 3], color: [f32; 4], normal: [f32; 3]) -> Instance {
+    Instance::new(position, color, normal, DEFAULT_MATERIAL)
+}
+
+//flat mosaic, one scale x scale block per pixel
+pub fn mosaic(image: &Image, origin: [i32; 3], style: &ImageStyle) -> Vec<Instance> {
+
+    let (run, up, back) = style.axis.basis();
+    let normal = [-back[0] as f32, -back[1] as f32, -back[2] as f32];
+    let scale = style.scale.max(1);
+
+    let mut instance_list: Vec<Instance> = Default::default();
+
+    for py in 0..image.height {
+        for px in 0..image.width {
+
+            let mut pixel = image.pixel(px, py);
+
+            if (pixel[3] as f32 / 255.0) < style.cutout {
+                continue;
+            }
+            if let Some(palette) = style.palette {
+                pixel = quantize(pixel, palette);
+            }
+
+            let color = [to_linear(pixel[0]), to_linear(pixel[1]), to_linear(pixel[2]), 1.0];
+
+            //image rows grow downwards
+            let u0 = px as i32 * scale;
+            let v0 = (image.height - 1 - py) as i32 * scale;
+
+            for du in 0..scale {
+                for dv in 0..scale {
+                    let u = u0 + du;
+                    let v = v0 + dv;
+                    instance_list.push(voxel(
+                        [
+                            origin[0] + u * run[0] + v * up[0],
+                            origin[1] + u * run[1] + v * up[1],
+                            origin[2] + u * run[2] + v * up[2],
+                        ],
+                        color,
+                        normal,
+                    ));
+                }
+            }
+        }
+    }
+
+    instance_list
+}
+
+fn brightness(pixel: [u8; 4]) -> f32 {
+    (0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32) / 255.0
+}
+
+//brightness becomes column height on the xz plane, transparent pixels leave holes
+pub fn heightmap(image: &Image, origin: [i32; 3], scale: i32, height: i32, color: [f32; 4]) -> Vec<Instance> {
+
+    let scale = scale.max(1);
+    let column = |px: i32, py: i32| -> i32 {
+        if px < 0 || py < 0 || px >= image.width as i32 || py >= image.height as i32 {
+            return 0;
+        }
+        let pixel = image.pixel(px as u32, py as u32);
+        if pixel[3] < 128 {
+            return 0;
+        }
+        (brightness(pixel) * height as f32).round() as i32 + 1
+    };
+
+    let mut instance_list: Vec<Instance> = Default::default();
+
+    for py in 0..image.height as i32 {
+        for px in 0..image.width as i32 {
+
+            let h = column(px, py);
+
+            //sides facing a lower neighbor keep their normal so the outline pass picks up the steps
+            let side = [
+                (column(px - 1, py), [-1.0, 0.0, 0.0]),
+                (column(px + 1, py), [1.0, 0.0, 0.0]),
+                (column(px, py - 1), [0.0, 0.0, -1.0]),
+                (column(px, py + 1), [0.0, 0.0, 1.0]),
+            ];
+
+            for y in 0..h {
+
+                let normal = if y == h - 1 {
+                    [0.0, 1.0, 0.0]
+                } else {
+                    match side.iter().find(|(n, _)| *n <= y) {
+                        Some((_, normal)) => *normal,
+                        //buried inside the terrain, never visible
+                        None => continue,
+                    }
+                };
+
+                for dx in 0..scale {
+                    for dz in 0..scale {
+                        instance_list.push(voxel(
+                            [origin[0] + px * scale + dx, origin[1] + y, origin[2] + py * scale + dz],
+                            color,
+                            normal,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    instance_list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32, color_type: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Default::default();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(data).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn mosaic_keeps_opaque_pixels_upright() {
+        let data = [
+            255, 0, 0, 255, 0, 0, 0, 0,
+            255, 255, 255, 255, 0, 0, 0, 255,
+        ];
+        let image = decode_png(&png(2, 2, png::ColorType::Rgba, &data)[..]).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+
+        let voxel_list = mosaic(&image, [10, 0, 0], &ImageStyle::new());
+        assert_eq!(voxel_list.len(), 3);
+        //the top row of the image is the top of the mosaic
+        let red = voxel_list.iter().find(|v| v.grid_position() == [10, 1, 0]).unwrap();
+        assert_eq!(red.color, [1.0, 0.0, 0.0, 1.0].into());
+        assert!(voxel_list.iter().all(|v| v.grid_position() != [11, 1, 0]));
+        assert!(voxel_list.iter().all(|v| v.normal == [0.0, 0.0, 1.0].into()));
+
+        let style = ImageStyle { scale: 2, ..ImageStyle::new() };
+        assert_eq!(mosaic(&image, [0, 0, 0], &style).len(), 12);
+    }
+
+    #[test]
+    fn heightmap_raises_bright_pixels_and_skips_buried_voxels() {
+        let mut data = [0; 9];
+        data[4] = 255;
+        let image = decode_png(&png(3, 3, png::ColorType::Grayscale, &data)[..]).unwrap();
+
+        let voxel_list = heightmap(&image, [0, 0, 0], 1, 4, [1.0, 1.0, 1.0, 1.0]);
+        let column: Vec<i32> = voxel_list.iter().filter(|v| v.grid_position()[0] == 1 && v.grid_position()[2] == 1).map(|v| v.grid_position()[1]).collect();
+        //the dark ring is one voxel high, the bottom of the peak is hidden behind it
+        assert_eq!(column, vec![1, 2, 3, 4]);
+        assert_eq!(voxel_list.len(), 8 + 4);
+        assert!(voxel_list.iter().filter(|v| v.grid_position()[1] == 4).all(|v| v.normal == [0.0, 1.0, 0.0].into()));
+    }
+
+    #[test]
+    fn rejects_oversized_images_from_the_header() {
+        let side = MAX_IMAGE_SIDE + 1;
+        let data = vec![0; side as usize];
+        let result = decode_png(&png(side, 1, png::ColorType::Grayscale, &data)[..]);
+        assert_eq!(result.err(), Some(format!("Image larger than {}x{}", MAX_IMAGE_SIDE, MAX_IMAGE_SIDE)));
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, HashMap};
+
+use iced_winit::winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode, WindowEvent};
+use serde::{Deserialize, Serialize};
+
+//read at startup from the working directory, written by /bind save
+pub const BINDING_FILE: &str = "bindings.toml";
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    CycleMode,
+    PriorModel,
+    NextModel,
+    ToggleConsole,
+    //also turns the scroll wheel into zoom
+    Delete,
+    Snap,
+    Place,
+    Look,
+    FastMove,
+    SlowMove,
+}
+
+const ACTION_LIST: [(&str, Action); 16] = [
+    ("MoveForward", Action::MoveForward),
+    ("MoveBackward", Action::MoveBackward),
+    ("MoveLeft", Action::MoveLeft),
+    ("MoveRight", Action::MoveRight),
+    ("MoveUp", Action::MoveUp),
+    ("MoveDown", Action::MoveDown),
+    ("CycleMode", Action::CycleMode),
+    ("PriorModel", Action::PriorModel),
+    ("NextModel", Action::NextModel),
+    ("ToggleConsole", Action::ToggleConsole),
+    ("Delete", Action::Delete),
+    ("Snap", Action::Snap),
+    ("Place", Action::Place),
+    ("Look", Action::Look),
+    ("FastMove", Action::FastMove),
+    ("SlowMove", Action::SlowMove),
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        ACTION_LIST.iter().find(|(_, a)| a == self).map(|(n, _)| *n).unwrap()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTION_LIST.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, a)| *a)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Input {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+//names as written in the binding file, the same as the winit key codes
+macro_rules! key_list {
+    ($($key:ident),* $(,)?) => {
+        [$((stringify!($key), VirtualKeyCode::$key)),*]
+    };
+}
+
+const KEY_LIST: &[(&str, VirtualKeyCode)] = &key_list![
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Space, Tab, Return, Back, Delete, Insert, Home, End, PageUp, PageDown,
+    Up, Down, Left, Right,
+    LShift, RShift, LControl, RControl, LAlt, RAlt,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    Minus, Equals, LBracket, RBracket, Semicolon, Apostrophe, Comma, Period, Slash, Backslash, Grave,
+];
+
+const MOUSE_LIST: [(&str, MouseButton); 3] = [
+    ("MouseLeft", MouseButton::Left),
+    ("MouseRight", MouseButton::Right),
+    ("MouseMiddle", MouseButton::Middle),
+];
+
+impl Input {
+    pub fn name(&self) -> String {
+        match self {
+            Input::Key(key) => KEY_LIST.iter().find(|(_, k)| k == key).map(|(n, _)| n.to_string()).unwrap_or(format!("{:?}", key)),
+            Input::Mouse(button) => MOUSE_LIST.iter().find(|(_, b)| b == button).map(|(n, _)| n.to_string()).unwrap_or(format!("{:?}", button)),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        KEY_LIST
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, k)| Input::Key(*k))
+            .or_else(|| MOUSE_LIST.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, b)| Input::Mouse(*b)))
+    }
+}
+
+//an input plus the modifiers that have to be held with it, written like Ctrl+Shift+S
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Binding {
+    pub input: Input,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Binding {
+    pub fn new(input: Input) -> Self {
+        Self { input, ctrl: false, shift: false, alt: false }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut part_list: Vec<&str> = text.split('+').collect();
+        let input_name = part_list.pop().unwrap_or_default();
+        let input = Input::from_name(input_name).ok_or(format!("Unknown key {}", input_name))?;
+
+        let mut binding = Binding::new(input);
+        for part in part_list {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" => binding.alt = true,
+                _ => return Err(format!("Unknown modifier {}", part)),
+            }
+        }
+        Ok(binding)
+    }
+
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        if self.ctrl { name += "Ctrl+"; }
+        if self.shift { name += "Shift+"; }
+        if self.alt { name += "Alt+"; }
+        name + &self.input.name()
+    }
+
+    fn modifier_count(&self) -> usize {
+        [self.ctrl, self.shift, self.alt].iter().filter(|m| **m).count()
+    }
+
+    fn held(&self, modifiers: ModifiersState) -> bool {
+        (!self.ctrl || modifiers.ctrl()) && (!self.shift || modifiers.shift()) && (!self.alt || modifiers.alt())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Target {
+    Action(Action),
+    //run through the command line as if typed
+    Command(String),
+}
+
+impl Target {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.starts_with('/') {
+            return Ok(Target::Command(text.to_string()));
+        }
+        Action::from_name(text).map(Target::Action).ok_or(format!("Unknown action {}", text))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Target::Action(action) => action.name().to_string(),
+            Target::Command(command) => command.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BindingFile {
+    bind: BTreeMap<String, String>,
+}
+
+pub struct InputMap {
+    binding_hash: HashMap<Binding, Target>,
+    modifiers: ModifiersState,
+    //what each held input started, so releasing it ends the same thing even if the modifiers changed
+    held_hash: HashMap<Input, Target>,
+}
+
+impl InputMap {
+
+    pub fn new() -> Self {
+        let mut input_map = Self { binding_hash: Default::default(), modifiers: Default::default(), held_hash: Default::default() };
+        input_map.reset();
+        input_map
+    }
+
+    //the layout the engine always shipped with
+    pub fn reset(&mut self) {
+        self.binding_hash = [
+            (Input::Key(VirtualKeyCode::W), Action::MoveForward),
+            (Input::Key(VirtualKeyCode::S), Action::MoveBackward),
+            (Input::Key(VirtualKeyCode::A), Action::MoveLeft),
+            (Input::Key(VirtualKeyCode::D), Action::MoveRight),
+            (Input::Key(VirtualKeyCode::Space), Action::MoveUp),
+            (Input::Key(VirtualKeyCode::LShift), Action::MoveDown),
+            (Input::Key(VirtualKeyCode::Tab), Action::CycleMode),
+            (Input::Key(VirtualKeyCode::Q), Action::PriorModel),
+            (Input::Key(VirtualKeyCode::E), Action::NextModel),
+            (Input::Key(VirtualKeyCode::F3), Action::ToggleConsole),
+            (Input::Key(VirtualKeyCode::LControl), Action::Delete),
+            (Input::Key(VirtualKeyCode::LAlt), Action::Snap),
+            (Input::Mouse(MouseButton::Left), Action::Place),
+            (Input::Mouse(MouseButton::Right), Action::Look),
+            (Input::Key(VirtualKeyCode::R), Action::FastMove),
+            (Input::Key(VirtualKeyCode::F), Action::SlowMove),
+        ]
+        .into_iter()
+        .map(|(input, action)| (Binding::new(input), Target::Action(action)))
+        .collect();
+        self.held_hash.clear();
+    }
+
+    //a present file replaces the defaults completely, so keys can also be freed
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let file: BindingFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut input_map = Self::new();
+        input_map.binding_hash.clear();
+        for (binding, target) in file.bind {
+            input_map.bind(&binding, &target)?;
+        }
+        Ok(input_map)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let bind = self.binding_hash.iter().map(|(b, t)| (b.name(), t.name())).collect();
+        toml::to_string(&BindingFile { bind }).unwrap_or_default()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_toml(&text)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_toml()).map_err(|e| e.to_string())
+    }
+
+    //target none frees the binding
+    pub fn bind(&mut self, binding: &str, target: &str) -> Result<(), String> {
+        let binding = Binding::parse(binding)?;
+        if target.eq_ignore_ascii_case("none") {
+            self.binding_hash.remove(&binding);
+            return Ok(());
+        }
+        self.binding_hash.insert(binding, Target::parse(target)?);
+        Ok(())
+    }
+
+    //one line per binding, sorted for display
+    pub fn binding_list(&self) -> Vec<String> {
+        let mut binding_list: Vec<String> = self.binding_hash.iter().map(|(b, t)| format!("{} = {}", b.name(), t.name())).collect();
+        binding_list.sort();
+        binding_list
+    }
+
+    //the binding needing the most held modifiers wins, so Ctrl+S can sit on top of S
+    fn find(&self, input: Input) -> Option<&Target> {
+        self.binding_hash
+            .iter()
+            .filter(|(b, _)| b.input == input && b.held(self.modifiers))
+            .max_by_key(|(b, _)| b.modifier_count())
+            .map(|(_, t)| t)
+    }
+
+    pub fn press(&mut self, input: Input) -> Option<Target> {
+        let target = self.find(input)?.clone();
+        self.held_hash.insert(input, target.clone());
+        Some(target)
+    }
+
+    pub fn release(&mut self, input: Input) -> Option<Target> {
+        self.held_hash.remove(&input)
+    }
+
+    //what the event started or ended, with true for a press
+    pub fn process(&mut self, event: &WindowEvent) -> Option<(Target, bool)> {
+        let (input, state) = match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                return None;
+            }
+            WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode: Some(key), .. }, .. } => (Input::Key(*key), *state),
+            WindowEvent::MouseInput { button, state, .. } => (Input::Mouse(*button), *state),
+            _ => return None,
+        };
+        match state {
+            //held keys repeat, only the first press counts
+            ElementState::Pressed if self.held_hash.contains_key(&input) => None,
+            ElementState::Pressed => self.press(input).map(|t| (t, true)),
+            ElementState::Released => self.release(input).map(|t| (t, false)),
+        }
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bindings_and_targets() {
+        let binding = Binding::parse("ctrl+shift+s").unwrap();
+        assert!(binding.ctrl && binding.shift && !binding.alt);
+        assert_eq!(binding.input, Input::Key(VirtualKeyCode::S));
+        assert_eq!(binding.name(), "Ctrl+Shift+S");
+        assert_eq!(Binding::parse("MouseRight").unwrap().input, Input::Mouse(MouseButton::Right));
+        assert!(Binding::parse("Hyper+S").is_err());
+        assert!(Binding::parse("NoSuchKey").is_err());
+
+        assert_eq!(Target::parse("moveforward").unwrap(), Target::Action(Action::MoveForward));
+        assert_eq!(Target::parse("/save world").unwrap(), Target::Command("/save world".to_string()));
+        assert!(Target::parse("Fly").is_err());
+    }
+
+    #[test]
+    fn modifiers_pick_the_most_specific_binding() {
+        let mut input_map = InputMap::new();
+        input_map.bind("Ctrl+S", "/save world").unwrap();
+
+        let s = Input::Key(VirtualKeyCode::S);
+        assert_eq!(input_map.press(s), Some(Target::Action(Action::MoveBackward)));
+        input_map.release(s);
+
+        input_map.modifiers = ModifiersState::CTRL;
+        assert_eq!(input_map.press(s), Some(Target::Command("/save world".to_string())));
+        //the release ends what the press started
+        input_map.modifiers = ModifiersState::empty();
+        assert_eq!(input_map.release(s), Some(Target::Command("/save world".to_string())));
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let mut input_map = InputMap::new();
+        //an azerty layout
+        input_map.bind("W", "none").unwrap();
+        input_map.bind("Z", "MoveForward").unwrap();
+        input_map.bind("Q", "MoveLeft").unwrap();
+        input_map.bind("A", "PriorModel").unwrap();
+
+        let loaded = InputMap::from_toml(&input_map.to_toml()).unwrap();
+        assert_eq!(loaded.binding_list(), input_map.binding_list());
+        assert!(loaded.find(Input::Key(VirtualKeyCode::W)).is_none());
+        assert_eq!(loaded.find(Input::Key(VirtualKeyCode::Z)), Some(&Target::Action(Action::MoveForward)));
+
+        assert!(InputMap::from_toml("[bind]\nW = \"Jump\"\n").is_err());
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{mpsc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicU32, Ordering};
+#[cfg(target_arch = "wasm32")]
+use iced_winit::time::Instant;
+
+use crate::Instance;
+use crate::material_list::MaterialList;
+use crate::model_list;
+use crate::terrain::Terrain;
+use crate::vegetation;
+
+//boxes up to this many voxels are still placed on the spot, the indicator relies on it
+pub const PLACE_JOB_VOLUME: i64 = 64 * 64 * 64;
+
+//columns per terrain step and x layers per place step
+const TERRAIN_PART_SIDE: i32 = 64;
+const PLACE_SLAB: i32 = 8;
+
+//time given to jobs each frame when there are no threads to run them on
+#[cfg(target_arch = "wasm32")]
+const FRAME_BUDGET_MS: u128 = 6;
+
+//one piece of a job, runs off the main thread and returns voxels ready to place
+//the instance buffers are still rebuilt on the main thread once they are placed, it owns the device
+pub type Step = Box<dyn FnOnce() -> Vec<Instance> + Send>;
+
+pub struct Job {
+    id: u32,
+    epoch: u32,
+    step_list: VecDeque<Step>,
+    total: usize,
+}
+
+//the result of one step, handed back in step order
+pub struct JobUpdate {
+    pub id: u32,
+    epoch: u32,
+    pub done: usize,
+    pub total: usize,
+    pub instance_list: Vec<Instance>,
+}
+
+impl JobUpdate {
+    pub fn finished(&self) -> bool {
+        self.done == self.total
+    }
+}
+
+struct Progress {
+    id: u32,
+    name: String,
+    done: usize,
+    total: usize,
+}
+
+pub struct JobSystem {
+    next_id: u32,
+    progress_list: Vec<Progress>,
+
+    //bumped by cancel_all, jobs and updates from an older epoch are dropped
+    #[cfg(not(target_arch = "wasm32"))]
+    epoch: Arc<AtomicU32>,
+    #[cfg(target_arch = "wasm32")]
+    epoch: u32,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    job_sender: mpsc::Sender<Job>,
+    #[cfg(not(target_arch = "wasm32"))]
+    update_receiver: mpsc::Receiver<JobUpdate>,
+
+    #[cfg(target_arch = "wasm32")]
+    queue: VecDeque<Job>,
+}
+
+impl Job {
+    fn run_step(&mut self) -> Option<JobUpdate> {
+        let step = self.step_list.pop_front()?;
+        Some(JobUpdate {
+            id: self.id,
+            epoch: self.epoch,
+            done: self.total - self.step_list.len(),
+            total: self.total,
+            instance_list: step(),
+        })
+    }
+}
+
+impl JobSystem {
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new() -> Self {
+
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (update_sender, update_receiver) = mpsc::channel::<JobUpdate>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let epoch = Arc::new(AtomicU32::new(0));
+
+        //one core is left to the event loop
+        let worker_count = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2).saturating_sub(1).max(1);
+
+        for i in 0..worker_count {
+            let job_receiver = job_receiver.clone();
+            let update_sender = update_sender.clone();
+            let epoch = epoch.clone();
+            std::thread::Builder::new()
+                .name(format!("job worker {}", i))
+                .spawn(move || loop {
+                    //a whole job stays on one worker so its steps arrive in order
+                    let job = job_receiver.lock().unwrap().recv();
+                    let mut job = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    //a cancelled job stops at its next step
+                    while job.epoch == epoch.load(Ordering::Relaxed) {
+                        let update = match job.run_step() {
+                            Some(update) => update,
+                            None => break,
+                        };
+                        if update_sender.send(update).is_err() {
+                            return;
+                        }
+                    }
+                })
+                .expect("failed to spawn job worker");
+        }
+
+        Self { next_id: 0, progress_list: Default::default(), epoch, job_sender, update_receiver }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn new() -> Self {
+        Self { next_id: 0, progress_list: Default::default(), epoch: 0, queue: Default::default() }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn epoch(&self) -> u32 {
+        self.epoch.load(Ordering::Relaxed)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    //drops every running job, their finished steps still in flight are thrown away by poll
+    pub fn cancel_all(&mut self) {
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.epoch.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.epoch += 1;
+            self.queue.clear();
+        }
+
+        self.progress_list.clear();
+    }
+
+    pub fn submit(&mut self, name: &str, step_list: Vec<Step>) -> u32 {
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let job = Job { id, epoch: self.epoch(), total: step_list.len(), step_list: step_list.into() };
+        self.progress_list.push(Progress { id, name: name.to_string(), done: 0, total: job.total });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.job_sender.send(job).expect("job workers stopped");
+
+        #[cfg(target_arch = "wasm32")]
+        self.queue.push_back(job);
+
+        id
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn receive(&mut self) -> Vec<JobUpdate> {
+        self.update_receiver.try_iter().collect()
+    }
+
+    //no threads here, steps run on the main thread until the frame budget is used up
+    #[cfg(target_arch = "wasm32")]
+    fn receive(&mut self) -> Vec<JobUpdate> {
+        let start = Instant::now();
+        let mut update_list: Vec<JobUpdate> = Default::default();
+        while let Some(job) = self.queue.front_mut() {
+            match job.run_step() {
+                Some(update) => update_list.push(update),
+                None => {
+                    self.queue.pop_front();
+                }
+            }
+            if start.elapsed().as_millis() >= FRAME_BUDGET_MS {
+                break;
+            }
+        }
+        update_list
+    }
+
+    //finished steps since the last call, to be placed by the caller
+    pub fn poll(&mut self) -> Vec<JobUpdate> {
+
+        let epoch = self.epoch();
+        let mut update_list = self.receive();
+        update_list.retain(|u| u.epoch == epoch);
+
+        update_list.iter().for_each(|u| {
+            if let Some(p) = self.progress_list.iter_mut().find(|p| p.id == u.id) {
+                p.done = u.done;
+            }
+        });
+        self.progress_list.retain(|p| p.done < p.total);
+
+        update_list
+    }
+
+    //one line per running job, empty when idle
+    pub fn progress_text(&self) -> String {
+        self.progress_list
+            .iter()
+            .map(|p| format!("{} {}%", p.name, p.done * 100 / p.total.max(1)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl Default for JobSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn terrain_job(terrain: Terrain, material_list: &MaterialList) -> Vec<Step> {
+
+    let terrain = Arc::new(terrain);
+    let material_list = Arc::new(material_list.clone());
+
+    terrain
+        .part_list(TERRAIN_PART_SIDE)
+        .into_iter()
+        .map(|(first, last)| {
+            let terrain = terrain.clone();
+            let material_list = material_list.clone();
+            Box::new(move || terrain.generate_part(first, last, &material_list)) as Step
+        })
+        .collect()
+}
+
+//the same shapes ChunkManager::place builds, cut into x slabs
+pub fn place_job(first: [i32; 3], last: [i32; 3], color: [f32; 4], id: i32, material: usize, material_list: &MaterialList) -> Vec<Step> {
+
+    //a plant is grown in one piece, its branches do not split along slabs
+    if let Some(plant) = vegetation::Plant::from_model(id) {
+        let material_list = material_list.clone();
+        let seed = vegetation::hash(0, [first[0], 0, first[2]]);
+        return vec![Box::new(move || {
+            vegetation::grow(plant, seed, first, last, material, &material_list).into_values().collect()
+        })];
+    }
+
+    (first[0]..=last[0])
+        .step_by(PLACE_SLAB as usize)
+        .map(|x_first| {
+            let x_last = (x_first + PLACE_SLAB - 1).min(last[0]);
+            Box::new(move || {
+                let mut instance_list: Vec<Instance> = Default::default();
+                for x in x_first..=x_last {
+                    for y in first[1]..=last[1] {
+                        for z in first[2]..=last[2] {
+                            if let Some(mut ins) = model_list::parse_place(x, y, z, first, last, color, id) {
+                                ins.material = material;
+                                instance_list.push(ins);
+                            }
+                        }
+                    }
+                }
+                instance_list
+            }) as Step
+        })
+        .collect()
+}
+
+pub fn volume(first: [i32; 3], last: [i32; 3]) -> i64 {
+    (0..3).map(|i| ((last[i] - first[i]).abs() + 1) as i64).product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_come_back_in_order() {
+        let mut job_system = JobSystem::new();
+        let step_list = place_job([0, 0, 0], [19, 1, 1], [1.0, 1.0, 1.0, 1.0], 12, 0, &MaterialList::new());
+        assert_eq!(step_list.len(), 3);
+        job_system.submit("test", step_list);
+        assert!(!job_system.progress_text().is_empty());
+
+        let mut update_list: Vec<JobUpdate> = Default::default();
+        for _ in 0..1000 {
+            update_list.extend(job_system.poll());
+            if update_list.iter().any(|u| u.finished()) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let done_list: Vec<usize> = update_list.iter().map(|u| u.done).collect();
+        assert_eq!(done_list, vec![1, 2, 3]);
+        assert_eq!(update_list.iter().map(|u| u.instance_list.len()).sum::<usize>(), 20 * 2 * 2);
+        assert!(job_system.progress_text().is_empty());
+    }
+
+    #[test]
+    fn cancelled_jobs_are_dropped() {
+        let mut job_system = JobSystem::new();
+        let slow = |n: usize| -> Vec<Step> {
+            (0..n)
+                .map(|_| {
+                    Box::new(|| {
+                        std::thread::sleep(std::time::Duration::from_millis(5));
+                        vec![]
+                    }) as Step
+                })
+                .collect()
+        };
+        job_system.submit("old", slow(20));
+        job_system.cancel_all();
+        assert!(job_system.progress_text().is_empty());
+
+        let id = job_system.submit("new", slow(2));
+        let mut update_list: Vec<JobUpdate> = Default::default();
+        for _ in 0..1000 {
+            update_list.extend(job_system.poll());
+            if update_list.iter().any(|u| u.id == id && u.finished()) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(update_list.iter().all(|u| u.id == id));
+        assert_eq!(update_list.len(), 2);
+        //the old job stopped instead of running its remaining steps
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(job_system.poll().is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use iced_wgpu::wgpu;
+
+//read before the command line, so flags win over the file
+pub const LAUNCH_FILE: &str = "launch.toml";
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum WindowMode {
+    Windowed,
+    //fullscreen at the desktop resolution without a mode switch
+    Borderless,
+    Exclusive,
+}
+
+impl WindowMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "windowed" => Some(WindowMode::Windowed),
+            "borderless" => Some(WindowMode::Borderless),
+            "exclusive" | "fullscreen" => Some(WindowMode::Exclusive),
+            _ => None,
+        }
+    }
+}
+
+//how the window is opened, see run
+pub struct LaunchOptions {
+    pub window_mode: WindowMode,
+    //window size, or the video mode closest to it when exclusive
+    pub resolution: Option<[u32; 2]>,
+    pub monitor: usize,
+    //overrides the settings file for this run only
+    pub present_mode: Option<wgpu::PresentMode>,
+    //clamped to the settings range by State::new
+    pub sample_ratio: f32,
+    //the old kiosk behaviour, quit when fullscreen was refused
+    pub exit_without_fullscreen: bool,
+}
+
+pub const USAGE: &str = "Usage: [--windowed | --borderless | --exclusive] [--resolution WxH] [--monitor n] \
+[--vsync on|off] [--present fifo|mailbox|immediate] [--sample-ratio x] [--exit-without-fullscreen] [--config file]";
+
+fn parse_resolution(text: &str) -> Result<[u32; 2], String> {
+    let (w, h) = text.split_once('x').ok_or("Resolution must be WxH")?;
+    let w = w.parse::<u32>().map_err(|e| e.to_string())?;
+    let h = h.parse::<u32>().map_err(|e| e.to_string())?;
+    if w == 0 || h == 0 {
+        return Err("Resolution must not be zero".to_string());
+    }
+    Ok([w, h])
+}
+
+fn parse_bool(text: &str) -> Result<bool, String> {
+    match text {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err("Expected on or off, got ".to_string() + text),
+    }
+}
+
+impl LaunchOptions {
+    pub fn new() -> Self {
+        Self {
+            window_mode: WindowMode::Exclusive,
+            resolution: None,
+            monitor: 0,
+            present_mode: None,
+            sample_ratio: 2.0,
+            exit_without_fullscreen: false,
+        }
+    }
+
+    //one option by its file key, the flags go through here too
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "window" => {
+                self.window_mode = WindowMode::from_name(value).ok_or("Window must be windowed, borderless or exclusive")?;
+            }
+            "resolution" => self.resolution = Some(parse_resolution(value)?),
+            "monitor" => self.monitor = value.parse::<usize>().map_err(|e| e.to_string())?,
+            "vsync" => {
+                self.present_mode = Some(if parse_bool(value)? { wgpu::PresentMode::Fifo } else { wgpu::PresentMode::Immediate });
+            }
+            "present_mode" => {
+                self.present_mode = Some(match value {
+                    "fifo" => wgpu::PresentMode::Fifo,
+                    "mailbox" => wgpu::PresentMode::Mailbox,
+                    "immediate" => wgpu::PresentMode::Immediate,
+                    _ => return Err("Present mode must be fifo, mailbox or immediate".to_string()),
+                });
+            }
+            "sample_ratio" => {
+                self.sample_ratio = value.parse::<f32>().map_err(|e| e.to_string())?;
+            }
+            "exit_without_fullscreen" => self.exit_without_fullscreen = parse_bool(value)?,
+            _ => return Err("Unknown option ".to_string() + key),
+        }
+        Ok(())
+    }
+
+    //a flat table of the same keys set takes, e.g. window = "windowed"
+    pub fn apply_toml(&mut self, text: &str) -> Result<(), String> {
+        let table: toml::value::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+        for (key, value) in table.iter() {
+            let value = match value {
+                toml::Value::String(s) => s.clone(),
+                toml::Value::Boolean(b) => (if *b { "on" } else { "off" }).to_string(),
+                other => other.to_string(),
+            };
+            self.set(key, &value)?;
+        }
+        Ok(())
+    }
+
+    //args without the program name
+    pub fn apply_args(&mut self, args: &[String]) -> Result<(), String> {
+        let mut i = 0;
+        while i < args.len() {
+            let value = args.get(i + 1).map(|s| s.as_str());
+            let need = || value.ok_or_else(|| "Missing value for ".to_string() + &args[i]);
+            match args[i].as_str() {
+                "--windowed" => self.window_mode = WindowMode::Windowed,
+                "--borderless" => self.window_mode = WindowMode::Borderless,
+                "--exclusive" | "--fullscreen" => self.window_mode = WindowMode::Exclusive,
+                "--exit-without-fullscreen" => self.exit_without_fullscreen = true,
+                //read by load before the rest
+                "--config" => {
+                    need()?;
+                    i += 1;
+                }
+                flag @ ("--resolution" | "--monitor" | "--vsync" | "--present" | "--sample-ratio") => {
+                    let key = match flag {
+                        "--present" => "present_mode",
+                        "--sample-ratio" => "sample_ratio",
+                        _ => &flag[2..],
+                    };
+                    self.set(key, need()?)?;
+                    i += 1;
+                }
+                other => return Err(format!("Unknown flag {}\n{}", other, USAGE)),
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    //defaults, then the launch file, then the command line
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Result<Self, String> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+
+        let path = args.iter().position(|a| a == "--config").and_then(|i| args.get(i + 1)).map(|s| s.as_str());
+
+        let mut options = Self::new();
+        match path {
+            Some(path) => options.apply_toml(&std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?)?,
+            None if std::path::Path::new(LAUNCH_FILE).exists() => {
+                let text = std::fs::read_to_string(LAUNCH_FILE).map_err(|e| e.to_string())?;
+                options.apply_toml(&text).map_err(|e| format!("{}: {}", LAUNCH_FILE, e))?;
+            }
+            None => {}
+        }
+        options.apply_args(&args)?;
+        Ok(options)
+    }
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn flags_override_the_file() {
+        let mut options = LaunchOptions::new();
+        options.apply_toml("window = \"borderless\"\nresolution = \"1280x720\"\nvsync = false\nsample_ratio = 3\n").unwrap();
+        assert_eq!(options.window_mode, WindowMode::Borderless);
+        assert_eq!(options.present_mode, Some(wgpu::PresentMode::Immediate));
+        assert_eq!(options.sample_ratio, 3.0);
+
+        options.apply_args(&args("--windowed --resolution 800x600 --present mailbox --sample-ratio 9 --config x.toml")).unwrap();
+        assert_eq!(options.window_mode, WindowMode::Windowed);
+        assert_eq!(options.resolution, Some([800, 600]));
+        assert_eq!(options.present_mode, Some(wgpu::PresentMode::Mailbox));
+        assert_eq!(options.sample_ratio, 9.0);
+        assert!(!options.exit_without_fullscreen);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let mut options = LaunchOptions::new();
+        assert!(options.apply_args(&args("--resolution 800")).is_err());
+        assert!(options.apply_args(&args("--monitor")).is_err());
+        assert!(options.apply_args(&args("--fast")).is_err());
+        assert!(options.apply_toml("window = \"tiny\"").is_err());
+    }
+}
@@ -1,15 +1,40 @@
 mod camera;
+mod camera_mode;
+mod input;
 mod shell;
 mod command;
 mod chunk;
 mod model_list;
 mod brush_list;
+mod font_list;
+mod import;
+mod raycast;
+mod query;
+mod material_list;
+mod light;
+mod world;
+mod clock;
+mod logic_list;
+mod tick;
+mod terrain;
+mod vegetation;
+mod job;
+mod stream;
+mod lod;
+mod frustum;
+mod camera_path;
+mod render_target;
+mod launch;
+mod settings;
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
 
 use cgmath::*;
 
 use chunk::ChunkType;
+use query::VoxelQuery;
 use shell::Controls;
-use shell::Message::{FrameUpdate,Update,ServerLog,CommandParsed};
+use shell::Message::{FrameUpdate,TickUpdate,CullUpdate,JobProgress,Update,ServerLog,CommandParsed};
 use command::Descriptor;
 use std::iter;
 
@@ -100,13 +125,6 @@ impl Vertex_tex {
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
-pub enum VoxelType {
-    Object,
-    Fire,
-    Tree,
-}
-
 #[derive(Copy, Clone, PartialEq)]
 pub struct Instance {
 
@@ -118,11 +136,29 @@ pub struct Instance {
     normal_strength:f32,
     light_strength:f32,
 
-    current_type:VoxelType,
+    //index into the material list
+    material:usize,
 }
 
 // NEW!
 impl Instance {
+
+    //a voxel with the default strengths
+    pub fn new(position: [i32;3], color: [f32;4], normal: [f32;3], material: usize) -> Self {
+        Self {
+            position: cgmath::Vector3 { x: position[0] as f32, y: position[1] as f32, z: position[2] as f32 },
+            color: color.into(),
+            normal: normal.into(),
+            depth_strength: 0.5,
+            normal_strength: 1.0,
+            light_strength: 0.0,
+            material,
+        }
+    }
+
+    pub fn grid_position(&self) -> [i32;3] {
+        [self.position.x as i32, self.position.y as i32, self.position.z as i32]
+    }
     
     pub fn to_raw(&self) -> InstanceRaw {
         InstanceRaw {
@@ -131,6 +167,7 @@ impl Instance {
             normal: [self.normal.x,self.normal.y,self.normal.z],
             depth_strength: self.depth_strength,
             normal_strength: self.normal_strength,
+            material: self.material as u32,
         }
     }
 }
@@ -144,6 +181,7 @@ pub struct InstanceRaw {
     normal: [f32; 3],
     depth_strength: f32,
     normal_strength:f32,
+    material:u32,
 }
 
 impl InstanceRaw {
@@ -193,6 +231,11 @@ impl InstanceRaw {
                     shader_location: 12,
                     format: wgpu::VertexFormat::Float32,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 25]>() as wgpu::BufferAddress,
+                    shader_location: 13,
+                    format: wgpu::VertexFormat::Uint32,
+                },
             ],
         }
     }
@@ -201,6 +244,11 @@ impl InstanceRaw {
 pub struct State {
 
     time:f64,
+    clock: clock::WorldClock,
+    simulation: logic_list::Simulation,
+    tick_clock: tick::TickClock,
+    job_system: job::JobSystem,
+    clear_color: wgpu::Color,
 
     iced_state: program::State<shell::Controls>,
     clipboard: Clipboard,
@@ -242,15 +290,13 @@ pub struct State {
 
     light_buffer: wgpu::Buffer,
     light_bind_group: wgpu::BindGroup,
+
+    material_buffer: wgpu::Buffer,
+    material_bind_group: wgpu::BindGroup,
     
-    diffuse_bind_group: wgpu::BindGroup,
-    depth_bind_group: wgpu::BindGroup,
-    normal_bind_group: wgpu::BindGroup,
-
-    diffuse_texture_view:wgpu::TextureView,
-    normal_texture_view:wgpu::TextureView,
-    depth_texture_view:wgpu::TextureView,
-    depth_test_texture_view:wgpu::TextureView,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group_layout_depth: wgpu::BindGroupLayout,
+    targets: render_target::RenderTargets,
     //msaa_texture_view:wgpu::TextureView,
 
     renderer: Renderer,
@@ -260,6 +306,10 @@ pub struct State {
     framerate_timer: f32,
     framerate_count: i32,
 
+    //chunks drawn and culled by the last frame
+    chunk_drawn: u32,
+    chunk_culled: u32,
+
     normal_texture_flag: bool,
     diffuse_texture_flag: bool,
     depth_texture_flag: bool,
@@ -271,10 +321,18 @@ pub struct State {
     vertex_texture: [Vertex_tex;6],
     sample_ratio: f32,
 
+    //tunables from the settings file, see /set
+    settings: settings::Settings,
+    present_mode_list: Vec<wgpu::PresentMode>,
+
+    //frames of a playing camera path, see /path record
+    #[cfg(not(target_arch = "wasm32"))]
+    recording: Option<capture::Recording>,
+
     
 }
 impl State {
-    async fn new(window: &Window,scr_width:u32,scr_height:u32) -> Self {
+    async fn new(window: &Window,scr_width:u32,scr_height:u32,options:&launch::LaunchOptions) -> Self {
 
         
         let normal_texture_flag = false;
@@ -282,6 +340,19 @@ impl State {
         let diffuse_texture_flag = false;
         let output_texture_flag = true;
 
+        //no file keeps the defaults
+        #[cfg(not(target_arch = "wasm32"))]
+        let settings = if std::path::Path::new(settings::SETTINGS_FILE).exists(){
+            settings::Settings::load(settings::SETTINGS_FILE).unwrap_or_else(|e|{
+                log::warn!("ignoring {}: {}", settings::SETTINGS_FILE, e);
+                settings::Settings::new()
+            })
+        } else {
+            settings::Settings::new()
+        };
+        #[cfg(target_arch = "wasm32")]
+        let settings = settings::Settings::new();
+
         let framerate_timer = 0.0;
         let framerate_count = 1;
         // Initialize staging belt
@@ -299,7 +370,7 @@ impl State {
         let instance = wgpu::Instance::new(wgpu::Backends::all());
         let surface = unsafe { instance.create_surface(window) };
 
-        let (format, (device, queue)) = futures::executor::block_on(async {
+        let (format, present_mode_list, (device, queue)) = futures::executor::block_on(async {
             let adapter = wgpu::util::initialize_adapter_from_env_or_default(
                 &instance,
                 backend,
@@ -323,6 +394,7 @@ impl State {
                     .first()
                     .copied()
                     .expect("Get preferred format"),
+                surface.get_supported_modes(&adapter),
                 adapter
                     .request_device(
                         &wgpu::DeviceDescriptor {
@@ -359,7 +431,8 @@ impl State {
         let clipboard = Clipboard::connect(&window);
 
         // Initialize scene and GUI controls
-        let control = Controls::new();
+        let mut control = Controls::new();
+        control.settings_list = settings.list().into_iter().map(|(k, v)| (k.to_string(), v)).collect();
         // Initialize iced
         let iced_state = program::State::new(
             control,
@@ -376,12 +449,21 @@ impl State {
             depth_or_array_layers: 1,
         };
 
+        //fifo is the one mode every surface has
+        let wanted_present_mode = options.present_mode.unwrap_or(settings.present_mode);
+        let present_mode = if present_mode_list.contains(&wanted_present_mode) {
+            wanted_present_mode
+        } else {
+            log::warn!("{:?} is not supported, using Fifo", wanted_present_mode);
+            wgpu::PresentMode::Fifo
+        };
+
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format,
             width: texture_size.width,
             height: texture_size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode,
         };
         surface.configure(&device, &config);
 
@@ -400,9 +482,24 @@ impl State {
             znear: 0.1,
             zfar: 10100.0,
             left: cgmath::Vector3::unit_y(),
+            projection: camera::Projection::Orthographic,
+            fov: camera::DEFAULT_FOV,
         };
 
-        let camera_controller = camera::CameraController::new(scr_width as f32 , scr_height as f32,300.0,0.002);
+        //only the native build changes it after creation, by loading the key bindings
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut camera_controller = camera::CameraController::new(scr_width as f32 , scr_height as f32,settings.camera_speed,settings.camera_sensitivity);
+        #[cfg(target_arch = "wasm32")]
+        let camera_controller = camera::CameraController::new(scr_width as f32 , scr_height as f32,settings.camera_speed,settings.camera_sensitivity);
+
+        //no file keeps the default layout
+        #[cfg(not(target_arch = "wasm32"))]
+        if std::path::Path::new(input::BINDING_FILE).exists(){
+            match input::InputMap::load(input::BINDING_FILE){
+                Ok(input_map) => camera_controller.input_map = input_map,
+                Err(e) => log::warn!("ignoring {}: {}", input::BINDING_FILE, e),
+            }
+        }
         let mut camera_uniform = camera::CameraUniform::new();
 
         camera_uniform.update_view_proj(&camera);
@@ -479,172 +576,6 @@ impl State {
             label: Some("uniform_bind_group"),
         });
 
-        #[cfg(not(target_arch = "wasm32"))]
-        let normal_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Bgra8UnormSrgb,//Bgra8UnormSrgb? Rgba8UnormSrgb
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("normal_texture"),
-            }
-        );
-
-
-
-        #[cfg(not(target_arch = "wasm32"))]
-        let diffuse_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("diffuse_texture"),
-            }
-        );
-
-        #[cfg(not(target_arch = "wasm32"))]
-        let depth_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::R32Float,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("depth_texture"),
-            }
-        );
-
-        /* 
-        #[cfg(not(target_arch = "wasm32"))]
-        let msaa_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("depth_texture"),
-            }
-        );
-        */
-
-        #[cfg(target_arch = "wasm32")]
-        let normal_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("normal_texture"),
-            }
-        );
-
-        #[cfg(target_arch = "wasm32")]
-        let diffuse_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                    
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("diffuse_texture"),
-            }
-        );
-
-        #[cfg(target_arch = "wasm32")]
-        let depth_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                    
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::R32Float,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("depth_texture"),
-            }
-        );
-        /* 
-        #[cfg(target_arch = "wasm32")]
-        let msaa_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 4,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                label: Some("depth_texture"),
-            }
-        );
-        */
-        let depth_test_texture = device.create_texture(
-            &wgpu::TextureDescriptor {
-                
-                size: texture_size,
-                mip_level_count: 1, 
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Depth32Float,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                | wgpu::TextureUsages::TEXTURE_BINDING,
-                label: Some("depth_test_texture"),
-            }
-        );
-        
-        let diffuse_texture_view = diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let normal_texture_view = normal_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let depth_texture_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let depth_test_texture_view = depth_test_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        //let msaa_texture_view = msaa_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-        let normal_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::Repeat,
-            address_mode_v: wgpu::AddressMode::Repeat,
-            address_mode_w: wgpu::AddressMode::Repeat,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-
-        let diffuse_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::Repeat,
-            address_mode_v: wgpu::AddressMode::Repeat,
-            address_mode_w: wgpu::AddressMode::Repeat,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-
-        let depth_sampler = device.create_sampler(&wgpu::SamplerDescriptor { 
-            address_mode_u: wgpu::AddressMode::Repeat,
-            address_mode_v: wgpu::AddressMode::Repeat,
-            address_mode_w: wgpu::AddressMode::Repeat,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-
-
         let texture_bind_group_layout =
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
@@ -691,62 +622,14 @@ impl State {
             label: Some("texture_bind_group_layout"),
         });
 
-        let diffuse_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&diffuse_texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
-                    }
-                ],
-                label: Some("diffuse_bind_group"),
-            }
-        );
-
-        let normal_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&normal_texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&normal_sampler),
-                    }
-                ],
-                label: Some("normal_bind_group"),
-            }
-        );
-
-        let depth_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout_depth,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&depth_texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&depth_sampler),
-                    }
-                ],
-                label: Some("depth_bind_group"),
-            }
-        );
+        //everything that has to match the window size, rebuilt by resize
+        let targets = render_target::RenderTargets::new(&device, texture_size, &texture_bind_group_layout, &texture_bind_group_layout_depth);
 
 
 
         let terrain_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("terrain_shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/terrain_shader/terrain_shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(light::shader_source(include_str!("shaders/terrain_shader/terrain_shader.wgsl")).into()),
         });
         
         let terrain_shader_normal = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -809,7 +692,7 @@ impl State {
             source: wgpu::ShaderSource::Wgsl(include_str!("shaders/blend_shader.wgsl").into()),
         });
 
-        let sample_ratio = 2.0;
+        let sample_ratio = options.sample_ratio.clamp(settings.sample_ratio_min, settings.sample_ratio_max);
 
         let vertex_texture: [Vertex_tex;6] = [
 
@@ -842,20 +725,20 @@ impl State {
 
         let chunk_manager = chunk::ChunkManager::new(&device);
         // We'll want to update our lights position, so we use COPY_DST
-        let light_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Light VB"),
-                contents: bytemuck::cast_slice(&chunk_manager.point_light_list),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            }
-        );
+        let light_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light VB"),
+            size: light::LightManager::buffer_size(),
+            usage: light::buffer_usage(),
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(&light_buffer, 0, &chunk_manager.light_manager.to_bytes());
 
         let light_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
                 visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
+                    ty: light::binding_type(),
                     has_dynamic_offset: false,
                     min_binding_size: None,
                 },
@@ -873,16 +756,43 @@ impl State {
             label: None,
         });
 
+        let material_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Material Buffer"),
+                contents: bytemuck::cast_slice(&chunk_manager.material_list.to_raw()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
 
+        let material_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("material_bind_group_layout"),
+        });
 
-
-
+        let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &material_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: material_buffer.as_entire_binding(),
+            }],
+            label: None,
+        });
 
         let render_terrain_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
             bind_group_layouts: &[
                 &camera_bind_group_layout,
                 &light_bind_group_layout,
+                &material_bind_group_layout,
             ],
             push_constant_ranges: &[],
         });
@@ -1239,6 +1149,11 @@ impl State {
         Self {
 
             time:0.0,
+            clock: clock::WorldClock::new(),
+            simulation: logic_list::Simulation::new(),
+            tick_clock: tick::TickClock::new(tick::DEFAULT_TICK_RATE),
+            job_system: job::JobSystem::new(),
+            clear_color: wgpu::Color { r: 0.01, g: 0.01, b: 0.01, a: 0.01 },
 
             iced_state,
             modifiers,
@@ -1281,15 +1196,13 @@ impl State {
 
             light_buffer,
             light_bind_group,
+
+            material_buffer,
+            material_bind_group,
             
-            diffuse_bind_group,
-            depth_bind_group,
-            normal_bind_group,
-
-            diffuse_texture_view,
-            depth_texture_view,
-            normal_texture_view,
-            depth_test_texture_view,
+            texture_bind_group_layout,
+            texture_bind_group_layout_depth,
+            targets,
             //msaa_texture_view,
 
             renderer,
@@ -1299,6 +1212,9 @@ impl State {
             framerate_timer,
             framerate_count,
 
+            chunk_drawn: 0,
+            chunk_culled: 0,
+
             cli_flag,
             normal_texture_flag,
             diffuse_texture_flag,
@@ -1310,6 +1226,12 @@ impl State {
             vertex_texture,
             sample_ratio,
 
+            settings,
+            present_mode_list,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            recording: None,
+
         }
 
     }
@@ -1318,14 +1240,63 @@ impl State {
         self.cursor_position = new_pos;
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+    //pushes changed settings into the parts that copied them
+    pub fn apply_settings(&mut self) {
+        let settings = &self.settings;
+
+        self.camera_controller.set_speed(settings.camera_speed, settings.camera_sensitivity);
+        self.sample_ratio = self.sample_ratio.clamp(settings.sample_ratio_min, settings.sample_ratio_max);
+
+        let brush_state = &mut self.chunk_manager.brush_state;
+        brush_state.radius = brush_state.radius.clamp(settings.brush_radius_min, settings.brush_radius_max);
+        brush_state.update();
+        let model_state = &mut self.chunk_manager.model_state;
+        model_state.scale = model_state.scale.clamp(settings.model_scale_min, settings.model_scale_max);
+
+        let present_mode = if self.present_mode_list.contains(&settings.present_mode) { settings.present_mode } else { wgpu::PresentMode::Fifo };
+        if self.config.present_mode != present_mode {
+            self.config.present_mode = present_mode;
+            self.surface.configure(&self.device, &self.config);
+        }
+
+        self.iced_state.queue_message(shell::Message::SettingsList(
+            settings.list().into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+        ));
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>, scale_factor: f64) {
         if new_size.width > 0 && new_size.height > 0 {
 
+            self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             
             self.surface.configure(&self.device, &self.config);
+
+            //one offscreen pixel per voxel at any size, so picking and the ortho view stay in step
             self.camera.aspect = self.config.width as f32 / self.config.height as f32;
+            self.camera.fovy = new_size.height as f32 / 2.0;
+            self.camera_controller.resize(new_size.width as f32, new_size.height as f32);
+
+            if self.texture_size.width != new_size.width || self.texture_size.height != new_size.height {
+                self.texture_size.width = new_size.width;
+                self.texture_size.height = new_size.height;
+                self.targets = render_target::RenderTargets::new(
+                    &self.device,
+                    self.texture_size,
+                    &self.texture_bind_group_layout,
+                    &self.texture_bind_group_layout_depth,
+                );
+
+                //frames of a sequence have to share one size
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(recording) = self.recording.take() {
+                    self.camera_controller.stop();
+                    self.iced_state.queue_message(ServerLog(format!("Recorded {} frames to {}, window resized", recording.frame, recording.dir.display())));
+                }
+            }
+
+            self.viewport = Viewport::with_physical_size(Size::new(new_size.width, new_size.height), scale_factor);
 
         }
     }
@@ -1340,6 +1311,10 @@ impl State {
         if self.camera_controller.is_cli_released{
             self.cli_flag = false;
         }
+        for command in self.camera_controller.command_queue.drain(..){
+            self.iced_state.queue_message(shell::Message::CommandChanged(command));
+            self.iced_state.queue_message(shell::Message::Parse);
+        }
         true
 
     }
@@ -1355,11 +1330,22 @@ impl State {
 
 
 
+    //fixed rate game logic, everything here sees the same dt whatever the framerate
+    fn tick(&mut self) {
+
+        let step = self.tick_clock.step();
+        self.clock.advance(step);
+        self.simulation.update(step as f32, &mut self.chunk_manager, &self.device);
+    }
+
     fn update(&mut self, dt: std::time::Duration) {
 
         self.time += dt.as_secs() as f64 + dt.subsec_nanos() as f64 * 1e-9;
 
-        self.camera_controller.update_camera(&mut self.camera, dt);
+        let chunk_manager = &self.chunk_manager;
+        if let Some(zoom) = self.camera_controller.update_camera(&mut self.camera, dt, &|p| chunk_manager.is_solid(p)) {
+            self.sample_ratio = zoom.clamp(self.settings.sample_ratio_min, self.settings.sample_ratio_max);
+        }
         self.camera_controller.process_mouse_position(self.cursor_position.x, self.cursor_position.y);
         
         //self.uniform.update((960.0 - self.cursor_position.x as f32)/960.0 * self.view_sensitivity , ( self.cursor_position.y as f32 - 540.0 )/540.0 * self.view_sensitivity);
@@ -1379,23 +1365,53 @@ impl State {
         );
 
 
-        self.chunk_manager.point_light_list[0].position[0] = Rad::sin(Rad(1.0)) * 200000.0;
-        self.chunk_manager.point_light_list[0].position[1] = 100000.0;
-        self.chunk_manager.point_light_list[0].position[2] = Rad::cos(Rad(1.0)) * 200000.0;
+        let clock = self.clock.interpolate(self.tick_clock.alpha());
+        clock.apply(&mut self.chunk_manager.light_manager);
+        self.chunk_manager.light_manager.update_sun();
+
+        self.clear_color = if clock.drives_sun{
+            let sky = clock.sky_color();
+            wgpu::Color { r: sky[0] as f64, g: sky[1] as f64, b: sky[2] as f64, a: 1.0 }
+        }else{
+            wgpu::Color { r: 0.0, g: 0.0, b: 0.0, a: 0.01 }
+        };
 
-        self.chunk_manager.point_light_list[1].position = [self.camera.target.x,self.camera.target.y,self.camera.target.z];
+        self.chunk_manager.light_manager.cursor().position = [self.camera.target.x,self.camera.target.y,self.camera.target.z];
 
         let r = ((Rad::sin(Rad(self.time * 10.0)) + 1.0)/2.0 )as f32;
         let g = ((Rad::sin(Rad(self.time * 10.0) + Rad(3.14 * 2.0 / 3.0)) + 1.0)/2.0 )as f32;
         let b = ((Rad::sin(Rad(self.time * 10.0) + Rad(3.14 * 4.0 / 3.0)) + 1.0)/2.0 )as f32;
 
-        self.chunk_manager.point_light_list[0].color = [1.0,0.9,0.7,1.0];
-        self.chunk_manager.point_light_list[1].color = [r,g,b,0.1];
+        self.chunk_manager.light_manager.cursor().color = [r,g,b,0.1];
         
 
-        self.chunk_manager.update(&self.device,dt,&self.camera,&mut self.camera_controller,self.cursor_position.x,self.cursor_position.y,self.texture_size,&mut self.iced_state,&mut self.sample_ratio);
+        //finished pieces of background jobs are uploaded here, on the thread owning the device
+        let update_list = self.job_system.poll();
+        if !update_list.is_empty(){
+            let finished = update_list.iter().any(|u| u.finished());
+            update_list.into_iter().for_each(|u|{
+                self.chunk_manager.place_instances(u.instance_list, &self.device);
+            });
+            self.iced_state.queue_message(JobProgress(self.job_system.progress_text()));
+            if finished{
+                self.iced_state.queue_message(ServerLog(String::from("Background job finished")));
+            }
+        }
+
+        let target = [self.camera.target.x,self.camera.target.y,self.camera.target.z];
+        if let Err(e) = self.chunk_manager.stream(target, &self.device){
+            self.iced_state.queue_message(ServerLog(format!("Streaming failed: {}", e)));
+        }
+        self.chunk_manager.update_lod(target, self.sample_ratio, &self.device);
+
+        self.chunk_manager.update(&self.device,dt,&self.camera,&mut self.camera_controller,self.cursor_position.x,self.cursor_position.y,self.texture_size,&mut self.iced_state,&mut self.sample_ratio,&self.settings);
+
+        self.queue.write_buffer(&self.light_buffer, 0, &self.chunk_manager.light_manager.to_bytes());
 
-        self.queue.write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&self.chunk_manager.point_light_list));
+        if self.chunk_manager.material_list.need_update{
+            self.queue.write_buffer(&self.material_buffer, 0, bytemuck::cast_slice(&self.chunk_manager.material_list.to_raw()));
+            self.chunk_manager.material_list.need_update = false;
+        }
         
         //self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.instance_data));
  
@@ -1419,11 +1435,49 @@ impl State {
         
         
 
+    }
+
+    //the full screen pass that puts the chosen buffer on the surface
+    fn draw_output<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+
+        if self.output_texture_flag {
+            render_pass.set_pipeline(&self.render_blend_pipeline);
+            render_pass.set_bind_group(1, &self.targets.diffuse_bind_group, &[]);
+            render_pass.set_bind_group(2, &self.targets.normal_bind_group, &[]);
+            render_pass.set_bind_group(3, &self.targets.depth_bind_group, &[]);
+        }
+
+        else if self.normal_texture_flag {
+            render_pass.set_pipeline(&self.render_sample_pipeline);
+            render_pass.set_bind_group(1, &self.targets.normal_bind_group, &[]);
+        } 
+        else if self.diffuse_texture_flag{
+            render_pass.set_pipeline(&self.render_sample_pipeline);
+            render_pass.set_bind_group(1, &self.targets.diffuse_bind_group, &[]);
+        }
+        else if self.depth_texture_flag {
+            render_pass.set_pipeline(&self.render_sample_for_r32Depth_pipeline);
+            render_pass.set_bind_group(1, &self.targets.depth_bind_group, &[]);
+        }
+        
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+
+        render_pass.set_vertex_buffer(0, self.vertex_tex_buffer.slice(..));
+        render_pass.draw(0..6, 0..1);
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
 
         let output = self.surface.get_current_texture()?;
+
+        //one test per chunk, shared by all three passes
+        let frustum = self.camera_uniform.frustum();
+        let visible_list:Vec<bool> = self.chunk_manager.chunk_list.iter().map(|c|{
+            let (min, max) = c.bounds();
+            frustum.intersects_aabb(min, max)
+        }).collect();
+        self.chunk_drawn = visible_list.iter().filter(|v|**v).count() as u32;
+        self.chunk_culled = visible_list.len() as u32 - self.chunk_drawn;
         
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
@@ -1437,7 +1491,7 @@ impl State {
             let mut render_pass_normal = normal_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Normal Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.normal_texture_view,
+                    view: &self.targets.normal_texture_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(
@@ -1452,7 +1506,7 @@ impl State {
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_test_texture_view,
+                    view: &self.targets.depth_test_texture_view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: true,
@@ -1465,12 +1519,14 @@ impl State {
 
             render_pass_normal.set_bind_group(0, &self.camera_bind_group, &[]);
             render_pass_normal.set_bind_group(1, &self.light_bind_group, &[]);
+            render_pass_normal.set_bind_group(2, &self.material_bind_group, &[]);
 
             render_pass_normal.set_vertex_buffer(0, self.vertex_buffer.slice(..));
 
-            self.chunk_manager.chunk_list.iter().for_each(|c|{
-                render_pass_normal.set_vertex_buffer(1, c.buffer_data.slice(..));
-                render_pass_normal.draw(0..self.num_vertices, 0..c.instance_len as _);
+            self.chunk_manager.chunk_list.iter().zip(&visible_list).filter(|(_,v)|**v).for_each(|(c,_)|{
+                let (buffer, len) = c.draw_buffer();
+                render_pass_normal.set_vertex_buffer(1, buffer.slice(..));
+                render_pass_normal.draw(0..self.num_vertices, 0..len);
             });
 
             //render_pass_normal.set_pipeline(&self.render_line_normal_pipeline);
@@ -1502,7 +1558,7 @@ impl State {
             let mut render_pass_depth = depth_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Depth Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.depth_texture_view,
+                    view: &self.targets.depth_texture_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(
@@ -1517,7 +1573,7 @@ impl State {
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_test_texture_view,
+                    view: &self.targets.depth_test_texture_view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: true,
@@ -1530,12 +1586,14 @@ impl State {
 
             render_pass_depth.set_bind_group(0, &self.camera_bind_group, &[]);
             render_pass_depth.set_bind_group(1, &self.light_bind_group, &[]);
+            render_pass_depth.set_bind_group(2, &self.material_bind_group, &[]);
 
             render_pass_depth.set_vertex_buffer(0, self.vertex_buffer.slice(..));
 
-            self.chunk_manager.chunk_list.iter().for_each(|c|{
-                render_pass_depth.set_vertex_buffer(1, c.buffer_data.slice(..));
-                render_pass_depth.draw(0..self.num_vertices, 0..c.instance_len as _);
+            self.chunk_manager.chunk_list.iter().zip(&visible_list).filter(|(_,v)|**v).for_each(|(c,_)|{
+                let (buffer, len) = c.draw_buffer();
+                render_pass_depth.set_vertex_buffer(1, buffer.slice(..));
+                render_pass_depth.draw(0..self.num_vertices, 0..len);
             });
                 
             
@@ -1570,22 +1628,21 @@ impl State {
             let mut render_pass_diffuse = diffuse_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.diffuse_texture_view,
+                    view: &self.targets.diffuse_texture_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
+                        //the blend pass replaces the surface, so the sky is cleared here
                         load: wgpu::LoadOp::Clear(
                             wgpu::Color {
-                                r: 0.0,
-                                g: 0.0,
-                                b: 0.0,
                                 a: 0.0,
+                                ..self.clear_color
                             }
                         ),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_test_texture_view,
+                    view: &self.targets.depth_test_texture_view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: true,
@@ -1600,17 +1657,19 @@ impl State {
 
             render_pass_diffuse.set_bind_group(0, &self.camera_bind_group, &[]);
             render_pass_diffuse.set_bind_group(1, &self.light_bind_group, &[]);
+            render_pass_diffuse.set_bind_group(2, &self.material_bind_group, &[]);
 
             render_pass_diffuse.set_vertex_buffer(0, self.vertex_buffer.slice(..));
 
-            self.chunk_manager.chunk_list.iter().for_each(|c|{
+            self.chunk_manager.chunk_list.iter().zip(&visible_list).filter(|(_,v)|**v).for_each(|(c,_)|{
                 if c.current_type != ChunkType::UsrIndicator{
-                    render_pass_diffuse.set_vertex_buffer(1, c.buffer_data.slice(..));
-                    render_pass_diffuse.draw(0..self.num_vertices, 0..c.instance_len as _);
+                    let (buffer, len) = c.draw_buffer();
+                    render_pass_diffuse.set_vertex_buffer(1, buffer.slice(..));
+                    render_pass_diffuse.draw(0..self.num_vertices, 0..len);
                 }
             });
 
-            self.chunk_manager.chunk_list.iter().for_each(|c|{
+            self.chunk_manager.chunk_list.iter().zip(&visible_list).filter(|(_,v)|**v).for_each(|(c,_)|{
                 if c.current_type == ChunkType::UsrIndicator{
                     render_pass_diffuse.set_vertex_buffer(1, c.buffer_data.slice(..));
                     render_pass_diffuse.draw(0..self.num_vertices, 0..c.instance_len as _);
@@ -1653,46 +1712,37 @@ impl State {
                     //view: &self.msaa_texture_view,
                     //resolve_target: Some(&view),
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(
-                            wgpu::Color {
-                                r: 0.01,
-                                g: 0.01,
-                                b: 0.01,
-                                a: 0.01,
-                            }
-                        ),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
 
-        
-            if self.output_texture_flag {
-                render_pass.set_pipeline(&self.render_blend_pipeline);
-                render_pass.set_bind_group(1, &self.diffuse_bind_group, &[]);
-                render_pass.set_bind_group(2, &self.normal_bind_group, &[]);
-                render_pass.set_bind_group(3, &self.depth_bind_group, &[]);
-            }
 
-            else if self.normal_texture_flag {
-                render_pass.set_pipeline(&self.render_sample_pipeline);
-                render_pass.set_bind_group(1, &self.normal_bind_group, &[]);
-            } 
-            else if self.diffuse_texture_flag{
-                render_pass.set_pipeline(&self.render_sample_pipeline);
-                render_pass.set_bind_group(1, &self.diffuse_bind_group, &[]);
-            }
-            else if self.depth_texture_flag {
-                render_pass.set_pipeline(&self.render_sample_for_r32Depth_pipeline);
-                render_pass.set_bind_group(1, &self.depth_bind_group, &[]);
-            }
+            self.draw_output(&mut render_pass);
             
-            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        }
 
-            render_pass.set_vertex_buffer(0, self.vertex_tex_buffer.slice(..));
-            render_pass.draw(0..6, 0..1);
-            
+        //the same image again without the ui, while a path is being recorded
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(recording) = self.recording.as_ref().filter(|_| self.camera_controller.is_playing()) {
+            {
+                let mut render_pass = surface_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Capture Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &recording.capture.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(self.clear_color),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                self.draw_output(&mut render_pass);
+            }
+            recording.capture.copy(&mut surface_encoder);
         }
         
         if self.cli_status{
@@ -1717,6 +1767,16 @@ impl State {
         self.queue.submit(iter::once(surface_encoder.finish()));
         output.present();
 
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(recording) = &mut self.recording {
+            let result = if self.camera_controller.is_playing() { recording.save_frame(&self.device) } else { Err(String::from("Path finished")) };
+            if let Err(e) = result {
+                self.iced_state.queue_message(ServerLog(format!("Recorded {} frames to {}, {}", recording.frame, recording.dir.display(), e)));
+                self.camera_controller.stop();
+                self.recording = None;
+            }
+        }
+
 
        // And recall staging buffers
        self.staging_belt.recall();
@@ -1746,14 +1806,37 @@ pub async fn run() {
         }
     }
 
+    //the browser canvas ignores most of these
+    #[cfg(not(target_arch = "wasm32"))]
+    let options = match launch::LaunchOptions::load(){
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    #[cfg(target_arch = "wasm32")]
+    let options = launch::LaunchOptions::new();
+
     let event_loop = EventLoop::new();
-    let monitor = event_loop.primary_monitor().unwrap();
-    let video_mode = monitor.video_modes().next();
-    //let size = video_mode.clone().map_or(PhysicalSize::new(1920, 1080), |vm| vm.size());
-    let window = WindowBuilder::new()
+    let monitor = event_loop.available_monitors().nth(options.monitor).or_else(|| event_loop.primary_monitor());
+    let fullscreen = match options.window_mode{
+        launch::WindowMode::Windowed => None,
+        launch::WindowMode::Borderless => Some(Fullscreen::Borderless(monitor.clone())),
+        //the video mode closest to the asked resolution, else the first one as before
+        launch::WindowMode::Exclusive => monitor.as_ref().and_then(|m| match options.resolution{
+            Some([w, h]) => m.video_modes().min_by_key(|vm| (vm.size().width as i64 - w as i64).abs() + (vm.size().height as i64 - h as i64).abs()),
+            None => m.video_modes().next(),
+        }).map(Fullscreen::Exclusive),
+    };
+    let mut window_builder = WindowBuilder::new()
         .with_visible(true)
         .with_title("?")
-        .with_fullscreen(video_mode.map(|vm| Fullscreen::Exclusive(vm)))
+        .with_fullscreen(fullscreen);
+    if let (launch::WindowMode::Windowed, Some([w, h])) = (options.window_mode, options.resolution){
+        window_builder = window_builder.with_inner_size(PhysicalSize::new(w, h));
+    }
+    let window = window_builder
         .build(&event_loop)
         .unwrap();
     //window.set_cursor_visible(false);
@@ -1801,7 +1884,7 @@ pub async fn run() {
     let scr_height = window.inner_size().height;
 
     // State::new uses async code, so we're going to wait for it to finish
-    let mut state = State::new(&window,scr_width,scr_height).await;
+    let mut state = State::new(&window,scr_width,scr_height,&options).await;
 
     let mut command_parser = Descriptor::new();
     let mut a = 0;
@@ -1828,7 +1911,7 @@ pub async fn run() {
                             state.viewport.scale_factor(),
                         ),
                         &mut state.renderer,
-                        &iced_wgpu::Theme::Matrix,
+                        &state.settings.theme,
                         &renderer::Style { text_color:  Color::from_rgb(
                                 0x01 as f32 / 255.0,
                                 0x01 as f32 / 255.0,
@@ -1886,11 +1969,11 @@ pub async fn run() {
                         }
 
                         WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
+                            state.resize(*physical_size, window.scale_factor());
                         }
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        WindowEvent::ScaleFactorChanged { new_inner_size, scale_factor } => {
                             // new_inner_size is &mut so w have to dereference it twice
-                            state.resize(**new_inner_size);
+                            state.resize(**new_inner_size, *scale_factor);
                         }
                         _ => {}
                     }
@@ -1914,6 +1997,32 @@ pub async fn run() {
                 let now = Instant::now();
                 let dt = now - last_render_time;
                 last_render_time = now;
+
+                let tick_count = state.tick_clock.advance(dt.as_secs_f64());
+                //the shell only drops applied commands once it handles CommandParsed, so count them here
+                let mut command_applied = 0;
+
+                for _ in 0..tick_count {
+
+                    //commands land on a tick boundary in the order they were sent
+                    let command_list = state.iced_state.program().command_queue[command_applied..].to_vec();
+
+                    for t in command_list{
+
+                        command_parser.text = t;
+
+                        command_parser.parse_command(&mut state);
+
+                        command_applied += 1;
+                    }
+
+                    state.tick();
+                }
+
+                if command_applied > 0 {
+                    state.iced_state.queue_message(CommandParsed(command_applied));
+                }
+
                 state.update(dt);
 
                 state.iced_state.queue_message(Update);
@@ -1952,23 +2061,15 @@ pub async fn run() {
                 }
                 else {
                     state.iced_state.queue_message(FrameUpdate(state.framerate_count));
+                    state.iced_state.queue_message(TickUpdate(state.tick_clock.tps));
+                    if state.chunk_manager.debug_mode{
+                        state.iced_state.queue_message(CullUpdate(state.chunk_drawn, state.chunk_culled));
+                    }
                     
                     state.framerate_timer = 0.0;
                     state.framerate_count = 1;
                 }
                 
-                if state.iced_state.program().parse_flag {
-
-                    let t = &state.iced_state.program().command_buffer;
-                    
-                    command_parser.text = t.to_string();
-
-                    command_parser.parse_command(&mut state);
-
-                    state.iced_state.queue_message(CommandParsed);
-                    
-                }
-
                 #[cfg(target_arch = "wasm32")]{
                     //use web_sys::console;
                     //console::log_1(&t.into());
@@ -1977,14 +2078,14 @@ pub async fn run() {
                 match state.render() {
                     Ok(_) => {}
                     // Reconfigure the surface if lost
-                    Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
+                    Err(wgpu::SurfaceError::Lost) => state.resize(state.size, state.viewport.scale_factor()),
                     // The system is out of memory, we should probably quit
                     Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                     // All other errors (Outdated, Timeout) should be resolved by the next frame
                     Err(e) => eprintln!("{:?}", e),
                 }
                 
-                state.chunk_manager.chunk_list.retain(|c|c.current_type != ChunkType::UsrIndicator);
+                state.chunk_manager.clear_indicators();
 
                 window.set_cursor_icon(
                     iced_winit::conversion::mouse_interaction(
@@ -1996,21 +2097,13 @@ pub async fn run() {
         }
 
         
-        a = a+1;
-        if window.fullscreen().is_none() {
+        //counts events only while waiting to quit, so it can not overflow
+        if window.fullscreen().is_none() && options.exit_without_fullscreen {
+            a = a+1;
             if a>333 {
                 window.set_inner_size(PhysicalSize::new(1, 1));
                 *control_flow = ControlFlow::Exit
             }
-        }else{
-            if a == 33 {
-                state.cli_status = true;
-                //iced enabled
-            }
-            if a>777 {
-                
-                a = 777;
-            }
         }
     });
 }
@@ -0,0 +1,329 @@
+use iced_wgpu::wgpu;
+
+//webgl2 guarantees only this much per uniform binding
+#[cfg(target_arch = "wasm32")]
+const MAX_UNIFORM_BINDING_SIZE: usize = 16384;
+
+//webgl has no storage buffers, the lights go into a fixed uniform array there that has to fit the binding with its header
+#[cfg(target_arch = "wasm32")]
+pub const MAX_LIGHT: usize = (MAX_UNIFORM_BINDING_SIZE - std::mem::size_of::<LightHeader>()) / std::mem::size_of::<Light>();
+#[cfg(not(target_arch = "wasm32"))]
+pub const MAX_LIGHT: usize = 65536;
+
+//slots handed out before any user light
+const SUN_SLOT: usize = 0;
+const CURSOR_SLOT: usize = 1;
+const RESERVED_SLOT: usize = 2;
+
+pub const DEFAULT_LIGHT_RADIUS: f32 = 512.0;
+pub const CURSOR_LIGHT_RADIUS: f32 = 2048.0;
+
+//far enough that the sun reads as directional over the whole world
+const SUN_DISTANCE: f32 = 200000.0;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Light {
+    pub position: [f32; 3],
+    //zero for directional lights, point lights are skipped beyond it
+    pub radius: f32,
+    //rgb color, w intensity
+    pub color: [f32; 4],
+}
+
+impl Light {
+    pub fn point(position: [f32; 3], color: [f32; 3], intensity: f32, radius: f32) -> Self {
+        Self { position, radius: radius.max(1.0), color: [color[0], color[1], color[2], intensity] }
+    }
+
+    pub fn directional(position: [f32; 3], color: [f32; 3], intensity: f32) -> Self {
+        Self { position, radius: 0.0, color: [color[0], color[1], color[2], intensity] }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.color[3] > 0.0
+    }
+}
+
+//precedes the light array in the buffer, 16 bytes to match the alignment of Light
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightHeader {
+    ambient: [f32; 3],
+    count: u32,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct LightHandle {
+    index: u32,
+    //bumped on every free so stale handles cannot remove a reused slot
+    generation: u32,
+}
+
+impl LightHandle {
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+pub struct LightManager {
+
+    //degrees, azimuth turns from +z towards +x
+    pub sun_azimuth: f32,
+    pub sun_elevation: f32,
+    pub sun_color: [f32; 3],
+    pub ambient: [f32; 3],
+
+    //set every frame by the world clock on top of the authored values
+    pub sun_tint: [f32; 3],
+    pub sun_intensity: f32,
+    pub ambient_scale: f32,
+
+    light_list: Vec<Light>,
+    //outlives trimmed slots so a slot pushed again keeps counting
+    generation_list: Vec<u32>,
+    free_list: Vec<usize>,
+}
+
+impl LightManager {
+    pub fn new() -> Self {
+
+        let light_list = vec![
+            Light::directional([0.0, 0.0, 0.0], [1.0, 0.9, 0.7], 1.0),
+            Light::point([0.0, 0.0, 0.0], [1.0, 1.0, 1.0], 0.1, CURSOR_LIGHT_RADIUS),
+        ];
+
+        Self {
+            sun_azimuth: 57.3,
+            sun_elevation: 26.6,
+            sun_color: [1.0, 0.9, 0.7],
+            ambient: [0.0, 0.0, 0.0],
+            sun_tint: [1.0, 1.0, 1.0],
+            sun_intensity: 1.0,
+            ambient_scale: 1.0,
+            generation_list: vec![0; light_list.len()],
+            light_list,
+            free_list: Default::default(),
+        }
+    }
+
+    pub fn sun(&mut self) -> &mut Light {
+        &mut self.light_list[SUN_SLOT]
+    }
+
+    pub fn cursor(&mut self) -> &mut Light {
+        &mut self.light_list[CURSOR_SLOT]
+    }
+
+    //moves the sun slot to the current azimuth and elevation
+    pub fn update_sun(&mut self) {
+        let azimuth = self.sun_azimuth.to_radians();
+        let elevation = self.sun_elevation.to_radians();
+        let position = [
+            azimuth.sin() * elevation.cos() * SUN_DISTANCE,
+            elevation.sin() * SUN_DISTANCE,
+            azimuth.cos() * elevation.cos() * SUN_DISTANCE,
+        ];
+        let color = [
+            self.sun_color[0] * self.sun_tint[0],
+            self.sun_color[1] * self.sun_tint[1],
+            self.sun_color[2] * self.sun_tint[2],
+        ];
+        self.light_list[SUN_SLOT] = Light::directional(position, color, self.sun_intensity);
+    }
+
+    //None once every slot is taken, or for a light that gives no light and would only hold a slot
+    pub fn add(&mut self, light: Light) -> Option<LightHandle> {
+
+        if !light.is_active() {
+            return None;
+        }
+
+        let index = match self.free_list.pop() {
+            Some(index) => index,
+            None if self.light_list.len() < MAX_LIGHT => {
+                self.light_list.push(light);
+                if self.generation_list.len() < self.light_list.len() {
+                    self.generation_list.push(0);
+                }
+                self.light_list.len() - 1
+            }
+            None => return None,
+        };
+
+        self.light_list[index] = light;
+        Some(LightHandle { index: index as u32, generation: self.generation_list[index] })
+    }
+
+    pub fn remove(&mut self, handle: LightHandle) -> bool {
+
+        if self.get(handle).is_none() {
+            return false;
+        }
+
+        let index = handle.index();
+        self.light_list[index] = bytemuck::Zeroable::zeroed();
+        self.generation_list[index] += 1;
+        self.free_list.push(index);
+
+        //trailing free slots are dropped so the shader loop stays short
+        while self.light_list.len() > RESERVED_SLOT && !self.light_list[self.light_list.len() - 1].is_active() {
+            let last = self.light_list.len() - 1;
+            self.light_list.pop();
+            self.free_list.retain(|i| *i != last);
+        }
+        true
+    }
+
+    pub fn get(&self, handle: LightHandle) -> Option<&Light> {
+        let index = handle.index();
+        if index < RESERVED_SLOT || index >= self.light_list.len() || self.generation_list[index] != handle.generation {
+            return None;
+        }
+        Some(&self.light_list[index])
+    }
+
+    pub fn get_mut(&mut self, handle: LightHandle) -> Option<&mut Light> {
+        self.get(handle)?;
+        Some(&mut self.light_list[handle.index()])
+    }
+
+    //user lights only, the reserved slots are not listed
+    pub fn iter(&self) -> impl Iterator<Item = (LightHandle, &Light)> {
+        self.light_list
+            .iter()
+            .enumerate()
+            .skip(RESERVED_SLOT)
+            .filter(|(_, l)| l.is_active())
+            .map(|(i, l)| (LightHandle { index: i as u32, generation: self.generation_list[i] }, l))
+    }
+
+    pub fn find(&self, index: usize) -> Option<LightHandle> {
+        self.iter().find(|(h, _)| h.index() == index).map(|(h, _)| h)
+    }
+
+    pub fn count(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn clear(&mut self) {
+        self.light_list.truncate(RESERVED_SLOT);
+        self.generation_list.iter_mut().skip(RESERVED_SLOT).for_each(|g| *g += 1);
+        self.free_list.clear();
+    }
+
+    pub fn buffer_size() -> wgpu::BufferAddress {
+        (std::mem::size_of::<LightHeader>() + std::mem::size_of::<Light>() * MAX_LIGHT) as wgpu::BufferAddress
+    }
+
+    //header and the used part of the array, the rest of the buffer is never read
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let ambient = [
+            self.ambient[0] * self.ambient_scale,
+            self.ambient[1] * self.ambient_scale,
+            self.ambient[2] * self.ambient_scale,
+        ];
+        let header = LightHeader { ambient, count: self.light_list.len() as u32 };
+        let mut bytes = bytemuck::bytes_of(&header).to_vec();
+        bytes.extend_from_slice(bytemuck::cast_slice(&self.light_list));
+        bytes
+    }
+}
+
+impl Default for LightManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn binding_type() -> wgpu::BufferBindingType {
+    wgpu::BufferBindingType::Uniform
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn binding_type() -> wgpu::BufferBindingType {
+    wgpu::BufferBindingType::Storage { read_only: true }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn buffer_usage() -> wgpu::BufferUsages {
+    wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn buffer_usage() -> wgpu::BufferUsages {
+    wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+}
+
+//the shaders are written against the storage buffer, webgl gets the fixed uniform array instead
+pub fn shader_source(source: &str) -> String {
+    if cfg!(target_arch = "wasm32") {
+        source
+            .replace("var<storage, read> lights", "var<uniform> lights")
+            .replace("array<Light>", &format!("array<Light,{}>", MAX_LIGHT))
+    } else {
+        source.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fire() -> Light {
+        Light::point([1.0, 2.0, 3.0], [1.0, 0.5, 0.1], 0.5, DEFAULT_LIGHT_RADIUS)
+    }
+
+    #[test]
+    fn add_remove_reuses_slots() {
+        let mut manager = LightManager::new();
+        let a = manager.add(fire()).unwrap();
+        let b = manager.add(fire()).unwrap();
+        assert_eq!(manager.count(), 2);
+
+        assert!(manager.remove(a));
+        assert!(!manager.remove(a));
+        assert_eq!(manager.count(), 1);
+
+        let c = manager.add(fire()).unwrap();
+        assert_eq!(c.index(), a.index());
+        assert!(manager.get(a).is_none());
+        assert!(manager.get(c).is_some());
+        assert!(manager.get(b).is_some());
+    }
+
+    #[test]
+    fn dark_lights_are_rejected() {
+        let mut manager = LightManager::new();
+        let dark = Light::point([1.0, 2.0, 3.0], [1.0, 0.5, 0.1], 0.0, DEFAULT_LIGHT_RADIUS);
+        assert!(manager.add(dark).is_none());
+        assert_eq!(manager.count(), 0);
+        assert_eq!(manager.add(fire()).unwrap().index(), RESERVED_SLOT);
+    }
+
+    #[test]
+    fn trailing_slots_shrink_the_header() {
+        let mut manager = LightManager::new();
+        let a = manager.add(fire()).unwrap();
+        let b = manager.add(fire()).unwrap();
+        manager.remove(b);
+        manager.remove(a);
+        let bytes = manager.to_bytes();
+        assert_eq!(bytes.len(), 16 + 32 * RESERVED_SLOT);
+        assert_eq!(bytes[12] as usize, RESERVED_SLOT);
+        let c = manager.add(fire()).unwrap();
+        assert_eq!(c.index(), RESERVED_SLOT);
+        assert!(manager.get(a).is_none());
+    }
+
+    #[test]
+    fn reserved_slots_are_not_handles() {
+        let mut manager = LightManager::new();
+        let sun = LightHandle { index: 0, generation: 0 };
+        assert!(!manager.remove(sun));
+        assert_eq!(manager.count(), 0);
+        manager.sun().color = [0.0, 0.0, 0.0, 1.0];
+        assert_eq!(manager.to_bytes().len(), 16 + 32 * RESERVED_SLOT);
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use crate::{Instance, InstanceRaw};
+
+//voxel size of each level past full detail
+pub const LOD_SCALE_LIST: [i32; 3] = [2, 4, 8];
+
+//effective distances in voxels where levels 1, 2 and 3 start
+pub const DEFAULT_DISTANCE_LIST: [f32; 3] = [384.0, 768.0, 1536.0];
+//a chunk has to cross a boundary by this much before it switches, so it does not flicker on the edge
+pub const DEFAULT_HYSTERESIS: f32 = 48.0;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ColorMode {
+    //the most common color in the cell, keeps hard edges like paths and water lines
+    Majority,
+    Average,
+}
+
+pub struct LodSettings {
+    pub enabled: bool,
+    pub distance_list: [f32; 3],
+    pub hysteresis: f32,
+    pub color_mode: ColorMode,
+}
+
+impl LodSettings {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            distance_list: DEFAULT_DISTANCE_LIST,
+            hysteresis: DEFAULT_HYSTERESIS,
+            color_mode: ColorMode::Majority,
+        }
+    }
+
+    //the level a chunk at current should move to, only stepping past a boundary plus hysteresis
+    pub fn select(&self, current: usize, distance: f32) -> usize {
+
+        if !self.enabled {
+            return 0;
+        }
+
+        let mut level = current.min(LOD_SCALE_LIST.len());
+        while level < LOD_SCALE_LIST.len() && distance > self.distance_list[level] + self.hysteresis {
+            level += 1;
+        }
+        while level > 0 && distance < self.distance_list[level - 1] - self.hysteresis {
+            level -= 1;
+        }
+        level
+    }
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//the ortho camera shows more world as sample_ratio drops, so distances count for more when zoomed out
+pub fn effective_distance(distance: f32, sample_ratio: f32) -> f32 {
+    distance / sample_ratio.max(1.0)
+}
+
+struct Cell {
+    count: u32,
+    color: [f32; 4],
+    normal: [f32; 3],
+    color_count: HashMap<[u8; 4], u32>,
+    material_count: HashMap<usize, u32>,
+    first: Instance,
+}
+
+fn quantize(color: [f32; 4]) -> [u8; 4] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn majority<K: Copy + Ord>(count: &HashMap<K, u32>) -> K {
+    //ties go to the smallest key so the result does not depend on hash order
+    count.iter().map(|(k, n)| (*n, std::cmp::Reverse(*k))).max().map(|(_, k)| k.0).unwrap()
+}
+
+//one scaled voxel per scale^3 cell that holds anything
+pub fn downsample(voxel_data: &[Instance], scale: i32, color_mode: ColorMode) -> Vec<InstanceRaw> {
+
+    let mut cell_hash: HashMap<[i32; 3], Cell> = Default::default();
+
+    voxel_data.iter().for_each(|v| {
+        let p = v.grid_position();
+        let key = [p[0].div_euclid(scale), p[1].div_euclid(scale), p[2].div_euclid(scale)];
+        let color = [v.color.x, v.color.y, v.color.z, v.color.w];
+
+        let cell = cell_hash.entry(key).or_insert_with(|| Cell {
+            count: 0,
+            color: [0.0; 4],
+            normal: [0.0; 3],
+            color_count: Default::default(),
+            material_count: Default::default(),
+            first: *v,
+        });
+        cell.count += 1;
+        (0..4).for_each(|i| cell.color[i] += color[i]);
+        cell.normal[0] += v.normal.x;
+        cell.normal[1] += v.normal.y;
+        cell.normal[2] += v.normal.z;
+        *cell.color_count.entry(quantize(color)).or_default() += 1;
+        *cell.material_count.entry(v.material).or_default() += 1;
+    });
+
+    let mut key_list: Vec<[i32; 3]> = cell_hash.keys().copied().collect();
+    key_list.sort();
+
+    key_list
+        .into_iter()
+        .map(|key| {
+            let cell = &cell_hash[&key];
+
+            let color = match color_mode {
+                ColorMode::Majority => majority(&cell.color_count).map(|c| c as f32 / 255.0),
+                ColorMode::Average => cell.color.map(|c| c / cell.count as f32),
+            };
+
+            let length = (cell.normal[0].powi(2) + cell.normal[1].powi(2) + cell.normal[2].powi(2)).sqrt();
+            let normal = if length > 0.0 { cell.normal.map(|n| n / length) } else { [0.0, 1.0, 0.0] };
+
+            //centered on the cell, so a lod voxel covers the same space as the voxels it stands for
+            let center = key.map(|k| (k * scale) as f32 + (scale - 1) as f32 / 2.0);
+
+            let mut ins = cell.first;
+            ins.position = center.into();
+            ins.color = color.into();
+            ins.normal = normal.into();
+            ins.material = majority(&cell.material_count);
+
+            let mut raw = ins.to_raw();
+            raw.model = (cgmath::Matrix4::from_translation(ins.position) * cgmath::Matrix4::from_scale(scale as f32)).into();
+            raw
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voxel(p: [i32; 3], color: [f32; 4], material: usize) -> Instance {
+        Instance::new(p, color, [0.0, 1.0, 0.0], material)
+    }
+
+    #[test]
+    fn merges_cells_by_majority_and_average() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        let voxel_list = vec![
+            voxel([0, 0, 0], red, 1),
+            voxel([1, 0, 0], red, 1),
+            voxel([0, 1, 1], blue, 2),
+            voxel([4, 0, 0], blue, 2),
+        ];
+
+        let raw_list = downsample(&voxel_list, 2, ColorMode::Majority);
+        assert_eq!(raw_list.len(), 2);
+        assert_eq!(raw_list[0].color, red);
+        assert_eq!(raw_list[0].material, 1);
+        assert_eq!(raw_list[0].model[0][0], 2.0);
+        assert_eq!(raw_list[0].model[3][..3], [0.5, 0.5, 0.5]);
+
+        let raw_list = downsample(&voxel_list, 2, ColorMode::Average);
+        assert!((raw_list[0].color[0] - 2.0 / 3.0).abs() < 1e-6);
+        assert!((raw_list[0].color[2] - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn levels_switch_past_the_hysteresis_only() {
+        let settings = LodSettings::new();
+        let edge = DEFAULT_DISTANCE_LIST[0];
+
+        assert_eq!(settings.select(0, edge + 1.0), 0);
+        assert_eq!(settings.select(0, edge + DEFAULT_HYSTERESIS + 1.0), 1);
+        assert_eq!(settings.select(1, edge - 1.0), 1);
+        assert_eq!(settings.select(1, edge - DEFAULT_HYSTERESIS - 1.0), 0);
+        assert_eq!(settings.select(0, 10_000.0), LOD_SCALE_LIST.len());
+    }
+}
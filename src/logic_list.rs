@@ -0,0 +1,558 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use iced_wgpu::wgpu;
+
+use crate::Instance;
+use crate::chunk::ChunkManager;
+use crate::material_list::{MaterialList, TAG_BURNING, TAG_FALLING, TAG_FLAMMABLE, TAG_GROWING, TAG_LIQUID};
+use crate::query::{VoxelQuery, NEIGHBOR_6, neighbor_26, offset};
+
+pub const DEFAULT_RATE: f32 = 10.0;
+//most ticks a single /sim step runs, they all run inside one frame
+pub const MAX_MANUAL_STEP: u32 = 1000;
+
+//a single game tick runs at most this many simulation ticks and drops the rest
+const MAX_STEP_PER_TICK: u32 = 4;
+//left over voxels keep their place in the order and run next tick
+const MAX_ACTIVE_PER_TICK: usize = 65536;
+
+//chances per tick, in 1/1000
+const FIRE_SPREAD: u32 = 250;
+const FIRE_BURN_OUT: u32 = 60;
+const VINE_GROW: u32 = 40;
+
+//color given to voxels catching fire, alpha is the intensity of their light
+const FIRE_COLOR: [f32; 4] = [1.0, 0.6, 0.3, 0.5];
+
+//voxels falling past this are removed, over open space nothing else would stop them
+pub const MIN_SIM_Y: i32 = -256;
+
+const SIM_TAGS: u32 = TAG_BURNING | TAG_FALLING | TAG_LIQUID | TAG_GROWING;
+
+const DOWN: [i32; 3] = [0, -1, 0];
+const UP: [i32; 3] = [0, 1, 0];
+const SIDE_LIST: [[i32; 3]; 4] = [[1, 0, 0], [0, 0, 1], [-1, 0, 0], [0, 0, -1]];
+
+//deterministic per position and tick, every client rolls the same numbers
+pub fn hash(position: [i32; 3], tick: u64, salt: u32) -> u32 {
+    let mut h = (position[0] as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (position[1] as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (position[2] as u32 as u64).wrapping_mul(0x1656_67B1_9E37_79F9)
+        ^ tick.wrapping_mul(0x27D4_EB2F_1656_67C5)
+        ^ salt as u64;
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    h ^= h >> 33;
+    h as u32
+}
+
+fn chance(position: [i32; 3], tick: u64, salt: u32, per_mille: u32) -> bool {
+    hash(position, tick, salt) % 1000 < per_mille
+}
+
+//side directions starting at a per voxel offset so flows do not drift one way
+fn side_order(position: [i32; 3], tick: u64) -> impl Iterator<Item = [i32; 3]> {
+    let start = (hash(position, tick, 7) % 4) as usize;
+    (0..4).map(move |i| SIDE_LIST[(start + i) % 4])
+}
+
+//the changes of one tick, read against the world as it was when the tick started
+//the rules only read through VoxelQuery, so they run on a plain map as well
+struct TickState<'a, W: VoxelQuery> {
+    world: &'a W,
+    removed: HashSet<[i32; 3]>,
+    put: HashMap<[i32; 3], Instance>,
+    woken: Vec<[i32; 3]>,
+}
+
+impl<'a, W: VoxelQuery> TickState<'a, W> {
+
+    fn new(world: &'a W) -> Self {
+        Self { world, removed: Default::default(), put: Default::default(), woken: Default::default() }
+    }
+
+    fn current(&self, position: [i32; 3]) -> Option<Instance> {
+        match self.put.get(&position) {
+            Some(ins) => Some(*ins),
+            None if self.removed.contains(&position) => None,
+            None => self.world.get(position).copied(),
+        }
+    }
+
+    fn occupied(&self, position: [i32; 3]) -> bool {
+        self.current(position).is_some()
+    }
+
+    fn set(&mut self, position: [i32; 3], mut ins: Instance) {
+        ins.position = cgmath::Vector3 { x: position[0] as f32, y: position[1] as f32, z: position[2] as f32 };
+        self.put.insert(position, ins);
+        self.woken.push(position);
+    }
+
+    fn remove(&mut self, position: [i32; 3]) {
+        self.put.remove(&position);
+        self.removed.insert(position);
+        self.woken.push(position);
+    }
+
+    fn move_voxel(&mut self, from: [i32; 3], to: [i32; 3], ins: Instance) {
+        self.remove(from);
+        self.set(to, ins);
+    }
+}
+
+fn fall<W: VoxelQuery>(state: &mut TickState<W>, position: [i32; 3], ins: Instance, tick: u64) -> bool {
+
+    let below = offset(position, DOWN);
+    if below[1] < MIN_SIM_Y {
+        state.remove(position);
+        return true;
+    }
+    if !state.occupied(below) {
+        state.move_voxel(position, below, ins);
+        return true;
+    }
+
+    //slide off the top of a pile, the side cell has to be free as well
+    for side in side_order(position, tick) {
+        let target = offset(below, side);
+        if !state.occupied(target) && !state.occupied(offset(position, side)) {
+            state.move_voxel(position, target, ins);
+            return true;
+        }
+    }
+    false
+}
+
+fn flow<W: VoxelQuery>(state: &mut TickState<W>, position: [i32; 3], ins: Instance, tick: u64, material_list: &MaterialList) -> bool {
+
+    if fall(state, position, ins, tick) {
+        return true;
+    }
+
+    //spread sideways only under pressure or over an edge, so a settled layer stays still
+    let pressed = match state.current(offset(position, UP)) {
+        Some(above) => material_list.get(above.material).has_tag(TAG_LIQUID),
+        None => false,
+    };
+
+    for side in side_order(position, tick) {
+        let target = offset(position, side);
+        if !state.occupied(target) && (pressed || !state.occupied(offset(target, DOWN))) {
+            state.move_voxel(position, target, ins);
+            return true;
+        }
+    }
+    false
+}
+
+//returns true while the fire keeps burning
+fn burn<W: VoxelQuery>(state: &mut TickState<W>, position: [i32; 3], tick: u64, material_list: &MaterialList, fire: usize) -> bool {
+
+    let mut fuel = false;
+    for (i, n) in NEIGHBOR_6.iter().enumerate() {
+        let target = offset(position, *n);
+        if let Some(mut other) = state.current(target) {
+            if !material_list.get(other.material).has_tag(TAG_FLAMMABLE) {
+                continue;
+            }
+            if chance(target, tick, i as u32, FIRE_SPREAD) {
+                other.material = fire;
+                other.color = FIRE_COLOR.into();
+                state.set(target, other);
+            } else {
+                fuel = true;
+            }
+        }
+    }
+
+    //a fire only dies down once nothing next to it is left to catch
+    if !fuel && chance(position, tick, 11, FIRE_BURN_OUT) {
+        state.remove(position);
+        return false;
+    }
+    true
+}
+
+//grows from the tip, returns true while the tip may still grow
+fn grow<W: VoxelQuery>(state: &mut TickState<W>, position: [i32; 3], ins: Instance, tick: u64, material_list: &MaterialList) -> bool {
+
+    let is_vine = |state: &TickState<W>, p: [i32; 3]| match state.current(p) {
+        Some(v) => material_list.get(v.material).has_tag(TAG_GROWING),
+        None => false,
+    };
+
+    let candidate_list: Vec<[i32; 3]> = std::iter::once(UP)
+        .chain(side_order(position, tick))
+        .map(|d| offset(position, d))
+        .filter(|c| !state.occupied(*c))
+        .filter(|c| {
+            let mut vine = 0;
+            let mut support = false;
+            NEIGHBOR_6.iter().for_each(|n| {
+                let p = offset(*c, *n);
+                if is_vine(state, p) {
+                    vine += 1;
+                } else if state.occupied(p) {
+                    support = true;
+                }
+            });
+            //climb along something solid and keep strands one voxel thin
+            support && vine == 1
+        })
+        .collect();
+
+    if candidate_list.is_empty() {
+        return false;
+    }
+
+    if chance(position, tick, 13, VINE_GROW) {
+        let index = hash(position, tick, 17) as usize % candidate_list.len();
+        state.set(candidate_list[index], ins);
+        return false;
+    }
+    true
+}
+
+//what one tick changed, applied to the world before the next one starts
+struct TickChange {
+    removed: Vec<[i32; 3]>,
+    put: Vec<Instance>,
+    //positions that may be able to move next tick
+    next: HashSet<[i32; 3]>,
+}
+
+pub struct Simulation {
+    pub paused: bool,
+    //ticks per second
+    pub rate: f32,
+    pub tick: u64,
+    accumulator: f32,
+    //keyed [y, x, z] so ticks run bottom up and piles settle in one pass
+    active: BTreeSet<[i32; 3]>,
+}
+
+fn key(position: [i32; 3]) -> [i32; 3] {
+    [position[1], position[0], position[2]]
+}
+
+fn unkey(key: [i32; 3]) -> [i32; 3] {
+    [key[1], key[0], key[2]]
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self { paused: false, rate: DEFAULT_RATE, tick: 0, accumulator: 0.0, active: Default::default() }
+    }
+
+    pub fn active_len(&self) -> usize {
+        self.active.len()
+    }
+
+    //called every game tick with the fixed tick length, so the step count only depends on the rates
+    pub fn update(&mut self, dt: f32, chunk_manager: &mut ChunkManager, device: &wgpu::Device) {
+
+        if self.paused || self.rate <= 0.0 {
+            return;
+        }
+
+        self.accumulator += dt;
+        let step = 1.0 / self.rate;
+        let mut count = 0;
+
+        while self.accumulator >= step && count < MAX_STEP_PER_TICK {
+            self.accumulator -= step;
+            self.step(chunk_manager, device);
+            count += 1;
+        }
+        if count == MAX_STEP_PER_TICK {
+            self.accumulator = 0.0;
+        }
+    }
+
+    //picks up voxels the user touched since the last tick
+    fn wake<W: VoxelQuery>(&mut self, world: &W, material_list: &MaterialList, wake_list: Vec<([i32; 3], [i32; 3])>) {
+
+        wake_list.into_iter().for_each(|(first, last)| {
+            let first = offset(first, [-1, -1, -1]);
+            let last = offset(last, [1, 1, 1]);
+            world.iter_box(first, last).for_each(|v| {
+                if material_list.get(v.material).tags & SIM_TAGS != 0 {
+                    self.active.insert(key(v.grid_position()));
+                }
+            });
+        });
+    }
+
+    //runs the rules for the active voxels against the world as it is, the caller applies the result
+    fn run<W: VoxelQuery>(&mut self, world: &W, material_list: &MaterialList) -> TickChange {
+
+        let tick = self.tick;
+        let fire = material_list.find("fire");
+
+        let mut run_list: Vec<[i32; 3]> = Default::default();
+        while run_list.len() < MAX_ACTIVE_PER_TICK {
+            match self.active.pop_first() {
+                Some(k) => run_list.push(unkey(k)),
+                None => break,
+            }
+        }
+
+        let mut state = TickState::new(world);
+
+        let mut keep_list: Vec<[i32; 3]> = Default::default();
+
+        run_list.into_iter().for_each(|position| {
+
+            //already decided this tick, e.g. a voxel that just moved in
+            if state.put.contains_key(&position) {
+                return;
+            }
+            let ins = match state.current(position) {
+                Some(ins) => ins,
+                None => return,
+            };
+            let material = material_list.get(ins.material);
+
+            let keep = if material.has_tag(TAG_BURNING) {
+                match fire {
+                    Some(fire) => burn(&mut state, position, tick, material_list, fire),
+                    None => false,
+                }
+            } else if material.has_tag(TAG_LIQUID) {
+                flow(&mut state, position, ins, tick, material_list);
+                false
+            } else if material.has_tag(TAG_FALLING) {
+                fall(&mut state, position, ins, tick);
+                false
+            } else if material.has_tag(TAG_GROWING) {
+                grow(&mut state, position, ins, tick, material_list)
+            } else {
+                false
+            };
+
+            if keep {
+                keep_list.push(position);
+            }
+        });
+
+        let TickState { removed, put, woken, .. } = state;
+
+        //neighbors of every change may be able to move now
+        let mut next: HashSet<[i32; 3]> = keep_list.into_iter().collect();
+        let offset_list = neighbor_26();
+        woken.iter().for_each(|p| {
+            next.insert(*p);
+            offset_list.iter().for_each(|o| {
+                next.insert(offset(*p, *o));
+            });
+        });
+
+        TickChange { removed: removed.into_iter().collect(), put: put.into_values().collect(), next }
+    }
+
+    //called once the change is in the world
+    fn activate<W: VoxelQuery>(&mut self, world: &W, material_list: &MaterialList, next: HashSet<[i32; 3]>) {
+        next.into_iter().for_each(|p| {
+            if let Some(v) = world.get(p) {
+                if material_list.get(v.material).tags & SIM_TAGS != 0 {
+                    self.active.insert(key(p));
+                }
+            }
+        });
+    }
+
+    pub fn step(&mut self, chunk_manager: &mut ChunkManager, device: &wgpu::Device) {
+
+        let wake_list: Vec<([i32; 3], [i32; 3])> = chunk_manager.wake_list.drain(..).collect();
+        self.wake(&*chunk_manager, &chunk_manager.material_list, wake_list);
+        if self.active.is_empty() {
+            self.tick += 1;
+            return;
+        }
+
+        let TickChange { removed, put, next } = self.run(&*chunk_manager, &chunk_manager.material_list);
+
+        chunk_manager.remove_voxels(removed, device);
+        chunk_manager.place_instances(put, device);
+        //our own edits are already in the active set
+        chunk_manager.wake_list.clear();
+
+        self.activate(&*chunk_manager, &chunk_manager.material_list, next);
+
+        self.tick += 1;
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voxel(position: [i32; 3], material: usize) -> Instance {
+        Instance::new(position, [1.0, 1.0, 1.0, 1.0], [0.0, 1.0, 0.0], material)
+    }
+
+    //a 5x1x5 stone floor at y = 0
+    fn floor(material_list: &MaterialList) -> HashMap<[i32; 3], Instance> {
+        let stone = material_list.find("stone").unwrap();
+        let mut world: HashMap<[i32; 3], Instance> = Default::default();
+        for x in -2..3 {
+            for z in -2..3 {
+                world.insert([x, 0, z], voxel([x, 0, z], stone));
+            }
+        }
+        world
+    }
+
+    //the map version of Simulation::step
+    fn step_map(sim: &mut Simulation, world: &mut HashMap<[i32; 3], Instance>, material_list: &MaterialList, wake_list: Vec<([i32; 3], [i32; 3])>) {
+        sim.wake(&*world, material_list, wake_list);
+        let TickChange { removed, put, next } = sim.run(&*world, material_list);
+        removed.iter().for_each(|p| {
+            world.remove(p);
+        });
+        put.into_iter().for_each(|ins| {
+            world.insert(ins.grid_position(), ins);
+        });
+        sim.activate(&*world, material_list, next);
+        sim.tick += 1;
+    }
+
+    #[test]
+    fn sand_over_open_space_stops_at_the_kill_plane() {
+        let material_list = MaterialList::new();
+        let sand = material_list.find("sand").unwrap();
+        let position = [0, MIN_SIM_Y + 3, 0];
+        let mut world: HashMap<[i32; 3], Instance> = Default::default();
+        world.insert(position, voxel(position, sand));
+
+        let mut sim = Simulation::new();
+        step_map(&mut sim, &mut world, &material_list, vec![(position, position)]);
+        assert_eq!(sim.active_len(), 1);
+        for _ in 0..10 {
+            step_map(&mut sim, &mut world, &material_list, Default::default());
+        }
+        assert!(world.is_empty());
+        assert_eq!(sim.active_len(), 0);
+    }
+
+    #[test]
+    fn fall_drops_then_slides_off_a_pile() {
+        let material_list = MaterialList::new();
+        let sand = material_list.find("sand").unwrap();
+        let mut world = floor(&material_list);
+        world.insert([0, 3, 0], voxel([0, 3, 0], sand));
+
+        let mut state = TickState::new(&world);
+        assert!(fall(&mut state, [0, 3, 0], world[&[0, 3, 0]], 0));
+        assert!(state.removed.contains(&[0, 3, 0]));
+        assert_eq!(state.put[&[0, 2, 0]].position.y, 2.0);
+
+        //resting on another grain it slides down one of the sides
+        world.insert([0, 1, 0], voxel([0, 1, 0], sand));
+        world.insert([0, 2, 0], voxel([0, 2, 0], sand));
+        let mut state = TickState::new(&world);
+        assert!(fall(&mut state, [0, 2, 0], world[&[0, 2, 0]], 0));
+        let (target, _) = state.put.iter().next().unwrap();
+        assert_eq!(target[1], 1);
+        assert_eq!(target[0].abs() + target[2].abs(), 1);
+
+        //flat on the floor it stays
+        let mut state = TickState::new(&world);
+        assert!(!fall(&mut state, [0, 1, 0], world[&[0, 1, 0]], 0));
+        assert!(state.put.is_empty() && state.removed.is_empty());
+    }
+
+    #[test]
+    fn flow_spreads_only_under_pressure() {
+        let material_list = MaterialList::new();
+        let water = material_list.find("water").unwrap();
+        let mut world = floor(&material_list);
+        world.insert([0, 1, 0], voxel([0, 1, 0], water));
+
+        //a settled layer on a floor does not wander
+        let mut state = TickState::new(&world);
+        assert!(!flow(&mut state, [0, 1, 0], world[&[0, 1, 0]], 0, &material_list));
+        assert!(state.put.is_empty());
+
+        world.insert([0, 2, 0], voxel([0, 2, 0], water));
+        let mut state = TickState::new(&world);
+        assert!(flow(&mut state, [0, 1, 0], world[&[0, 1, 0]], 0, &material_list));
+        let (target, _) = state.put.iter().next().unwrap();
+        assert_eq!(target[1], 1);
+        assert_eq!(target[0].abs() + target[2].abs(), 1);
+        assert!(state.removed.contains(&[0, 1, 0]));
+    }
+
+    #[test]
+    fn burn_spreads_to_flammable_neighbors_and_dies_out() {
+        let material_list = MaterialList::new();
+        let fire = material_list.find("fire").unwrap();
+        let wood = material_list.find("wood").unwrap();
+        let mut world = floor(&material_list);
+        world.insert([0, 1, 0], voxel([0, 1, 0], fire));
+        world.insert([1, 1, 0], voxel([1, 1, 0], wood));
+
+        let caught = (0..200u64).find_map(|tick| {
+            let mut state = TickState::new(&world);
+            assert!(burn(&mut state, [0, 1, 0], tick, &material_list, fire));
+            state.put.get(&[1, 1, 0]).copied()
+        });
+        let caught = caught.expect("the wood never caught fire");
+        assert_eq!(caught.material, fire);
+        assert_eq!(caught.color, FIRE_COLOR.into());
+
+        //with nothing left to catch the fire goes out, and never spreads to the stone below
+        world.remove(&[1, 1, 0]);
+        let out = (0..1000u64).find(|tick| {
+            let mut state = TickState::new(&world);
+            let keep = burn(&mut state, [0, 1, 0], *tick, &material_list, fire);
+            assert!(!state.put.contains_key(&[0, 0, 0]));
+            !keep && state.removed.contains(&[0, 1, 0])
+        });
+        assert!(out.is_some());
+    }
+
+    #[test]
+    fn grow_climbs_along_support() {
+        let material_list = MaterialList::new();
+        let vine = material_list.find("vine").unwrap();
+        let mut world = floor(&material_list);
+        world.insert([0, 1, 0], voxel([0, 1, 0], vine));
+
+        let grown = (0..1000u64).find_map(|tick| {
+            let mut state = TickState::new(&world);
+            let keep = grow(&mut state, [0, 1, 0], world[&[0, 1, 0]], tick, &material_list);
+            match state.put.iter().next() {
+                Some((p, ins)) => {
+                    assert!(!keep);
+                    Some((*p, *ins))
+                }
+                None => {
+                    assert!(keep);
+                    None
+                }
+            }
+        });
+        let (position, ins) = grown.expect("the vine never grew");
+        assert_eq!(ins.material, vine);
+        //straight up has nothing to hold on to, so it creeps along the floor
+        assert_eq!(position[1], 1);
+        assert_eq!(position[0].abs() + position[2].abs(), 1);
+
+        //a tip in the air has nowhere to go
+        let mut world: HashMap<[i32; 3], Instance> = Default::default();
+        world.insert([0, 1, 0], voxel([0, 1, 0], vine));
+        let mut state = TickState::new(&world);
+        assert!(!grow(&mut state, [0, 1, 0], world[&[0, 1, 0]], 0, &material_list));
+        assert!(state.put.is_empty());
+    }
+}
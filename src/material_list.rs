@@ -0,0 +1,201 @@
+//simulation tags, combined as bit flags
+pub const TAG_FLAMMABLE: u32 = 1;
+pub const TAG_BURNING: u32 = 1 << 1;
+pub const TAG_FALLING: u32 = 1 << 2;
+pub const TAG_LIQUID: u32 = 1 << 3;
+pub const TAG_GROWING: u32 = 1 << 4;
+
+//size of the material uniform array, keep in sync with the terrain shaders
+pub const MAX_MATERIAL: usize = 64;
+
+pub const DEFAULT_MATERIAL: usize = 0;
+
+#[derive(Clone)]
+pub struct Material {
+    pub name: String,
+    //tints the voxel color, white keeps it unchanged
+    pub color: [f32; 4],
+    pub emissive_strength: f32,
+    pub emissive_color: [f32; 3],
+    pub opacity: f32,
+    pub roughness: f32,
+    pub casts_light: bool,
+    pub tags: u32,
+}
+
+impl Material {
+    pub fn new(name: &str, color: [f32; 4]) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            emissive_strength: 0.0,
+            emissive_color: [0.0, 0.0, 0.0],
+            opacity: 1.0,
+            roughness: 0.5,
+            casts_light: false,
+            tags: 0,
+        }
+    }
+
+    pub fn emissive(mut self, emissive_color: [f32; 3], emissive_strength: f32) -> Self {
+        self.emissive_color = emissive_color;
+        self.emissive_strength = emissive_strength;
+        self
+    }
+
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn roughness(mut self, roughness: f32) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn casts_light(mut self) -> Self {
+        self.casts_light = true;
+        self
+    }
+
+    pub fn tags(mut self, tags: u32) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn has_tag(&self, tag: u32) -> bool {
+        self.tags & tag != 0
+    }
+
+    pub fn to_raw(&self) -> MaterialRaw {
+        MaterialRaw {
+            color: self.color,
+            emissive: [self.emissive_color[0], self.emissive_color[1], self.emissive_color[2], self.emissive_strength],
+            opacity: self.opacity,
+            roughness: self.roughness,
+            casts_light: self.casts_light as u32,
+            tags: self.tags,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialRaw {
+    color: [f32; 4],
+    emissive: [f32; 4],
+    opacity: f32,
+    roughness: f32,
+    casts_light: u32,
+    tags: u32,
+}
+
+#[derive(Clone)]
+pub struct MaterialList {
+    pub material_list: Vec<Material>,
+    //set when the table changed and the material uniform must be rewritten
+    pub need_update: bool,
+}
+
+impl MaterialList {
+    pub fn new() -> Self {
+
+        let material_list = vec![
+            Material::new("object", [1.0, 1.0, 1.0, 1.0]),
+            Material::new("fire", [1.0, 0.55, 0.2, 1.0])
+                .emissive([1.0, 0.6, 0.2], 0.8)
+                .opacity(0.8)
+                .casts_light()
+                .tags(TAG_BURNING),
+            Material::new("wood", [0.45, 0.3, 0.15, 1.0]).roughness(0.9).tags(TAG_FLAMMABLE),
+            Material::new("leaves", [0.35, 0.65, 0.25, 1.0]).roughness(0.8).tags(TAG_FLAMMABLE),
+            Material::new("sand", [0.95, 0.85, 0.55, 1.0]).roughness(1.0).tags(TAG_FALLING),
+            Material::new("water", [0.25, 0.45, 0.95, 1.0]).opacity(0.6).roughness(0.1).tags(TAG_LIQUID),
+            Material::new("stone", [0.55, 0.55, 0.55, 1.0]).roughness(0.8),
+            Material::new("dirt", [0.5, 0.35, 0.2, 1.0]).roughness(1.0),
+            Material::new("grass", [0.4, 0.75, 0.3, 1.0]).roughness(0.9).tags(TAG_FLAMMABLE),
+            Material::new("vine", [0.25, 0.6, 0.2, 1.0]).roughness(0.9).tags(TAG_FLAMMABLE | TAG_GROWING),
+            Material::new("glass", [0.85, 0.95, 1.0, 1.0]).opacity(0.3).roughness(0.05),
+            Material::new("lamp", [1.0, 1.0, 0.9, 1.0]).emissive([1.0, 0.95, 0.8], 1.0).casts_light(),
+        ];
+
+        Self { material_list, need_update: false }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.material_list.iter().position(|m| m.name == name)
+    }
+
+    pub fn get(&self, index: usize) -> &Material {
+        self.material_list.get(index).unwrap_or(&self.material_list[DEFAULT_MATERIAL])
+    }
+
+    //adds or replaces a material by name, None once the table is full
+    pub fn register(&mut self, material: Material) -> Option<usize> {
+        self.need_update = true;
+        match self.find(&material.name) {
+            Some(index) => {
+                self.material_list[index] = material;
+                Some(index)
+            }
+            None if self.material_list.len() < MAX_MATERIAL => {
+                self.material_list.push(material);
+                Some(self.material_list.len() - 1)
+            }
+            None => None,
+        }
+    }
+
+    pub fn names(&self) -> String {
+        self.material_list.iter().map(|m| m.name.as_str()).collect::<Vec<_>>().join("|")
+    }
+
+    //fixed size array uploaded to the material uniform
+    pub fn to_raw(&self) -> Vec<MaterialRaw> {
+        let mut raw_list: Vec<MaterialRaw> = self.material_list.iter().map(Material::to_raw).collect();
+        raw_list.resize(MAX_MATERIAL, bytemuck::Zeroable::zeroed());
+        raw_list
+    }
+}
+
+impl Default for MaterialList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILT_IN: [&str; 12] = ["object", "fire", "wood", "leaves", "sand", "water", "stone", "dirt", "grass", "vine", "glass", "lamp"];
+
+    #[test]
+    fn raw_table_matches_the_shader_layout() {
+        //two vec4 and four scalars, the stride the shader array expects
+        assert_eq!(std::mem::size_of::<MaterialRaw>(), 48);
+
+        let material_list = MaterialList::new();
+        let raw_list = material_list.to_raw();
+        assert_eq!(raw_list.len(), MAX_MATERIAL);
+        assert_eq!(bytemuck::cast_slice::<MaterialRaw, u8>(&raw_list).len(), 48 * MAX_MATERIAL);
+
+        let lamp = raw_list[material_list.find("lamp").unwrap()];
+        assert_eq!(lamp.emissive, [1.0, 0.95, 0.8, 1.0]);
+        assert_eq!(lamp.casts_light, 1);
+        //unused slots stay zeroed
+        assert_eq!(raw_list[MAX_MATERIAL - 1].color, [0.0; 4]);
+    }
+
+    #[test]
+    fn finds_every_built_in_material() {
+        let material_list = MaterialList::new();
+        assert_eq!(material_list.material_list.len(), BUILT_IN.len());
+        BUILT_IN.iter().enumerate().for_each(|(i, name)| {
+            assert_eq!(material_list.find(name), Some(i));
+            assert_eq!(&material_list.get(i).name, name);
+        });
+        assert_eq!(material_list.find("unobtainium"), None);
+        assert_eq!(material_list.find("object"), Some(DEFAULT_MATERIAL));
+    }
+}
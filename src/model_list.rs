@@ -3,7 +3,36 @@ use cgmath::Point3;
 use cgmath::Rad;
 use cgmath::Angle;
 
-use crate::{Instance, VoxelType};
+use crate::Instance;
+use crate::material_list::DEFAULT_MATERIAL;
+
+//orientation of flat models such as text and images, named after the axis they run along
+#[derive(Copy, Clone, PartialEq)]
+pub enum PlaneAxis {
+    X,
+    Z,
+    Y,
+}
+
+impl PlaneAxis {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "x" => Some(PlaneAxis::X),
+            "z" => Some(PlaneAxis::Z),
+            "y" | "flat" => Some(PlaneAxis::Y),
+            _ => None,
+        }
+    }
+
+    //run direction, up direction and extrusion direction, facing the default camera
+    pub fn basis(&self) -> ([i32; 3], [i32; 3], [i32; 3]) {
+        match self {
+            PlaneAxis::X => ([1, 0, 0], [0, 1, 0], [0, 0, -1]),
+            PlaneAxis::Z => ([0, 0, -1], [0, 1, 0], [-1, 0, 0]),
+            PlaneAxis::Y => ([1, 0, 0], [0, 0, -1], [0, -1, 0]),
+        }
+    }
+}
 
 
 pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id:i32) -> Option<Instance>{
@@ -24,7 +53,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else {
@@ -51,7 +80,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -85,7 +114,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -117,7 +146,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -151,7 +180,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -189,7 +218,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -225,7 +254,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -253,7 +282,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -283,7 +312,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -317,7 +346,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -373,7 +402,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -403,7 +432,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -449,7 +478,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })           
             }
             else{
@@ -473,7 +502,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
 
@@ -500,7 +529,7 @@ pub fn parse_place(x:i32,y:i32,z:i32,first:[i32;3],last:[i32;3],color:[f32;4],id
                     depth_strength:0.5,
                     normal_strength:1.0,
                     light_strength:0.0,
-                    current_type: VoxelType::Object,
+                    material: DEFAULT_MATERIAL,
                 })
             }
             else{
@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::Instance;
+use crate::chunk::{ChunkManager, chunk_position};
+
+pub const NEIGHBOR_6: [[i32; 3]; 6] = [
+    [1, 0, 0], [-1, 0, 0],
+    [0, 1, 0], [0, -1, 0],
+    [0, 0, 1], [0, 0, -1],
+];
+
+pub fn neighbor_26() -> Vec<[i32; 3]> {
+    let mut offset_list = Vec::with_capacity(26);
+    for x in -1..2 {
+        for y in -1..2 {
+            for z in -1..2 {
+                if x != 0 || y != 0 || z != 0 {
+                    offset_list.push([x, y, z]);
+                }
+            }
+        }
+    }
+    offset_list
+}
+
+pub fn offset(position: [i32; 3], offset: [i32; 3]) -> [i32; 3] {
+    [position[0] + offset[0], position[1] + offset[1], position[2] + offset[2]]
+}
+
+//the same box with first holding the low and last the high corner
+pub fn sorted_box(first: [i32; 3], last: [i32; 3]) -> ([i32; 3], [i32; 3]) {
+    (
+        [first[0].min(last[0]), first[1].min(last[1]), first[2].min(last[2])],
+        [first[0].max(last[0]), first[1].max(last[1]), first[2].max(last[2])],
+    )
+}
+
+pub fn in_box(position: [i32; 3], first: [i32; 3], last: [i32; 3]) -> bool {
+    (0..3).all(|i| position[i] >= first[i] && position[i] <= last[i])
+}
+
+//flood fill predicates, called with the seed voxel and the candidate
+pub fn same_color(seed: &Instance, other: &Instance) -> bool {
+    seed.color == other.color
+}
+
+pub fn any_solid(_seed: &Instance, _other: &Instance) -> bool {
+    true
+}
+
+//read access to a voxel world, implemented by the chunk manager and by plain cpu maps
+pub trait VoxelQuery {
+
+    fn get(&self, position: [i32; 3]) -> Option<&Instance>;
+
+    //every voxel inside the inclusive box, in no particular order, the corners can come either way round
+    fn iter_box<'a>(&'a self, first: [i32; 3], last: [i32; 3]) -> Box<dyn Iterator<Item = &'a Instance> + 'a>;
+
+    fn is_solid(&self, position: [i32; 3]) -> bool {
+        self.get(position).is_some()
+    }
+
+    fn count<P>(&self, first: [i32; 3], last: [i32; 3], predicate: P) -> usize
+    where
+        P: Fn(&Instance) -> bool,
+    {
+        self.iter_box(first, last).filter(|v| predicate(v)).count()
+    }
+
+    fn neighbors_6(&self, position: [i32; 3]) -> [Option<&Instance>; 6] {
+        let mut neighbor_list = [None; 6];
+        for i in 0..6 {
+            neighbor_list[i] = self.get(offset(position, NEIGHBOR_6[i]));
+        }
+        neighbor_list
+    }
+
+    fn neighbors_26(&self, position: [i32; 3]) -> Vec<([i32; 3], &Instance)> {
+        neighbor_26()
+            .into_iter()
+            .filter_map(|o| {
+                let p = offset(position, o);
+                self.get(p).map(|v| (p, v))
+            })
+            .collect()
+    }
+
+    //6-connected fill from start, stops after limit voxels
+    fn flood_fill<P>(&self, start: [i32; 3], limit: usize, predicate: P) -> Vec<[i32; 3]>
+    where
+        P: Fn(&Instance, &Instance) -> bool,
+    {
+        let mut filled: Vec<[i32; 3]> = Default::default();
+
+        let seed = match self.get(start) {
+            Some(seed) => seed,
+            None => return filled,
+        };
+
+        let mut visited: HashSet<[i32; 3]> = Default::default();
+        let mut queue: VecDeque<[i32; 3]> = Default::default();
+
+        visited.insert(start);
+        queue.push_back(start);
+
+        while let Some(position) = queue.pop_front() {
+
+            if filled.len() >= limit {
+                break;
+            }
+            filled.push(position);
+
+            NEIGHBOR_6.iter().for_each(|o| {
+                let p = offset(position, *o);
+                if !visited.contains(&p) {
+                    if let Some(v) = self.get(p) {
+                        if predicate(seed, v) {
+                            visited.insert(p);
+                            queue.push_back(p);
+                        }
+                    }
+                }
+            });
+        }
+
+        filled
+    }
+}
+
+impl VoxelQuery for ChunkManager {
+
+    fn get(&self, position: [i32; 3]) -> Option<&Instance> {
+        let chunk = &self.chunk_list[*self.chunk_hash.get(&chunk_position(position))?];
+        chunk.position_hash.get(&position).map(|i| &chunk.voxel_data[*i])
+    }
+
+    fn iter_box<'a>(&'a self, first: [i32; 3], last: [i32; 3]) -> Box<dyn Iterator<Item = &'a Instance> + 'a> {
+
+        let (first, last) = sorted_box(first, last);
+        let chunk_first = chunk_position(first);
+        let chunk_last = chunk_position(last);
+
+        Box::new(
+            self.chunk_hash
+                .iter()
+                .filter(move |(p, _)| in_box(**p, chunk_first, chunk_last))
+                .flat_map(move |(_, i)| self.chunk_list[*i].voxel_data.iter())
+                .filter(move |v| in_box(v.grid_position(), first, last)),
+        )
+    }
+}
+
+//scratch worlds built on the cpu, e.g. by generators before they are placed
+impl VoxelQuery for HashMap<[i32; 3], Instance> {
+
+    fn get(&self, position: [i32; 3]) -> Option<&Instance> {
+        HashMap::get(self, &position)
+    }
+
+    fn iter_box<'a>(&'a self, first: [i32; 3], last: [i32; 3]) -> Box<dyn Iterator<Item = &'a Instance> + 'a> {
+        let (first, last) = sorted_box(first, last);
+        Box::new(self.iter().filter(move |(p, _)| in_box(**p, first, last)).map(|(_, v)| v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material_list::DEFAULT_MATERIAL;
+
+    fn voxel(position: [i32; 3], color: [f32; 4]) -> Instance {
+        Instance::new(position, color, [0.0, 1.0, 0.0], DEFAULT_MATERIAL)
+    }
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    //a 4x1x1 red bar, a blue voxel touching its end and a detached red voxel
+    fn world() -> HashMap<[i32; 3], Instance> {
+        let mut world: HashMap<[i32; 3], Instance> = Default::default();
+        for x in 0..4 {
+            world.insert([x, 0, 0], voxel([x, 0, 0], RED));
+        }
+        world.insert([4, 0, 0], voxel([4, 0, 0], BLUE));
+        world.insert([0, 5, 0], voxel([0, 5, 0], RED));
+        world
+    }
+
+    #[test]
+    fn get_and_solid() {
+        let world = world();
+        assert!(world.is_solid([2, 0, 0]));
+        assert!(!world.is_solid([2, 1, 0]));
+        assert_eq!(VoxelQuery::get(&world, [4, 0, 0]).unwrap().color, cgmath::Vector4::from(BLUE));
+    }
+
+    #[test]
+    fn box_iteration_is_inclusive() {
+        let world = world();
+        assert_eq!(world.iter_box([0, 0, 0], [3, 0, 0]).count(), 4);
+        assert_eq!(world.iter_box([-10, -10, -10], [10, 10, 10]).count(), 6);
+        assert_eq!(world.count([0, 0, 0], [10, 0, 0], |v| v.color.z > 0.5), 1);
+        //reversed corners describe the same box
+        assert_eq!(world.iter_box([3, 0, 0], [0, 0, 0]).count(), 4);
+        assert_eq!(world.iter_box([10, 10, -10], [-10, -10, 10]).count(), 6);
+    }
+
+    #[test]
+    fn neighbors() {
+        let world = world();
+        let six = world.neighbors_6([1, 0, 0]);
+        assert_eq!(six.iter().filter(|n| n.is_some()).count(), 2);
+        assert!(six[0].is_some() && six[1].is_some());
+        assert_eq!(world.neighbors_26([1, 1, 0]).len(), 3);
+        assert_eq!(neighbor_26().len(), 26);
+    }
+
+    #[test]
+    fn flood_fill_predicates_and_limit() {
+        let world = world();
+        assert_eq!(world.flood_fill([0, 0, 0], 100, same_color).len(), 4);
+        assert_eq!(world.flood_fill([0, 0, 0], 100, any_solid).len(), 5);
+        assert_eq!(world.flood_fill([0, 0, 0], 2, any_solid).len(), 2);
+        assert!(world.flood_fill([9, 9, 9], 100, any_solid).is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+use cgmath::*;
+
+use crate::chunk::{ChunkManager, chunk_position};
+use crate::query::VoxelQuery;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub voxel: [i32; 3],
+    //points out of the face the ray entered through, zero when the ray starts inside the voxel
+    pub face_normal: [i32; 3],
+    pub chunk: [i32; 3],
+    pub distance: f32,
+}
+
+//grid traversal (Amanatides & Woo), visits every voxel the ray crosses exactly once in order.
+//voxel v covers [v - 0.5, v + 0.5) on each axis, matching how instances are positioned
+pub fn traverse<F>(origin: Point3<f32>, dir: Vector3<f32>, max_dist: f32, mut is_solid: F) -> Option<Hit>
+where
+    F: FnMut([i32; 3]) -> bool,
+{
+    if dir.magnitude2() == 0.0 {
+        return None;
+    }
+    let dir = dir.normalize();
+
+    let start = [origin.x + 0.5, origin.y + 0.5, origin.z + 0.5];
+    let d = [dir.x, dir.y, dir.z];
+
+    let mut voxel = [start[0].floor() as i32, start[1].floor() as i32, start[2].floor() as i32];
+    let mut step = [0; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+
+    for i in 0..3 {
+        if d[i] > 0.0 {
+            step[i] = 1;
+            t_max[i] = (voxel[i] as f32 + 1.0 - start[i]) / d[i];
+            t_delta[i] = 1.0 / d[i];
+        } else if d[i] < 0.0 {
+            step[i] = -1;
+            t_max[i] = (voxel[i] as f32 - start[i]) / d[i];
+            t_delta[i] = -1.0 / d[i];
+        }
+    }
+
+    let mut face_normal = [0; 3];
+    let mut distance = 0.0;
+
+    while distance <= max_dist {
+
+        if is_solid(voxel) {
+            return Some(Hit { voxel, face_normal, chunk: chunk_position(voxel), distance });
+        }
+
+        //advance along the axis whose boundary is closest
+        let axis = if t_max[0] < t_max[1] {
+            if t_max[0] < t_max[2] { 0 } else { 2 }
+        } else if t_max[1] < t_max[2] { 1 } else { 2 };
+
+        distance = t_max[axis];
+        voxel[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        face_normal = [0; 3];
+        face_normal[axis] = -step[axis];
+    }
+
+    None
+}
+
+impl ChunkManager {
+
+    pub fn raycast(&self, origin: Point3<f32>, dir: Vector3<f32>, max_dist: f32) -> Option<Hit> {
+        traverse(origin, dir, max_dist, |voxel| self.is_solid(voxel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //every voxel the ray passes within max_dist, in order
+    fn visit(origin: [f32; 3], dir: [f32; 3], max_dist: f32) -> Vec<[i32; 3]> {
+        let mut visited = Vec::new();
+        traverse(Point3::from(origin), Vector3::from(dir), max_dist, |voxel| {
+            visited.push(voxel);
+            false
+        });
+        visited
+    }
+
+    fn hit(origin: [f32; 3], dir: [f32; 3], max_dist: f32, solid: [i32; 3]) -> Option<Hit> {
+        traverse(Point3::from(origin), Vector3::from(dir), max_dist, |voxel| voxel == solid)
+    }
+
+    //no voxel is skipped or visited twice
+    fn face_connected(visited: &[[i32; 3]]) -> bool {
+        visited.windows(2).all(|w| (0..3).map(|i| (w[0][i] - w[1][i]).abs()).sum::<i32>() == 1)
+    }
+
+    #[test]
+    fn axis_aligned() {
+        let h = hit([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], 100.0, [5, 0, 0]).unwrap();
+        assert_eq!(h.voxel, [5, 0, 0]);
+        assert_eq!(h.face_normal, [-1, 0, 0]);
+        assert!((h.distance - 4.5).abs() < 1e-5);
+
+        let h = hit([0.0, 0.0, 0.0], [0.0, 3.0, 0.0], 100.0, [0, 2, 0]).unwrap();
+        assert_eq!(h.face_normal, [0, -1, 0]);
+        assert!((h.distance - 1.5).abs() < 1e-5);
+
+        assert_eq!(visit([0.0, 0.0, 0.0], [0.0, 0.0, 1.0], 3.0), vec![[0, 0, 0], [0, 0, 1], [0, 0, 2], [0, 0, 3]]);
+    }
+
+    #[test]
+    fn negative_directions() {
+        let h = hit([0.0, 0.0, 0.0], [-1.0, 0.0, 0.0], 100.0, [-3, 0, 0]).unwrap();
+        assert_eq!(h.face_normal, [1, 0, 0]);
+        assert!((h.distance - 2.5).abs() < 1e-5);
+
+        let visited = visit([0.0, 0.0, 0.0], [-1.0, -1.0, -2.0], 10.0);
+        assert!(face_connected(&visited));
+        assert!(visited.windows(2).all(|w| (0..3).all(|i| w[1][i] <= w[0][i])));
+        assert!(visited.contains(&[-2, -2, -4]));
+    }
+
+    #[test]
+    fn diagonal_through_an_edge_and_a_corner() {
+        //(1, 1, 0) from a voxel center crosses the shared edge at x = y = 0.5
+        let h = hit([0.0, 0.0, 0.0], [1.0, 1.0, 0.0], 100.0, [1, 1, 0]).unwrap();
+        assert!((h.distance - 0.5f32.hypot(0.5)).abs() < 1e-5);
+        let visited = visit([0.0, 0.0, 0.0], [1.0, 1.0, 0.0], 4.0);
+        assert!(face_connected(&visited));
+        assert!(visited.contains(&[2, 2, 0]));
+
+        //(1, 1, 1) crosses the shared corners
+        let visited = visit([0.0, 0.0, 0.0], [1.0, 1.0, 1.0], 4.0);
+        assert!(face_connected(&visited));
+        assert!(visited.contains(&[2, 2, 2]));
+    }
+
+    #[test]
+    fn visits_only_voxels_on_the_ray() {
+        let origin = Point3::new(0.1, -0.2, 0.3);
+        let dir = Vector3::new(1.0, 2.0, -3.0).normalize();
+
+        let mut visited = Vec::new();
+        let mut entry = Vec::new();
+        traverse(origin, dir, 20.0, |voxel| {
+            visited.push(voxel);
+            false
+        });
+        //rebuild the entry distance from the face crossed, then check the entry point lies in the voxel
+        visited.windows(2).for_each(|w| {
+            let axis = (0..3).find(|&i| w[0][i] != w[1][i]).unwrap();
+            let boundary = (w[0][axis] + w[1][axis]) as f32 / 2.0;
+            entry.push((w[1], (boundary - origin[axis]) / dir[axis]));
+        });
+
+        assert!(face_connected(&visited));
+        entry.iter().for_each(|(voxel, t)| {
+            let p = origin + dir * *t;
+            (0..3).for_each(|i| assert!((p[i] - voxel[i] as f32).abs() <= 0.5 + 1e-4));
+        });
+    }
+
+    #[test]
+    fn stops_at_max_distance() {
+        assert!(hit([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], 5.0, [10, 0, 0]).is_none());
+        assert!(hit([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], 9.5, [10, 0, 0]).is_some());
+        assert_eq!(visit([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], 2.0).len(), 3);
+        assert!(hit([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 100.0, [0, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn starts_inside_a_voxel() {
+        let h = hit([2.2, -0.3, 0.4], [0.0, 1.0, 0.0], 100.0, [2, 0, 0]).unwrap();
+        assert_eq!(h.voxel, [2, 0, 0]);
+        assert_eq!(h.face_normal, [0, 0, 0]);
+        assert_eq!(h.distance, 0.0);
+    }
+
+    #[test]
+    fn crosses_chunk_boundaries() {
+        let visited = visit([120.0, 0.0, 0.0], [1.0, 0.0, 0.0], 20.0);
+        assert!(visited.contains(&[127, 0, 0]) && visited.contains(&[128, 0, 0]));
+
+        assert_eq!(hit([120.0, 0.0, 0.0], [1.0, 0.0, 0.0], 20.0, [127, 0, 0]).unwrap().chunk, [0, 0, 0]);
+        assert_eq!(hit([120.0, 0.0, 0.0], [1.0, 0.0, 0.0], 20.0, [128, 0, 0]).unwrap().chunk, [1, 0, 0]);
+        assert_eq!(hit([-120.0, 0.0, 0.0], [-1.0, 0.0, 0.0], 20.0, [-128, 0, 0]).unwrap().chunk, [0, 0, 0]);
+        assert_eq!(hit([-120.0, 0.0, 0.0], [-1.0, 0.0, 0.0], 20.0, [-129, 0, 0]).unwrap().chunk, [-1, 0, 0]);
+    }
+}
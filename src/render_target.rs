@@ -0,0 +1,90 @@
+use iced_wgpu::wgpu;
+
+//webgl only samples rgba
+#[cfg(not(target_arch = "wasm32"))]
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+#[cfg(target_arch = "wasm32")]
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+//the offscreen buffers the terrain passes draw into and the blend pass reads, sized to the window
+pub struct RenderTargets {
+    pub diffuse_texture_view: wgpu::TextureView,
+    pub normal_texture_view: wgpu::TextureView,
+    pub depth_texture_view: wgpu::TextureView,
+    pub depth_test_texture_view: wgpu::TextureView,
+
+    pub diffuse_bind_group: wgpu::BindGroup,
+    pub normal_bind_group: wgpu::BindGroup,
+    pub depth_bind_group: wgpu::BindGroup,
+}
+
+fn create_view(device: &wgpu::Device, size: wgpu::Extent3d, format: wgpu::TextureFormat, usage: wgpu::TextureUsages, label: &str) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            label: Some(label),
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, view: &wgpu::TextureView, label: &str) -> wgpu::BindGroup {
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        address_mode_w: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+        label: Some(label),
+    })
+}
+
+impl RenderTargets {
+
+    //layout samples color, depth_layout the unfilterable r32 depth
+    pub fn new(device: &wgpu::Device, size: wgpu::Extent3d, layout: &wgpu::BindGroupLayout, depth_layout: &wgpu::BindGroupLayout) -> Self {
+
+        let usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT;
+
+        let diffuse_texture_view = create_view(device, size, COLOR_FORMAT, usage, "diffuse_texture");
+        let normal_texture_view = create_view(device, size, COLOR_FORMAT, usage, "normal_texture");
+        let depth_texture_view = create_view(device, size, wgpu::TextureFormat::R32Float, usage, "depth_texture");
+        let depth_test_texture_view = create_view(
+            device,
+            size,
+            wgpu::TextureFormat::Depth32Float,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            "depth_test_texture",
+        );
+
+        Self {
+            diffuse_bind_group: create_bind_group(device, layout, &diffuse_texture_view, "diffuse_bind_group"),
+            normal_bind_group: create_bind_group(device, layout, &normal_texture_view, "normal_bind_group"),
+            depth_bind_group: create_bind_group(device, depth_layout, &depth_texture_view, "depth_bind_group"),
+            diffuse_texture_view,
+            normal_texture_view,
+            depth_texture_view,
+            depth_test_texture_view,
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use iced_wgpu::{wgpu, Theme};
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+pub const DEFAULT_CAMERA_SPEED: f32 = 300.0;
+
+//in the order the shell panel and /settings list show them
+pub const KEY_LIST: [&str; 10] = [
+    "camera_speed",
+    "camera_sensitivity",
+    "sample_ratio_min",
+    "sample_ratio_max",
+    "brush_radius_min",
+    "brush_radius_max",
+    "model_scale_min",
+    "model_scale_max",
+    "present_mode",
+    "theme",
+];
+
+//past these the offscreen image or a chunk snap stops making sense
+const MAX_SAMPLE_RATIO: f32 = 8.0;
+const MAX_BRUSH_RADIUS: i32 = 256;
+const MAX_MODEL_SCALE: i32 = 64;
+
+//engine tunables, edited with /set and saved to SETTINGS_FILE
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub camera_speed: f32,
+    pub camera_sensitivity: f32,
+    //zoom range for ctrl scroll, transitions and paths
+    pub sample_ratio_min: f32,
+    pub sample_ratio_max: f32,
+    pub brush_radius_min: i32,
+    pub brush_radius_max: i32,
+    //scroll doubles and halves the scale, so both ends are powers of two
+    pub model_scale_min: i32,
+    pub model_scale_max: i32,
+    pub present_mode: wgpu::PresentMode,
+    pub theme: Theme,
+}
+
+fn present_mode_name(mode: wgpu::PresentMode) -> &'static str {
+    match mode {
+        wgpu::PresentMode::Mailbox => "mailbox",
+        wgpu::PresentMode::Immediate => "immediate",
+        _ => "fifo",
+    }
+}
+
+fn theme_name(theme: Theme) -> &'static str {
+    match theme {
+        Theme::Light => "light",
+        Theme::Dark => "dark",
+        Theme::Matrix => "matrix",
+    }
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse::<T>().map_err(|_| format!("{} can not be {}", key, value))
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            camera_speed: DEFAULT_CAMERA_SPEED,
+            camera_sensitivity: 0.002,
+            sample_ratio_min: 1.0,
+            sample_ratio_max: 4.0,
+            brush_radius_min: 1,
+            brush_radius_max: 64,
+            model_scale_min: 1,
+            model_scale_max: 8,
+            present_mode: wgpu::PresentMode::Fifo,
+            theme: Theme::Matrix,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        Some(match key {
+            "camera_speed" => self.camera_speed.to_string(),
+            "camera_sensitivity" => self.camera_sensitivity.to_string(),
+            "sample_ratio_min" => self.sample_ratio_min.to_string(),
+            "sample_ratio_max" => self.sample_ratio_max.to_string(),
+            "brush_radius_min" => self.brush_radius_min.to_string(),
+            "brush_radius_max" => self.brush_radius_max.to_string(),
+            "model_scale_min" => self.model_scale_min.to_string(),
+            "model_scale_max" => self.model_scale_max.to_string(),
+            "present_mode" => present_mode_name(self.present_mode).to_string(),
+            "theme" => theme_name(self.theme).to_string(),
+            _ => return None,
+        })
+    }
+
+    pub fn list(&self) -> Vec<(&'static str, String)> {
+        KEY_LIST.iter().map(|k| (*k, self.get(k).unwrap_or_default())).collect()
+    }
+
+    //parses one value without checking it against the others
+    fn assign(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "camera_speed" => self.camera_speed = parse(key, value)?,
+            "camera_sensitivity" => self.camera_sensitivity = parse(key, value)?,
+            "sample_ratio_min" => self.sample_ratio_min = parse(key, value)?,
+            "sample_ratio_max" => self.sample_ratio_max = parse(key, value)?,
+            "brush_radius_min" => self.brush_radius_min = parse(key, value)?,
+            "brush_radius_max" => self.brush_radius_max = parse(key, value)?,
+            "model_scale_min" => self.model_scale_min = parse(key, value)?,
+            "model_scale_max" => self.model_scale_max = parse(key, value)?,
+            "present_mode" => {
+                self.present_mode = match value {
+                    "fifo" => wgpu::PresentMode::Fifo,
+                    "mailbox" => wgpu::PresentMode::Mailbox,
+                    "immediate" => wgpu::PresentMode::Immediate,
+                    _ => return Err("present_mode must be fifo, mailbox or immediate".to_string()),
+                };
+            }
+            "theme" => {
+                self.theme = match value {
+                    "light" => Theme::Light,
+                    "dark" => Theme::Dark,
+                    "matrix" => Theme::Matrix,
+                    _ => return Err("theme must be light, dark or matrix".to_string()),
+                };
+            }
+            _ => return Err("Unknown setting ".to_string() + key + ", try " + &KEY_LIST.join(", ")),
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if [self.camera_speed, self.camera_sensitivity].iter().any(|v| v.is_nan() || *v <= 0.0) {
+            return Err("Camera speed and sensitivity must be above 0".to_string());
+        }
+        if !(1.0 <= self.sample_ratio_min && self.sample_ratio_min <= self.sample_ratio_max && self.sample_ratio_max <= MAX_SAMPLE_RATIO) {
+            return Err(format!("Sample ratio needs 1 <= min <= max <= {}", MAX_SAMPLE_RATIO));
+        }
+        if !(1 <= self.brush_radius_min && self.brush_radius_min <= self.brush_radius_max && self.brush_radius_max <= MAX_BRUSH_RADIUS) {
+            return Err(format!("Brush radius needs 1 <= min <= max <= {}", MAX_BRUSH_RADIUS));
+        }
+        let power = |n: i32| n > 0 && n & (n - 1) == 0;
+        if !(power(self.model_scale_min) && power(self.model_scale_max) && self.model_scale_min <= self.model_scale_max && self.model_scale_max <= MAX_MODEL_SCALE) {
+            return Err(format!("Model scale needs powers of two with min <= max <= {}", MAX_MODEL_SCALE));
+        }
+        Ok(())
+    }
+
+    //nothing changes unless the result is valid
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let mut settings = self.clone();
+        settings.assign(key, value)?;
+        settings.validate()?;
+        *self = settings;
+        Ok(())
+    }
+
+    //keys left out keep their defaults
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let table: toml::value::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut settings = Self::new();
+        for (key, value) in table.iter() {
+            let value = match value {
+                toml::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            settings.assign(key, &value)?;
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut table = toml::value::Table::new();
+        let mut float = |key: &str, v: f32| {
+            //through the string so 0.002 is not written as 0.0020000000949949026
+            table.insert(key.to_string(), toml::Value::Float(v.to_string().parse().unwrap_or(v as f64)));
+        };
+        float("camera_speed", self.camera_speed);
+        float("camera_sensitivity", self.camera_sensitivity);
+        float("sample_ratio_min", self.sample_ratio_min);
+        float("sample_ratio_max", self.sample_ratio_max);
+        [
+            ("brush_radius_min", self.brush_radius_min),
+            ("brush_radius_max", self.brush_radius_max),
+            ("model_scale_min", self.model_scale_min),
+            ("model_scale_max", self.model_scale_max),
+        ]
+        .iter()
+        .for_each(|(key, v)| {
+            table.insert(key.to_string(), toml::Value::Integer(*v as i64));
+        });
+        table.insert("present_mode".to_string(), toml::Value::String(present_mode_name(self.present_mode).to_string()));
+        table.insert("theme".to_string(), toml::Value::String(theme_name(self.theme).to_string()));
+        toml::to_string(&table).unwrap_or_default()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::from_toml(&std::fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_toml()).map_err(|e| e.to_string())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let mut settings = Settings::new();
+        settings.set("camera_speed", "450").unwrap();
+        settings.set("model_scale_max", "16").unwrap();
+        settings.set("theme", "dark").unwrap();
+        settings.set("present_mode", "immediate").unwrap();
+
+        let loaded = Settings::from_toml(&settings.to_toml()).unwrap();
+        assert_eq!(loaded, settings);
+        assert!(settings.to_toml().contains("camera_sensitivity = 0.002\n"));
+    }
+
+    #[test]
+    fn missing_keys_keep_defaults() {
+        let settings = Settings::from_toml("brush_radius_max = 32\n").unwrap();
+        assert_eq!(settings.brush_radius_max, 32);
+        assert_eq!(settings.sample_ratio_max, Settings::new().sample_ratio_max);
+    }
+
+    #[test]
+    fn invalid_values_change_nothing() {
+        let mut settings = Settings::new();
+        assert!(settings.set("sample_ratio_min", "5").is_err());
+        assert!(settings.set("model_scale_max", "12").is_err());
+        assert!(settings.set("brush_radius_min", "0").is_err());
+        assert!(settings.set("camera_speed", "fast").is_err());
+        assert!(settings.set("gravity", "1").is_err());
+        assert_eq!(settings, Settings::new());
+
+        //checked together, so a file may raise min above the default max
+        assert!(Settings::from_toml("sample_ratio_min = 5\nsample_ratio_max = 6\n").is_ok());
+    }
+}
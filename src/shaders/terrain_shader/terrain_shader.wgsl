@@ -8,13 +8,32 @@ var<uniform> camera: CameraUniform;
 
 struct Light {
     position: vec3<f32>,
+    //zero for directional lights
+    radius: f32,
     color: vec4<f32>,
 }
 struct Lights {
-    data: array<Light,512>,
+    ambient: vec3<f32>,
+    count: u32,
+    data: array<Light>,
 }
 @group(1) @binding(0)
-var<uniform> lights: Lights;
+var<storage, read> lights: Lights;
+
+struct Material {
+    color: vec4<f32>,
+    //rgb color, w strength
+    emissive: vec4<f32>,
+    opacity: f32,
+    roughness: f32,
+    casts_light: u32,
+    tags: u32,
+}
+struct Materials {
+    data: array<Material,64>,
+}
+@group(2) @binding(0)
+var<uniform> materials: Materials;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
@@ -34,6 +53,7 @@ struct InstanceInput {
     @location(10) normal: vec3<f32>,
     @location(11) depth_strength:f32,
     @location(12) normal_strength:f32,
+    @location(13) material:u32,
 };
 
 @vertex
@@ -70,31 +90,42 @@ fn vs_main(
 
     out.clip_position =  camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
    
-    var i = 0;
+    var result = lights.ambient;
+
+    let material = materials.data[instance.material];
+    //rough surfaces spread the highlight and lose most of it
+    let shininess = mix(128.0, 2.0, material.roughness);
+
+    for (var i = 0u; i < lights.count; i++) {
 
-    var result:vec3<f32>;
+        if lights.data[i].color.w <= 0.0{
+            //freed slot
+            continue;
+        }
+
+        let distance = length(lights.data[i].position - (model_matrix * vec4<f32>(model.position, 1.0)).xyz);
 
-    while i < 512 && lights.data[i].color.w > 0.0{
+        if lights.data[i].radius > 0.0 && distance > lights.data[i].radius{
+            continue;
+        }
 
-        
         let light_dir = normalize(lights.data[i].position - (model_matrix *vec4<f32>(model.position, 1.0)).xyz);
         let view_dir = normalize(camera.eye - (model_matrix *vec4<f32>(model.position, 1.0)).xyz);
         let reflect_dir = reflect(-light_dir, instance.normal);
 
         let ambient_strength = 0.1;
         var diffuse_strength = max(dot(instance.normal, light_dir),0.0);
-        let specular_strength = 0.5;
-
-        let spec = pow(max(dot(view_dir, reflect_dir), 0.0), 32.0);
+        let specular_strength = 0.5 * (1.0 - material.roughness);
 
-        let distance = length(lights.data[i].position - (model_matrix * vec4<f32>(model.position, 1.0)).xyz);
+        let spec = pow(max(dot(view_dir, reflect_dir), 0.0), shininess);
 
         var attenuation = 1.0 / (1.0 + 0.0014 * distance + 0.000007 * (distance * distance));
 
-        if lights.data[i].color.w < 1.0{
-            //point light
+        if lights.data[i].radius > 0.0{
+            //point light, faded out towards its radius
 
-            attenuation *= lights.data[i].color.w;
+            let falloff = 1.0 - distance / lights.data[i].radius;
+            attenuation *= lights.data[i].color.w * falloff * falloff;
 
             let ambient = ambient_strength * lights.data[i].color.xyz* attenuation;
             let diffuse = diffuse_strength * lights.data[i].color.xyz * attenuation;
@@ -108,17 +139,16 @@ fn vs_main(
             let diffuse = diffuse_strength * lights.data[i].color.xyz;
             let specular = specular_strength * spec * lights.data[i].color.xyz;
 
-            result = result + ambient + diffuse;
+            result = result + (ambient + diffuse) * lights.data[i].color.w;
             
         }
-        i++;
     }
 
-    let object_color = vec3<f32>(instance.color[0],instance.color[1],instance.color[2]);
-    let color = result * object_color;
+    let object_color = vec3<f32>(instance.color[0],instance.color[1],instance.color[2]) * material.color.xyz;
+    let color = result * object_color + material.emissive.xyz * material.emissive.w;
 
     //out.clip_position.w = (distance_to_plane - near) / (far - near);
-    out.color = vec4<f32>(color[0],color[1],color[2],instance.color[3]);
+    out.color = vec4<f32>(color[0],color[1],color[2],instance.color[3] * material.opacity);
     
     return out;
 }
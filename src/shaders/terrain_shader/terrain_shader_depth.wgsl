@@ -1,7 +1,9 @@
 struct CameraUniform {
     view_proj: mat4x4<f32>,
     position:vec3<f32>,
-    eye:vec3<f32>
+    depth_near:f32,
+    eye:vec3<f32>,
+    depth_far:f32,
 };
 @group(0)@binding(0)
 var<uniform> camera: CameraUniform;
@@ -63,8 +65,9 @@ fn vs_main(
 
     let distance_to_plane = abs(plane_a * voxel_position.x + plane_b * voxel_position.y + plane_c * voxel_position.z + plane_d)/ sqrt(plane_a * plane_a + plane_b * plane_b + plane_c * plane_c);
 
-    let near = 1300.0;
-    let far = 4500.0;
+    //follows the eye distance, the same in both projections
+    let near = camera.depth_near;
+    let far = camera.depth_far;
 
     out.depth_value =(distance_to_plane - near) / (far - near);
 
@@ -38,17 +38,25 @@ impl TextColumn {
 pub struct Controls {
     pub text: String,
     pub command_buffer: String,
+    //submitted commands in order, the game tick applies them and removes them with CommandParsed
+    pub command_queue: Vec<String>,
 
     pub indicator_text: String,
     pub indicator_overdose: bool,
 
     pub test: [f32;3],
     pub fps: i32,
+    pub tps: i32,
+    pub job_text: String,
+    pub cull_text: String,
 
     pub text_column: Vec<TextColumn>,
-    pub parse_flag: bool,
 
     pub color: Color,
+
+    //key and value pairs from State::apply_settings, edited in place until submitted
+    pub settings_open: bool,
+    pub settings_list: Vec<(String,String)>,
 }
 
 #[derive(Debug, Clone)]
@@ -58,13 +66,20 @@ pub enum Message {
     UsrIndicator(String,bool),
     ServerLog(String),
     FrameUpdate(i32),
+    TickUpdate(i32),
+    JobProgress(String),
+    CullUpdate(u32,u32),
     OnSubmit,
     Parse,
     Update,
-    CommandParsed,
-    ChatMessage,
+    CommandParsed(usize),
+    ChatMessage(String),
     Coordinate([f32;3]),
     BackgroundColorChanged(Color),
+    SettingsList(Vec<(String,String)>),
+    SettingsToggle,
+    SettingEdited(usize,String),
+    SettingSubmitted(usize),
 }
 
 impl Controls {
@@ -73,16 +88,22 @@ impl Controls {
             text: Default::default(),
 
             command_buffer: Default::default(),
+            command_queue: Default::default(),
 
             indicator_text: Default::default(),
             indicator_overdose: Default::default(),
 
             test: Default::default(),
             fps: 0,
+            tps: 0,
+            job_text: Default::default(),
+            cull_text: Default::default(),
             text_column: Default::default(),
-            parse_flag: false,
 
             color: Color::WHITE,
+
+            settings_open: false,
+            settings_list: Default::default(),
         }
     }
 }
@@ -129,7 +150,7 @@ impl Program for Controls {
             }
 
             Message::Parse => {
-                self.parse_flag = true;
+                self.command_queue.push(std::mem::take(&mut self.command_buffer));
             }
             
             
@@ -140,6 +161,18 @@ impl Program for Controls {
                 //}
             }
 
+            Message::TickUpdate(tps) => {
+                self.tps = tps;
+            }
+
+            Message::JobProgress(text) => {
+                self.job_text = text;
+            }
+
+            Message::CullUpdate(drawn, culled) => {
+                self.cull_text = format!("Chunks: {} drawn, {} culled", drawn, culled);
+            }
+
             Message::Update =>{
 
                 self.text_column.iter_mut().for_each(|t|{
@@ -161,13 +194,12 @@ impl Program for Controls {
             Message::ServerLog(text) => {
                 self.text_column.push(TextColumn::new(text));
             }
-            Message::CommandParsed => {
-                self.command_buffer = String::from("");
-                self.parse_flag = false;
+            Message::CommandParsed(count) => {
+                self.command_queue.drain(..count.min(self.command_queue.len()));
             }
-            Message::ChatMessage =>{
+            Message::ChatMessage(message) =>{
 
-                let s: Vec<&str> = self.command_buffer.split(":").collect();
+                let s: Vec<&str> = message.split(":").collect();
 
                 let mut text = TextColumn::new((&s[0]).to_string());
 
@@ -200,6 +232,24 @@ impl Program for Controls {
                 self.color = color;
             }
 
+            Message::SettingsList(list) =>{
+                self.settings_list = list;
+            }
+            Message::SettingsToggle =>{
+                self.settings_open = !self.settings_open;
+            }
+            Message::SettingEdited(i,value) =>{
+                if let Some(setting) = self.settings_list.get_mut(i){
+                    setting.1 = value;
+                }
+            }
+            //goes through /set like a typed command, a rejected value is put back by the next SettingsList
+            Message::SettingSubmitted(i) =>{
+                if let Some((key,value)) = self.settings_list.get(i){
+                    self.command_queue.push("/set ".to_string() + key + " " + value);
+                }
+            }
+
         }
 
         Command::none()
@@ -263,7 +313,30 @@ impl Program for Controls {
             .step(0.01),
         );
 
-        
+        let settings_panel = Column::with_children(
+            if self.settings_open{
+                self.settings_list
+                .iter()
+                .enumerate()
+                .map(|(i,(key,value))|
+                    Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(Text::new(key.to_owned()).style(Color::from_rgb(1.0,1.0,1.0)).size(20).width(Length::Units(200)))
+                    .push(
+                        TextInput::new(key, value, move |v| Message::SettingEdited(i,v))
+                        .on_submit(Message::SettingSubmitted(i))
+                        .width(Length::Units(200))
+                    )
+                )
+                .map(Element::from)
+                .collect()
+            }
+            else{
+                Vec::new()
+            }
+        ).spacing(5);
+
         Column::new()
             .width(Length::Fill)
             .height(Length::Fill)
@@ -291,12 +364,21 @@ impl Program for Controls {
                     ).size(20),
             )
             .push(sliders)
+            .push(settings_panel)
             .push(
                 Text::new("FPS: ".to_owned() + &self.fps.to_string())
                     .style(Color::from_rgb(1.0,1.0,1.0)).size(20),
             )
             .push(
-                Text::new("TPS: ".to_owned() + &'0'.to_string())
+                Text::new("TPS: ".to_owned() + &self.tps.to_string())
+                    .style(Color::from_rgb(1.0,1.0,1.0)).size(20),
+            )
+            .push(
+                Text::new(&self.job_text)
+                    .style(Color::from_rgb(1.0,1.0,0.0)).size(20),
+            )
+            .push(
+                Text::new(&self.cull_text)
                     .style(Color::from_rgb(1.0,1.0,1.0)).size(20),
             )
 
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use crate::Instance;
+use crate::chunk::Chunk;
+use crate::world;
+
+//chunks around the camera target that always stay loaded, counted in chunks along each axis
+pub const DEFAULT_VIEW_RADIUS: i32 = 2;
+
+//resident voxels before chunks outside the view radius are written out, webgl gets less room
+#[cfg(not(target_arch = "wasm32"))]
+pub const DEFAULT_BUDGET: usize = 8_000_000;
+#[cfg(target_arch = "wasm32")]
+pub const DEFAULT_BUDGET: usize = 1_000_000;
+
+//spread the work so crossing into a new area does not stall a single frame
+const MAX_EVICT_PER_FRAME: usize = 4;
+const MAX_RELOAD_PER_FRAME: usize = 2;
+
+//where evicted chunks go, packed in the world chunk format
+enum Store {
+    #[cfg(not(target_arch = "wasm32"))]
+    Disk(PathBuf),
+    //no filesystem in the browser, packed chunks are still far smaller than resident ones
+    Memory(HashMap<[i32; 3], Vec<u8>>),
+}
+
+pub struct ChunkStreamer {
+    pub enabled: bool,
+    pub view_radius: i32,
+    pub budget: usize,
+    store: Store,
+    evicted: HashSet<[i32; 3]>,
+    last_used: HashMap<[i32; 3], u64>,
+    frame: u64,
+}
+
+//what ChunkManager::stream should do this frame
+pub struct StreamPlan {
+    pub evict_list: Vec<[i32; 3]>,
+    pub reload_list: Vec<[i32; 3]>,
+}
+
+fn distance(a: [i32; 3], b: [i32; 3]) -> i32 {
+    (0..3).map(|i| (a[i] - b[i]).abs()).max().unwrap_or(0)
+}
+
+impl ChunkStreamer {
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new() -> Self {
+        //one directory per process so two running clients do not share evicted chunks
+        let dir = std::env::temp_dir().join(format!("voxel_stream_{}", std::process::id()));
+        Self::with_store(Store::Disk(dir))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn new() -> Self {
+        Self::in_memory()
+    }
+
+    pub fn in_memory() -> Self {
+        Self::with_store(Store::Memory(Default::default()))
+    }
+
+    fn with_store(store: Store) -> Self {
+        Self {
+            enabled: true,
+            view_radius: DEFAULT_VIEW_RADIUS,
+            budget: DEFAULT_BUDGET,
+            store,
+            evicted: Default::default(),
+            last_used: Default::default(),
+            frame: 0,
+        }
+    }
+
+    pub fn is_evicted(&self, position: [i32; 3]) -> bool {
+        self.evicted.contains(&position)
+    }
+
+    pub fn evicted_list(&self) -> Vec<[i32; 3]> {
+        let mut evicted_list: Vec<[i32; 3]> = self.evicted.iter().copied().collect();
+        evicted_list.sort();
+        evicted_list
+    }
+
+    pub fn bytes(&self, position: [i32; 3]) -> Result<Vec<u8>, String> {
+        match &self.store {
+            #[cfg(not(target_arch = "wasm32"))]
+            Store::Disk(dir) => fs::read(dir.join(world::chunk_file(position))).map_err(|e| e.to_string()),
+            Store::Memory(hash) => hash.get(&position).cloned().ok_or_else(|| "Chunk not evicted".to_string()),
+        }
+    }
+
+    pub fn put(&mut self, chunk: &Chunk) -> Result<(), String> {
+        match &mut self.store {
+            #[cfg(not(target_arch = "wasm32"))]
+            Store::Disk(dir) => {
+                fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+                world::write_chunk(dir, chunk)?;
+            }
+            Store::Memory(hash) => {
+                hash.insert(chunk.position, world::chunk_bytes(&chunk.voxel_data));
+            }
+        }
+        self.evicted.insert(chunk.position);
+        Ok(())
+    }
+
+    //the voxels of an evicted chunk, which stops counting as evicted
+    //a chunk that fails to read stays evicted and keeps its data
+    pub fn take(&mut self, position: [i32; 3]) -> Result<Vec<Instance>, String> {
+        if !self.evicted.contains(&position) {
+            return Ok(Default::default());
+        }
+        let instance_list = world::parse_chunk(&self.bytes(position)?, None)?;
+        self.evicted.remove(&position);
+        match &mut self.store {
+            #[cfg(not(target_arch = "wasm32"))]
+            Store::Disk(dir) => {
+                let _ = fs::remove_file(dir.join(world::chunk_file(position)));
+            }
+            Store::Memory(hash) => {
+                hash.remove(&position);
+            }
+        }
+        Ok(instance_list)
+    }
+
+    //forgets every evicted chunk, used when a world is replaced
+    pub fn clear(&mut self) {
+        match &mut self.store {
+            #[cfg(not(target_arch = "wasm32"))]
+            Store::Disk(dir) => {
+                let _ = fs::remove_dir_all(dir);
+            }
+            Store::Memory(hash) => hash.clear(),
+        }
+        self.evicted.clear();
+        self.last_used.clear();
+    }
+
+    //resident_list holds chunk position and voxel count of every loaded chunk
+    pub fn plan(&mut self, center: [i32; 3], resident_list: &[([i32; 3], usize)]) -> StreamPlan {
+
+        self.frame += 1;
+        let frame = self.frame;
+
+        resident_list.iter().filter(|(p, _)| distance(*p, center) <= self.view_radius).for_each(|(p, _)| {
+            self.last_used.insert(*p, frame);
+        });
+
+        let mut reload_list: Vec<[i32; 3]> = self.evicted.iter().filter(|p| distance(**p, center) <= self.view_radius).copied().collect();
+        reload_list.sort_by_key(|p| (distance(*p, center), *p));
+        reload_list.truncate(MAX_RELOAD_PER_FRAME);
+
+        //least recently seen chunks outside the radius go first
+        let mut total: usize = resident_list.iter().map(|(_, n)| n).sum();
+        let mut candidate_list: Vec<&([i32; 3], usize)> =
+            resident_list.iter().filter(|(p, _)| distance(*p, center) > self.view_radius).collect();
+        candidate_list.sort_by_key(|(p, _)| (self.last_used.get(p).copied().unwrap_or(0), *p));
+
+        let mut evict_list: Vec<[i32; 3]> = Default::default();
+        for (p, n) in candidate_list {
+            if total <= self.budget || evict_list.len() >= MAX_EVICT_PER_FRAME {
+                break;
+            }
+            evict_list.push(*p);
+            total -= n;
+        }
+        evict_list.iter().for_each(|p| {
+            self.last_used.remove(p);
+        });
+
+        StreamPlan { evict_list, reload_list }
+    }
+}
+
+impl Default for ChunkStreamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//the temp directory is only ours while the process runs
+impl Drop for ChunkStreamer {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_view_radius_and_evicts_least_recent() {
+        let mut streamer = ChunkStreamer::in_memory();
+        streamer.view_radius = 1;
+        streamer.budget = 250;
+
+        let resident_list = vec![([0, 0, 0], 100), ([5, 0, 0], 100), ([9, 0, 0], 100)];
+
+        //[9, 0, 0] was seen more recently than [5, 0, 0]
+        streamer.plan([9, 0, 0], &resident_list);
+        let plan = streamer.plan([0, 0, 0], &resident_list);
+        assert_eq!(plan.evict_list, vec![[5, 0, 0]]);
+        assert!(plan.reload_list.is_empty());
+    }
+
+    #[test]
+    fn unreadable_chunks_stay_evicted() {
+        let mut streamer = ChunkStreamer::in_memory();
+        if let Store::Memory(hash) = &mut streamer.store {
+            hash.insert([1, 0, 0], b"AVOX".to_vec());
+            hash.insert([2, 0, 0], world::chunk_bytes(&[]));
+        }
+        streamer.evicted.extend([[1, 0, 0], [2, 0, 0]]);
+
+        assert!(streamer.take([1, 0, 0]).is_err());
+        assert!(streamer.is_evicted([1, 0, 0]));
+        assert!(streamer.bytes([1, 0, 0]).is_ok());
+
+        assert!(streamer.take([2, 0, 0]).unwrap().is_empty());
+        assert!(!streamer.is_evicted([2, 0, 0]));
+        assert!(streamer.bytes([2, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn drop_removes_the_temp_dir() {
+        let dir = std::env::temp_dir().join(format!("voxel_stream_test_{}", std::process::id()));
+        let streamer = ChunkStreamer::with_store(Store::Disk(dir.clone()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(world::chunk_file([0, 0, 0])), world::chunk_bytes(&[])).unwrap();
+
+        drop(streamer);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn under_budget_nothing_moves() {
+        let mut streamer = ChunkStreamer::in_memory();
+        let plan = streamer.plan([0, 0, 0], &[([40, 0, 0], 10)]);
+        assert!(plan.evict_list.is_empty());
+    }
+}
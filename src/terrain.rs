@@ -0,0 +1,550 @@
+use crate::Instance;
+use crate::material_list::{MaterialList, DEFAULT_MATERIAL};
+use crate::vegetation::{self, Plant, ScatterRule};
+
+//largest box side accepted, keeps a typo from generating for minutes
+const MAX_GEN_SIDE: i32 = 1024;
+const MAX_GEN_VOLUME: i64 = 64 * 1024 * 1024;
+
+//caves stay this far below the surface so the ground never caves in under water
+const CAVE_ROOF: i32 = 4;
+const CAVE_SCALE: f32 = 1.0 / 24.0;
+const CAVE_WIDTH: f32 = 0.09;
+const DIRT_DEPTH: i32 = 3;
+
+const WATER_COLOR: [f32; 4] = [0.2, 0.4, 0.8, 1.0];
+const SAND_COLOR: [f32; 4] = [0.85, 0.78, 0.55, 1.0];
+const GRASS_COLOR: [f32; 4] = [0.35, 0.65, 0.25, 1.0];
+const FOREST_COLOR: [f32; 4] = [0.2, 0.45, 0.18, 1.0];
+const DRY_COLOR: [f32; 4] = [0.65, 0.62, 0.3, 1.0];
+const DIRT_COLOR: [f32; 4] = [0.45, 0.32, 0.2, 1.0];
+const ROCK_COLOR: [f32; 4] = [0.5, 0.5, 0.52, 1.0];
+const SNOW_COLOR: [f32; 4] = [0.95, 0.95, 1.0, 1.0];
+
+#[derive(Copy, Clone, PartialEq)]
+enum Biome {
+    Forest,
+    Meadow,
+    Dry,
+    Bare,
+}
+
+//plants per biome, tried in order
+const FOREST_RULE_LIST: [ScatterRule; 3] = [
+    ScatterRule { plant: Plant::Tree, spacing: 9, density: 0.6, size: [13, 24] },
+    ScatterRule { plant: Plant::Bush, spacing: 5, density: 0.3, size: [5, 3] },
+    ScatterRule { plant: Plant::Grass, spacing: 2, density: 0.3, size: [1, 2] },
+];
+const MEADOW_RULE_LIST: [ScatterRule; 3] = [
+    ScatterRule { plant: Plant::Tree, spacing: 24, density: 0.25, size: [11, 20] },
+    ScatterRule { plant: Plant::Bush, spacing: 10, density: 0.2, size: [5, 3] },
+    ScatterRule { plant: Plant::Grass, spacing: 2, density: 0.5, size: [1, 3] },
+];
+const DRY_RULE_LIST: [ScatterRule; 2] = [
+    ScatterRule { plant: Plant::Bush, spacing: 12, density: 0.2, size: [4, 2] },
+    ScatterRule { plant: Plant::Grass, spacing: 4, density: 0.2, size: [1, 2] },
+];
+
+#[derive(Copy, Clone)]
+pub struct Preset {
+    pub name: &'static str,
+    //horizontal size of the largest features, in voxels
+    pub scale: f32,
+    pub octaves: u32,
+    pub ridged: bool,
+    //how far the sample point is pushed around, in voxels
+    pub warp: f32,
+    //share of the box height the surface may use
+    pub height: f32,
+    pub caves: bool,
+    //share of the box height filled with water, None for dry land
+    pub water: Option<f32>,
+    //scales the density of every scatter rule, 0 for bare ground
+    pub vegetation: f32,
+}
+
+pub const PRESET_LIST: [Preset; 5] = [
+    Preset { name: "plains", scale: 256.0, octaves: 4, ridged: false, warp: 0.0, height: 0.3, caves: false, water: None, vegetation: 1.0 },
+    Preset { name: "hills", scale: 128.0, octaves: 5, ridged: false, warp: 16.0, height: 0.6, caves: true, water: Some(0.2), vegetation: 1.0 },
+    Preset { name: "mountains", scale: 192.0, octaves: 6, ridged: true, warp: 24.0, height: 0.95, caves: true, water: None, vegetation: 0.5 },
+    Preset { name: "islands", scale: 96.0, octaves: 5, ridged: false, warp: 32.0, height: 0.7, caves: false, water: Some(0.35), vegetation: 1.5 },
+    Preset { name: "canyons", scale: 160.0, octaves: 4, ridged: true, warp: 48.0, height: 0.8, caves: true, water: Some(0.1), vegetation: 0.3 },
+];
+
+pub const DEFAULT_PRESET: usize = 1;
+
+pub fn find_preset(name: &str) -> Option<usize> {
+    PRESET_LIST.iter().position(|p| p.name == name)
+}
+
+//numbers are taken as they are, anything else is hashed so "/gen terrain hello" works too
+pub fn parse_seed(s: &str) -> u32 {
+    match s.parse::<u32>() {
+        Ok(seed) => seed,
+        Err(_) => s.bytes().fold(0x811C_9DC5u32, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193)),
+    }
+}
+
+fn lattice(seed: u32, x: i32, y: i32, z: i32) -> u32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8DA6_B343)
+        ^ (y as u32).wrapping_mul(0xD816_3841)
+        ^ (z as u32).wrapping_mul(0xCB1A_B31F);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    h
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn grad2(h: u32, dx: f32, dz: f32) -> f32 {
+    match h & 7 {
+        0 => dx + dz,
+        1 => dx - dz,
+        2 => -dx + dz,
+        3 => -dx - dz,
+        4 => dx,
+        5 => -dx,
+        6 => dz,
+        _ => -dz,
+    }
+}
+
+fn grad3(h: u32, dx: f32, dy: f32, dz: f32) -> f32 {
+    match h % 12 {
+        0 => dx + dy,
+        1 => -dx + dy,
+        2 => dx - dy,
+        3 => -dx - dy,
+        4 => dx + dz,
+        5 => -dx + dz,
+        6 => dx - dz,
+        7 => -dx - dz,
+        8 => dy + dz,
+        9 => -dy + dz,
+        10 => dy - dz,
+        _ => -dy - dz,
+    }
+}
+
+//gradient noise, only depends on the seed and the sample point
+pub struct Noise {
+    pub seed: u32,
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Self {
+        Self { seed }
+    }
+
+    //roughly [-1, 1]
+    pub fn noise2(&self, x: f32, z: f32) -> f32 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (dx, dz) = (x - x0, z - z0);
+        let (ix, iz) = (x0 as i32, z0 as i32);
+        let g = |cx: i32, cz: i32| grad2(lattice(self.seed, ix + cx, 0, iz + cz), dx - cx as f32, dz - cz as f32);
+        let (u, v) = (fade(dx), fade(dz));
+        lerp(lerp(g(0, 0), g(1, 0), u), lerp(g(0, 1), g(1, 1), u), v)
+    }
+
+    pub fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (dx, dy, dz) = (x - x0, y - y0, z - z0);
+        let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+        let g = |cx: i32, cy: i32, cz: i32| {
+            grad3(lattice(self.seed, ix + cx, iy + cy, iz + cz), dx - cx as f32, dy - cy as f32, dz - cz as f32)
+        };
+        let (u, v, w) = (fade(dx), fade(dy), fade(dz));
+        let bottom = lerp(lerp(g(0, 0, 0), g(1, 0, 0), u), lerp(g(0, 0, 1), g(1, 0, 1), u), w);
+        let top = lerp(lerp(g(0, 1, 0), g(1, 1, 0), u), lerp(g(0, 1, 1), g(1, 1, 1), u), w);
+        lerp(bottom, top, v)
+    }
+
+    //octaves summed at doubling frequency and halving weight, in [0, 1]
+    pub fn fbm2(&self, x: f32, z: f32, octaves: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut weight = 1.0;
+        let mut total = 0.0;
+        let mut frequency = 1.0;
+        for octave in 0..octaves {
+            let layer = Noise::new(self.seed.wrapping_add(octave.wrapping_mul(0x9E37_79B9)));
+            sum += layer.noise2(x * frequency, z * frequency) * weight;
+            total += weight;
+            weight *= 0.5;
+            frequency *= 2.0;
+        }
+        (sum / total * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+
+    //sharp crests where the noise crosses zero, in [0, 1]
+    pub fn ridged2(&self, x: f32, z: f32, octaves: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut weight = 1.0;
+        let mut total = 0.0;
+        let mut frequency = 1.0;
+        for octave in 0..octaves {
+            let layer = Noise::new(self.seed.wrapping_add(octave.wrapping_mul(0x9E37_79B9)));
+            let ridge = 1.0 - layer.noise2(x * frequency, z * frequency).abs();
+            sum += ridge * ridge * weight;
+            total += weight;
+            weight *= 0.5;
+            frequency *= 2.0;
+        }
+        (sum / total).clamp(0.0, 1.0)
+    }
+
+    //the sample point pushed by two other noise fields, breaks up the grid look of plain fbm
+    pub fn warp2(&self, x: f32, z: f32, strength: f32) -> (f32, f32) {
+        if strength == 0.0 {
+            return (x, z);
+        }
+        let wx = Noise::new(self.seed ^ 0x5BD1_E995).noise2(x, z);
+        let wz = Noise::new(self.seed ^ 0x1B87_3593).noise2(x + 5.2, z + 1.3);
+        (x + wx * strength, z + wz * strength)
+    }
+}
+
+struct Generator<'a> {
+    preset: &'a Preset,
+    first: [i32; 3],
+    last: [i32; 3],
+    surface: Noise,
+    moisture: Noise,
+    cave: [Noise; 2],
+}
+
+impl<'a> Generator<'a> {
+
+    fn height(&self, x: i32, z: i32) -> i32 {
+        let preset = self.preset;
+        let (wx, wz) = self.surface.warp2(x as f32 / preset.scale, z as f32 / preset.scale, preset.warp / preset.scale);
+        let base = if preset.ridged {
+            self.surface.ridged2(wx, wz, preset.octaves)
+        } else {
+            self.surface.fbm2(wx, wz, preset.octaves)
+        };
+        let span = (self.last[1] - self.first[1]) as f32;
+        self.first[1] + (base * preset.height * span).round() as i32
+    }
+
+    fn cave(&self, x: i32, y: i32, z: i32) -> bool {
+        let (x, y, z) = (x as f32 * CAVE_SCALE, y as f32 * CAVE_SCALE * 1.5, z as f32 * CAVE_SCALE);
+        //tunnels run where two noise fields are both close to zero
+        self.cave[0].noise3(x, y, z).abs() + self.cave[1].noise3(x, y, z).abs() < CAVE_WIDTH
+    }
+
+    fn biome(&self, x: i32, y: i32, z: i32, slope: i32, water: Option<i32>) -> Biome {
+
+        let span = (self.last[1] - self.first[1]).max(1) as f32;
+        let altitude = (y - self.first[1]) as f32 / span;
+
+        if water.is_some_and(|water| y <= water + 1) || altitude > 0.6 || slope > 2 {
+            return Biome::Bare;
+        }
+
+        let moisture = self.moisture.fbm2(x as f32 / 192.0, z as f32 / 192.0, 3);
+        if moisture > 0.58 {
+            Biome::Forest
+        } else if moisture < 0.4 {
+            Biome::Dry
+        } else {
+            Biome::Meadow
+        }
+    }
+
+    fn surface_color(&self, y: i32, biome: Biome, water: Option<i32>) -> ([f32; 4], &'static str) {
+
+        let span = (self.last[1] - self.first[1]).max(1) as f32;
+        let altitude = (y - self.first[1]) as f32 / span;
+
+        match biome {
+            Biome::Forest => (FOREST_COLOR, "grass"),
+            Biome::Meadow => (GRASS_COLOR, "grass"),
+            Biome::Dry => (DRY_COLOR, "grass"),
+            Biome::Bare if water.is_some_and(|water| y <= water + 1) => (SAND_COLOR, "sand"),
+            Biome::Bare if altitude > 0.8 => (SNOW_COLOR, "stone"),
+            Biome::Bare => (ROCK_COLOR, "stone"),
+        }
+    }
+}
+
+fn jitter(color: [f32; 4], position: [i32; 3], seed: u32) -> [f32; 4] {
+    let f = 0.95 + (lattice(seed, position[0], position[1], position[2]) % 1000) as f32 / 10000.0;
+    [color[0] * f, color[1] * f, color[2] * f, color[3]]
+}
+
+fn voxel(position: [i32; 3], color: [f32; 4], normal: [f32; 3], material: usize) -> Instance {
+    Instance::new(position, color, normal, material)
+}
+
+//one /gen request, split into column parts so it can be built a piece at a time
+pub struct Terrain {
+    pub seed: u32,
+    pub first: [i32; 3],
+    pub last: [i32; 3],
+    pub preset: Preset,
+    //absolute height of the water surface
+    pub water: Option<i32>,
+}
+
+impl Terrain {
+
+    //water None falls back to the preset
+    pub fn new(seed: u32, first: [i32; 3], last: [i32; 3], preset: Preset, water: Option<i32>) -> Result<Self, String> {
+
+        let first_box = [first[0].min(last[0]), first[1].min(last[1]), first[2].min(last[2])];
+        let last_box = [first[0].max(last[0]), first[1].max(last[1]), first[2].max(last[2])];
+        let (first, last) = (first_box, last_box);
+
+        let size = [last[0] - first[0] + 1, last[1] - first[1] + 1, last[2] - first[2] + 1];
+        if size.iter().any(|s| *s > MAX_GEN_SIDE) || size.iter().map(|s| *s as i64).product::<i64>() > MAX_GEN_VOLUME {
+            return Err(format!("Box larger than {} per side", MAX_GEN_SIDE));
+        }
+
+        let water = water.or_else(|| preset.water.map(|w| first[1] + ((last[1] - first[1]) as f32 * w).round() as i32));
+
+        Ok(Self { seed, first, last, preset, water })
+    }
+
+    fn generator(&self) -> Generator<'_> {
+        let seed = self.seed;
+        Generator {
+            preset: &self.preset,
+            first: self.first,
+            last: self.last,
+            surface: Noise::new(seed),
+            moisture: Noise::new(seed ^ 0xA076_1D64),
+            cave: [Noise::new(seed ^ 0xE703_7ED1), Noise::new(seed ^ 0x8EBC_6AF0)],
+        }
+    }
+
+    //column ranges of at most side x side, covering the box
+    pub fn part_list(&self, side: i32) -> Vec<([i32; 3], [i32; 3])> {
+        let side = side.max(1);
+        let mut part_list: Vec<([i32; 3], [i32; 3])> = Default::default();
+        for z in (self.first[2]..=self.last[2]).step_by(side as usize) {
+            for x in (self.first[0]..=self.last[0]).step_by(side as usize) {
+                part_list.push((
+                    [x, self.first[1], z],
+                    [(x + side - 1).min(self.last[0]), self.last[1], (z + side - 1).min(self.last[2])],
+                ));
+            }
+        }
+        part_list
+    }
+
+    pub fn generate(&self, material_list: &MaterialList) -> Vec<Instance> {
+        self.generate_part(self.first, self.last, material_list)
+    }
+
+    //the voxels of the columns between first and last, parts put together give the same world as one piece
+    pub fn generate_part(&self, first: [i32; 3], last: [i32; 3], material_list: &MaterialList) -> Vec<Instance> {
+
+        let (seed, preset, water) = (self.seed, &self.preset, self.water);
+        let (box_first, box_last) = (self.first, self.last);
+        let first = [first[0].max(box_first[0]), box_first[1], first[2].max(box_first[2])];
+        let last = [last[0].min(box_last[0]), box_last[1], last[2].min(box_last[2])];
+        if first[0] > last[0] || first[2] > last[2] {
+            return Default::default();
+        }
+
+        let size = [last[0] - first[0] + 1, last[1] - first[1] + 1, last[2] - first[2] + 1];
+        let generator = self.generator();
+
+        let material = |name: &str| material_list.find(name).unwrap_or(DEFAULT_MATERIAL);
+        let water_material = material("water");
+
+        //one extra column around the part so slopes at the border see their neighbors
+        let column = |x: i32, z: i32| ((z - first[2] + 1) * (size[0] + 2) + (x - first[0] + 1)) as usize;
+        let mut height_list = vec![0; ((size[0] + 2) * (size[2] + 2)) as usize];
+        for z in first[2] - 1..=last[2] + 1 {
+            for x in first[0] - 1..=last[0] + 1 {
+                height_list[column(x, z)] = generator.height(x, z);
+            }
+        }
+
+        let carve = |x: i32, y: i32, z: i32, h: i32| preset.caves && y < h - CAVE_ROOF && generator.cave(x, y, z);
+
+        let cell = |x: i32, y: i32, z: i32| (((z - first[2]) * size[1] + (y - first[1])) * size[0] + (x - first[0])) as usize;
+        let mut solid_list = vec![false; (size[0] * size[1] * size[2]) as usize];
+        for z in first[2]..=last[2] {
+            for x in first[0]..=last[0] {
+                let h = height_list[column(x, z)];
+                for y in first[1]..=h.min(last[1]) {
+                    solid_list[cell(x, y, z)] = !carve(x, y, z, h);
+                }
+            }
+        }
+
+        //outside the box counts as air so the cut faces are closed, except below it
+        let solid = |x: i32, y: i32, z: i32| {
+            if y < box_first[1] {
+                return true;
+            }
+            if x < box_first[0] || x > box_last[0] || y > box_last[1] || z < box_first[2] || z > box_last[2] {
+                return false;
+            }
+            if x < first[0] || x > last[0] || z < first[2] || z > last[2] {
+                //a neighboring part, worked out on the spot
+                let h = generator.height(x, z);
+                return y <= h && !carve(x, y, z, h);
+            }
+            solid_list[cell(x, y, z)]
+        };
+
+        let side_list: [([i32; 3], [f32; 3]); 5] = [
+            ([0, 1, 0], [0.0, 1.0, 0.0]),
+            ([-1, 0, 0], [-1.0, 0.0, 0.0]),
+            ([1, 0, 0], [1.0, 0.0, 0.0]),
+            ([0, 0, -1], [0.0, 0.0, -1.0]),
+            ([0, 0, 1], [0.0, 0.0, 1.0]),
+        ];
+
+        let mut instance_list: Vec<Instance> = Default::default();
+        let mut ground_list: [Vec<[i32; 3]>; 3] = Default::default();
+
+        for z in first[2]..=last[2] {
+            for x in first[0]..=last[0] {
+
+                let h = height_list[column(x, z)];
+                let slope = [column(x - 1, z), column(x + 1, z), column(x, z - 1), column(x, z + 1)]
+                    .iter()
+                    .map(|c| (height_list[*c] - h).abs())
+                    .max()
+                    .unwrap_or(0);
+
+                for y in first[1]..=h.min(last[1]) {
+
+                    if !solid(x, y, z) {
+                        continue;
+                    }
+
+                    let normal = match side_list.iter().find(|(d, _)| !solid(x + d[0], y + d[1], z + d[2])) {
+                        Some((_, normal)) => *normal,
+                        None if !solid(x, y - 1, z) => [0.0, -1.0, 0.0],
+                        //buried inside the terrain, never visible
+                        None => continue,
+                    };
+
+                    let (color, name) = if y == h {
+                        let biome = generator.biome(x, y, z, slope, water);
+                        if biome != Biome::Bare && y < last[1] {
+                            ground_list[biome as usize].push([x, y, z]);
+                        }
+                        generator.surface_color(y, biome, water)
+                    } else if y > h - DIRT_DEPTH && slope <= 2 {
+                        (DIRT_COLOR, "dirt")
+                    } else {
+                        (ROCK_COLOR, "stone")
+                    };
+                    instance_list.push(voxel([x, y, z], jitter(color, [x, y, z], seed), normal, material(name)));
+                }
+
+                //full columns so the simulation sees standing water and leaves it alone
+                if let Some(water) = water {
+                    for y in (h + 1).max(first[1])..=water.min(last[1]) {
+                        instance_list.push(voxel([x, y, z], WATER_COLOR, [0.0, 1.0, 0.0], water_material));
+                    }
+                }
+            }
+        }
+
+        if preset.vegetation > 0.0 {
+
+            let scale = |rule_list: &[ScatterRule]| -> Vec<ScatterRule> {
+                rule_list.iter().map(|r| ScatterRule { density: (r.density * preset.vegetation).min(1.0), ..*r }).collect()
+            };
+            let rule_list = [scale(&FOREST_RULE_LIST), scale(&MEADOW_RULE_LIST), scale(&DRY_RULE_LIST)];
+
+            //plants rooted in this part may reach into the next one, they are still cut by the whole box
+            for (biome, ground) in ground_list.iter().enumerate() {
+                vegetation::scatter(seed, ground, &rule_list[biome], (box_first, box_last), material_list)
+                    .into_iter()
+                    //crowns reaching into a hillside or under water are cut off
+                    .filter(|ins| {
+                        let p = ins.grid_position();
+                        !solid(p[0], p[1], p[2]) && water.is_none_or(|water| p[1] > water)
+                    })
+                    .for_each(|ins| instance_list.push(ins));
+            }
+        }
+
+        instance_list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_preset(seed: u32, preset: &str) -> Vec<Instance> {
+        let material_list = MaterialList::new();
+        let preset = &PRESET_LIST[find_preset(preset).unwrap()];
+        Terrain::new(seed, [0, 0, 0], [47, 63, 47], *preset, None).unwrap().generate(&material_list)
+    }
+
+    #[test]
+    fn same_seed_same_world() {
+        let a = generate_preset(7, "hills");
+        let b = generate_preset(7, "hills");
+        assert!(!a.is_empty());
+        assert!(a == b);
+        let c = generate_preset(8, "hills");
+        assert!(a != c);
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        let noise = Noise::new(3);
+        for i in 0..1000 {
+            let (x, z) = (i as f32 * 0.37, i as f32 * 0.71);
+            assert!(noise.noise2(x, z).abs() <= 1.0);
+            assert!(noise.noise3(x, z, x - z).abs() <= 1.5);
+            let f = noise.fbm2(x, z, 5);
+            let r = noise.ridged2(x, z, 5);
+            assert!((0.0..=1.0).contains(&f) && (0.0..=1.0).contains(&r));
+        }
+    }
+
+    #[test]
+    fn water_fills_up_to_level() {
+        let material_list = MaterialList::new();
+        let water = material_list.find("water").unwrap();
+        let preset = &PRESET_LIST[find_preset("plains").unwrap()];
+        let instance_list = Terrain::new(1, [0, 0, 0], [31, 31, 31], *preset, Some(20)).unwrap().generate(&material_list);
+        assert!(instance_list.iter().filter(|i| i.material == water).all(|i| i.position.y <= 20.0));
+        assert!(instance_list.iter().any(|i| i.material == water && i.position.y == 20.0));
+    }
+
+    #[test]
+    fn rejects_huge_boxes() {
+        assert!(Terrain::new(1, [0, 0, 0], [4096, 10, 10], PRESET_LIST[0], None).is_err());
+    }
+
+    #[test]
+    fn parts_match_the_whole() {
+        let material_list = MaterialList::new();
+        let terrain = Terrain::new(11, [0, 0, 0], [40, 47, 40], PRESET_LIST[DEFAULT_PRESET], None).unwrap();
+        let mut whole: Vec<[i32; 3]> = terrain.generate(&material_list).iter().map(|i| i.grid_position()).collect();
+        let mut parts: Vec<[i32; 3]> = terrain
+            .part_list(16)
+            .iter()
+            .flat_map(|(first, last)| terrain.generate_part(*first, *last, &material_list))
+            .map(|i| i.grid_position())
+            .collect();
+        whole.sort();
+        parts.sort();
+        parts.dedup();
+        assert_eq!(terrain.part_list(16).len(), 9);
+        assert_eq!(whole, parts);
+    }
+}
@@ -0,0 +1,103 @@
+pub const DEFAULT_TICK_RATE: u32 = 20;
+pub const MAX_TICK_RATE: u32 = 240;
+
+//a frame that falls further behind than this drops the missing ticks instead of freezing to catch up
+const MAX_CATCH_UP: u32 = 5;
+
+pub struct TickClock {
+    //ticks per second
+    pub rate: u32,
+    pub tick: u64,
+    accumulator: f64,
+
+    //measured over the last full second
+    pub tps: i32,
+    measure_timer: f64,
+    measure_count: i32,
+}
+
+impl TickClock {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate: rate.clamp(1, MAX_TICK_RATE),
+            tick: 0,
+            accumulator: 0.0,
+            tps: 0,
+            measure_timer: 0.0,
+            measure_count: 0,
+        }
+    }
+
+    //seconds per tick
+    pub fn step(&self) -> f64 {
+        1.0 / self.rate as f64
+    }
+
+    pub fn set_rate(&mut self, rate: u32) {
+        self.rate = rate.clamp(1, MAX_TICK_RATE);
+        self.accumulator = self.accumulator.min(self.step());
+    }
+
+    //adds the frame time and returns how many ticks to run now
+    pub fn advance(&mut self, dt: f64) -> u32 {
+
+        self.accumulator += dt;
+        let step = self.step();
+
+        let mut count = 0;
+        while self.accumulator >= step && count < MAX_CATCH_UP {
+            self.accumulator -= step;
+            count += 1;
+        }
+        if count == MAX_CATCH_UP && self.accumulator >= step {
+            self.accumulator %= step;
+        }
+
+        self.tick += count as u64;
+        self.measure_count += count as i32;
+        self.measure_timer += dt;
+        if self.measure_timer >= 1.0 {
+            self.tps = (self.measure_count as f64 / self.measure_timer).round() as i32;
+            self.measure_timer = 0.0;
+            self.measure_count = 0;
+        }
+
+        count
+    }
+
+    //how far rendering is between the last tick and the next one, in [0, 1)
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator / self.step()).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_whole_ticks_and_keeps_the_rest() {
+        let mut clock = TickClock::new(20);
+        assert_eq!(clock.advance(0.12), 2);
+        assert!((clock.alpha() - 0.4).abs() < 1e-6);
+        assert_eq!(clock.advance(0.04), 1);
+        assert_eq!(clock.tick, 3);
+    }
+
+    #[test]
+    fn long_frames_drop_ticks() {
+        let mut clock = TickClock::new(60);
+        assert_eq!(clock.advance(2.0), MAX_CATCH_UP);
+        assert!(clock.alpha() < 1.0);
+        assert_eq!(clock.advance(0.0), 0);
+    }
+
+    #[test]
+    fn measures_ticks_per_second() {
+        let mut clock = TickClock::new(20);
+        for _ in 0..61 {
+            clock.advance(1.0 / 60.0);
+        }
+        assert_eq!(clock.tps, 20);
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::Instance;
+use crate::material_list::{MaterialList, DEFAULT_MATERIAL};
+
+//Place mode model ids, following the shapes in model_list
+pub const TREE_MODEL: i32 = 15;
+pub const BUSH_MODEL: i32 = 16;
+pub const GRASS_MODEL: i32 = 17;
+
+const BARK_COLOR: [f32; 4] = [0.4, 0.27, 0.15, 1.0];
+const LEAF_COLOR_LIST: [[f32; 4]; 4] = [
+    [0.3, 0.6, 0.22, 1.0],
+    [0.24, 0.52, 0.2, 1.0],
+    [0.38, 0.62, 0.18, 1.0],
+    [0.45, 0.55, 0.2, 1.0],
+];
+const BLADE_COLOR_LIST: [[f32; 4]; 3] = [
+    [0.4, 0.72, 0.28, 1.0],
+    [0.33, 0.64, 0.24, 1.0],
+    [0.52, 0.7, 0.3, 1.0],
+];
+
+//leaf clusters leave this share of cells out, in 1/100, so light gets through the crown
+const LEAF_GAP: u32 = 15;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Plant {
+    Tree,
+    Bush,
+    Grass,
+}
+
+impl Plant {
+    pub fn from_model(id: i32) -> Option<Self> {
+        match id {
+            TREE_MODEL => Some(Plant::Tree),
+            BUSH_MODEL => Some(Plant::Bush),
+            GRASS_MODEL => Some(Plant::Grass),
+            _ => None,
+        }
+    }
+}
+
+//where plants may go and how thick they stand
+#[derive(Copy, Clone)]
+pub struct ScatterRule {
+    pub plant: Plant,
+    //one candidate per spacing x spacing cell, so two plants of a rule never touch
+    pub spacing: i32,
+    //chance that a candidate grows, in [0, 1]
+    pub density: f32,
+    //box of the plant, width and height
+    pub size: [i32; 2],
+}
+
+pub fn hash(seed: u32, position: [i32; 3]) -> u32 {
+    let mut h = seed
+        ^ (position[0] as u32).wrapping_mul(0x27D4_EB2D)
+        ^ (position[1] as u32).wrapping_mul(0x1656_67B1)
+        ^ (position[2] as u32).wrapping_mul(0x9E37_79B1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297A_2D39);
+    h ^= h >> 15;
+    h
+}
+
+//[0, 1)
+fn unit(seed: u32, position: [i32; 3], salt: u32) -> f32 {
+    (hash(seed ^ salt.wrapping_mul(0x85EB_CA6B), position) % 10000) as f32 / 10000.0
+}
+
+fn shade(color: [f32; 4], f: f32) -> [f32; 4] {
+    [color[0] * f, color[1] * f, color[2] * f, color[3]]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let l = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if l < 1e-6 {
+        return [0.0, 1.0, 0.0];
+    }
+    [v[0] / l, v[1] / l, v[2] / l]
+}
+
+struct Builder {
+    seed: u32,
+    first: [i32; 3],
+    last: [i32; 3],
+    wood: usize,
+    leaves: usize,
+    grass: usize,
+    leaf_radius: f32,
+    //a chosen material replaces the natural ones
+    material: Option<usize>,
+    cell_hash: HashMap<[i32; 3], Instance>,
+}
+
+impl Builder {
+
+    fn new(seed: u32, first: [i32; 3], last: [i32; 3], material: usize, material_list: &MaterialList) -> Self {
+        let find = |name: &str| material_list.find(name).unwrap_or(DEFAULT_MATERIAL);
+        Self {
+            seed,
+            first,
+            last,
+            wood: find("wood"),
+            leaves: find("leaves"),
+            grass: find("grass"),
+            leaf_radius: 1.5,
+            material: if material == DEFAULT_MATERIAL { None } else { Some(material) },
+            cell_hash: Default::default(),
+        }
+    }
+
+    fn inside(&self, p: [i32; 3]) -> bool {
+        (0..3).all(|i| p[i] >= self.first[i] && p[i] <= self.last[i])
+    }
+
+    fn put(&mut self, p: [i32; 3], color: [f32; 4], normal: [f32; 3], material: usize, overwrite: bool) {
+        if !self.inside(p) || (!overwrite && self.cell_hash.contains_key(&p)) {
+            return;
+        }
+        let material = self.material.unwrap_or(material);
+        let jitter = 0.94 + unit(self.seed, p, 1) * 0.12;
+        self.cell_hash.insert(p, Instance::new(p, shade(color, jitter), normal, material));
+    }
+
+    //tapering cylinder from one point to another, built from spheres half a voxel apart
+    fn segment(&mut self, from: [f32; 3], to: [f32; 3], r0: f32, r1: f32) {
+
+        let d = [to[0] - from[0], to[1] - from[1], to[2] - from[2]];
+        let length = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+        let steps = (length * 2.0).ceil().max(1.0) as i32;
+        let wood = self.wood;
+
+        for s in 0..=steps {
+            let t = s as f32 / steps as f32;
+            let c = [from[0] + d[0] * t, from[1] + d[1] * t, from[2] + d[2] * t];
+            let r = r0 + (r1 - r0) * t;
+            let ri = r.ceil() as i32;
+            for x in -ri..=ri {
+                for y in -ri..=ri {
+                    for z in -ri..=ri {
+                        let p = [c[0].round() as i32 + x, c[1].round() as i32 + y, c[2].round() as i32 + z];
+                        let o = [p[0] as f32 - c[0], p[1] as f32 - c[1], p[2] as f32 - c[2]];
+                        if o[0] * o[0] + o[1] * o[1] + o[2] * o[2] <= r * r + 0.25 {
+                            //bark faces away from the axis
+                            let along = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+                            let l2 = (length * length).max(1e-6);
+                            let radial = [o[0] - d[0] * along / l2, o[1] - d[1] * along / l2, o[2] - d[2] * along / l2];
+                            self.put(p, BARK_COLOR, normalize(radial), wood, true);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    //ellipsoid of leaves, never replaces wood
+    fn blob(&mut self, center: [f32; 3], radius: [f32; 3], color: [f32; 4]) {
+        let leaves = self.leaves;
+        let r = [radius[0].ceil() as i32, radius[1].ceil() as i32, radius[2].ceil() as i32];
+        for x in -r[0]..=r[0] {
+            for y in -r[1]..=r[1] {
+                for z in -r[2]..=r[2] {
+                    let p = [center[0].round() as i32 + x, center[1].round() as i32 + y, center[2].round() as i32 + z];
+                    let o = [p[0] as f32 - center[0], p[1] as f32 - center[1], p[2] as f32 - center[2]];
+                    let e = (o[0] / radius[0]).powi(2) + (o[1] / radius[1]).powi(2) + (o[2] / radius[2]).powi(2);
+                    if e > 1.0 || hash(self.seed, p) % 100 < LEAF_GAP {
+                        continue;
+                    }
+                    self.put(p, color, normalize(o), leaves, false);
+                }
+            }
+        }
+    }
+
+    //recursive branching, each level splits into a few shorter and thinner limbs
+    fn branch(&mut self, from: [f32; 3], direction: [f32; 3], length: f32, radius: f32, depth: u32, key: [i32; 3]) {
+
+        let to = [from[0] + direction[0] * length, from[1] + direction[1] * length, from[2] + direction[2] * length];
+        let end_radius = (radius * 0.6).max(0.5);
+        self.segment(from, to, radius, end_radius);
+
+        if depth == 0 {
+            let color = LEAF_COLOR_LIST[hash(self.seed, key) as usize % LEAF_COLOR_LIST.len()];
+            let stretch = 0.8 + unit(self.seed, key, 2) * 0.4;
+            let r = self.leaf_radius;
+            self.blob(to, [r * stretch, r * 0.8, r * stretch], color);
+            return;
+        }
+
+        let count = 2 + hash(self.seed, key) % 3;
+        let start = unit(self.seed, key, 3) * 2.0 * PI;
+        for i in 0..count {
+            let child = [key[0] * 4 + i as i32, key[1] + 1, key[2]];
+            let azimuth = start + i as f32 * 2.0 * PI / count as f32 + (unit(self.seed, child, 4) - 0.5) * 0.8;
+            let tilt = (25.0 + unit(self.seed, child, 5) * 30.0).to_radians();
+            //children lean away from the parent while keeping some of its heading
+            let spread = [tilt.sin() * azimuth.cos(), tilt.cos(), tilt.sin() * azimuth.sin()];
+            let direction = normalize([
+                spread[0] + direction[0] * 0.5,
+                spread[1] + direction[1] * 0.5,
+                spread[2] + direction[2] * 0.5,
+            ]);
+            let child_length = length * (0.6 + unit(self.seed, child, 6) * 0.2);
+            self.branch(to, direction, child_length, end_radius, depth - 1, child);
+        }
+    }
+
+    fn tree(&mut self) {
+
+        let width = (self.last[0] - self.first[0]).min(self.last[2] - self.first[2]) as f32 + 1.0;
+        let height = (self.last[1] - self.first[1]) as f32 + 1.0;
+
+        let base = [
+            (self.first[0] + self.last[0]) as f32 / 2.0,
+            self.first[1] as f32,
+            (self.first[2] + self.last[2]) as f32 / 2.0,
+        ];
+        let trunk_height = height * (0.4 + unit(self.seed, self.first, 7) * 0.15);
+        let trunk_radius = (width / 20.0).max(0.5);
+        self.leaf_radius = (width / 7.0).max(1.5);
+        let depth = if height >= 48.0 { 3 } else if height >= 16.0 { 2 } else { 1 };
+
+        let top = [base[0], base[1] + trunk_height, base[2]];
+        self.segment(base, top, trunk_radius, trunk_radius * 0.7);
+        self.branch(top, [0.0, 1.0, 0.0], height * 0.25, trunk_radius * 0.7, depth, [0, 0, 0]);
+    }
+
+    fn bush(&mut self) {
+
+        let width = (self.last[0] - self.first[0]).min(self.last[2] - self.first[2]) as f32 + 1.0;
+        let height = (self.last[1] - self.first[1]) as f32 + 1.0;
+        let center = [(self.first[0] + self.last[0]) as f32 / 2.0, self.first[1] as f32, (self.first[2] + self.last[2]) as f32 / 2.0];
+
+        //a few overlapping blobs sitting on the ground around a short stem
+        let count = 3 + hash(self.seed, self.first) % 3;
+        for i in 0..count {
+            let key = [i as i32, 0, 0];
+            let angle = unit(self.seed, key, 8) * 2.0 * PI;
+            let offset = unit(self.seed, key, 9) * width / 4.0;
+            let radius = width / 4.0 * (0.7 + unit(self.seed, key, 10) * 0.3);
+            let color = LEAF_COLOR_LIST[hash(self.seed, key) as usize % LEAF_COLOR_LIST.len()];
+            self.blob(
+                [center[0] + angle.cos() * offset, center[1] + radius * 0.8, center[2] + angle.sin() * offset],
+                [radius.max(1.0), (height / 2.0).min(radius).max(1.0), radius.max(1.0)],
+                color,
+            );
+        }
+        self.segment(center, [center[0], center[1] + height / 3.0, center[2]], (width / 16.0).max(0.5), 0.5);
+    }
+
+    fn grass(&mut self) {
+
+        let width = (self.last[0] - self.first[0]).min(self.last[2] - self.first[2]) + 1;
+        let height = self.last[1] - self.first[1] + 1;
+        let grass = self.grass;
+
+        let count = 3 + hash(self.seed, self.first) % 5;
+        for i in 0..count {
+            let key = [i as i32, 1, 0];
+            let x = self.first[0] + (unit(self.seed, key, 11) * width as f32) as i32;
+            let z = self.first[2] + (unit(self.seed, key, 12) * width as f32) as i32;
+            let blade = 1 + (unit(self.seed, key, 13) * height as f32) as i32;
+            //blades lean one step once they are past half their height
+            let lean = [[1, 0], [-1, 0], [0, 1], [0, -1]][hash(self.seed, key) as usize % 4];
+            let color = BLADE_COLOR_LIST[hash(self.seed, [x, 0, z]) as usize % BLADE_COLOR_LIST.len()];
+            for y in 0..blade {
+                let bent = if blade > 2 && y > blade / 2 { 1 } else { 0 };
+                let p = [x + lean[0] * bent, self.first[1] + y, z + lean[1] * bent];
+                self.put(p, shade(color, 0.8 + 0.2 * y as f32 / blade as f32), [0.0, 1.0, 0.0], grass, false);
+            }
+        }
+    }
+}
+
+//a plant grown from the bottom middle of the box and clipped to it, same seed same plant
+pub fn grow(plant: Plant, seed: u32, first: [i32; 3], last: [i32; 3], material: usize, material_list: &MaterialList) -> HashMap<[i32; 3], Instance> {
+
+    let first_box = [first[0].min(last[0]), first[1].min(last[1]), first[2].min(last[2])];
+    let last_box = [first[0].max(last[0]), first[1].max(last[1]), first[2].max(last[2])];
+
+    let mut builder = Builder::new(seed, first_box, last_box, material, material_list);
+    match plant {
+        Plant::Tree => builder.tree(),
+        Plant::Bush => builder.bush(),
+        Plant::Grass => builder.grass(),
+    }
+    builder.cell_hash
+}
+
+//plants on top of the given ground cells, rules are tried in order and the first one that grows wins
+pub fn scatter(seed: u32, ground_list: &[[i32; 3]], rule_list: &[ScatterRule], bound: ([i32; 3], [i32; 3]), material_list: &MaterialList) -> Vec<Instance> {
+
+    let mut cell_hash: HashMap<[i32; 3], Instance> = Default::default();
+
+    for ground in ground_list {
+        for (r, rule) in rule_list.iter().enumerate() {
+
+            //jittered grid, the one candidate of a cell sits at a hashed spot inside it
+            let spacing = rule.spacing.max(1);
+            let cell = [ground[0].div_euclid(spacing), r as i32, ground[2].div_euclid(spacing)];
+            let spot = hash(seed, cell);
+            let candidate = [
+                cell[0] * spacing + (spot % spacing as u32) as i32,
+                cell[2] * spacing + ((spot >> 16) % spacing as u32) as i32,
+            ];
+            if candidate != [ground[0], ground[2]] || unit(seed, cell, 14) >= rule.density {
+                continue;
+            }
+
+            let half = rule.size[0] / 2;
+            let first = [ground[0] - half, ground[1] + 1, ground[2] - half];
+            let last = [
+                (first[0] + rule.size[0] - 1).min(bound.1[0]).max(first[0]),
+                (first[1] + rule.size[1] - 1).min(bound.1[1]).max(first[1]),
+                (first[2] + rule.size[0] - 1).min(bound.1[2]).max(first[2]),
+            ];
+            let first = [first[0].max(bound.0[0]), first[1], first[2].max(bound.0[2])];
+
+            let plant_seed = hash(seed, *ground);
+            grow(rule.plant, plant_seed, first, last, DEFAULT_MATERIAL, material_list)
+                .into_iter()
+                .for_each(|(p, ins)| {
+                    cell_hash.entry(p).or_insert(ins);
+                });
+            break;
+        }
+    }
+
+    //sorted so the same seed also gives the same order
+    let mut cell_list: Vec<([i32; 3], Instance)> = cell_hash.into_iter().collect();
+    cell_list.sort_by_key(|(p, _)| *p);
+    cell_list.into_iter().map(|(_, ins)| ins).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trees_are_deterministic_and_stay_in_the_box() {
+        let material_list = MaterialList::new();
+        let first = [0, 0, 0];
+        let last = [23, 47, 23];
+        let a = grow(Plant::Tree, 5, first, last, DEFAULT_MATERIAL, &material_list);
+        let b = grow(Plant::Tree, 5, first, last, DEFAULT_MATERIAL, &material_list);
+        assert!(!a.is_empty());
+        assert_eq!(a.len(), b.len());
+        assert!(a.iter().all(|(p, ins)| b.get(p) == Some(ins)));
+        assert!(a.keys().all(|p| (0..3).all(|i| p[i] >= first[i] && p[i] <= last[i])));
+
+        let wood = material_list.find("wood").unwrap();
+        let leaves = material_list.find("leaves").unwrap();
+        assert!(a.values().any(|ins| ins.material == wood));
+        assert!(a.values().any(|ins| ins.material == leaves));
+        //the trunk starts at the ground
+        assert!(a.get(&[11, 0, 11]).or(a.get(&[12, 0, 12])).is_some());
+    }
+
+    #[test]
+    fn chosen_material_replaces_natural_ones() {
+        let material_list = MaterialList::new();
+        let stone = material_list.find("stone").unwrap();
+        let bush = grow(Plant::Bush, 1, [0, 0, 0], [9, 5, 9], stone, &material_list);
+        assert!(!bush.is_empty());
+        assert!(bush.values().all(|ins| ins.material == stone));
+    }
+
+    #[test]
+    fn scatter_keeps_spacing() {
+        let material_list = MaterialList::new();
+        let mut ground_list = vec![];
+        for x in 0..64 {
+            for z in 0..64 {
+                ground_list.push([x, 0, z]);
+            }
+        }
+        let rule = ScatterRule { plant: Plant::Grass, spacing: 8, density: 1.0, size: [1, 2] };
+        let instance_list = scatter(3, &ground_list, &[rule], ([0, 0, 0], [63, 31, 63]), &material_list);
+        let mut column_list: Vec<[i32; 2]> = instance_list.iter().map(|i| [i.position.x as i32, i.position.z as i32]).collect();
+        column_list.sort();
+        column_list.dedup();
+        assert!(!column_list.is_empty() && column_list.len() <= 64);
+    }
+}
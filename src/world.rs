@@ -0,0 +1,299 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use iced_wgpu::wgpu;
+
+use crate::Instance;
+use crate::camera_mode::View;
+use crate::chunk::{Chunk, ChunkManager};
+use crate::job::JobSystem;
+use crate::light::Light;
+use crate::material_list::{MaterialList, DEFAULT_MATERIAL};
+
+//a world is a directory holding world.txt and one binary file per chunk
+pub const DEFAULT_WORLD: &str = "world";
+const WORLD_FILE: &str = "world.txt";
+
+const CHUNK_MAGIC: [u8; 4] = *b"AVOX";
+const CHUNK_VERSION: u32 = 1;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ChunkHeader {
+    magic: [u8; 4],
+    version: u32,
+    count: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct VoxelRecord {
+    position: [i32; 3],
+    color: [f32; 4],
+    normal: [f32; 3],
+    //index into the material lines of world.txt
+    material: u32,
+}
+
+impl VoxelRecord {
+    fn from_instance(ins: &Instance) -> Self {
+        Self {
+            position: ins.grid_position(),
+            color: ins.color.into(),
+            normal: ins.normal.into(),
+            material: ins.material as u32,
+        }
+    }
+
+    fn to_instance(self, material: usize) -> Instance {
+        Instance::new(self.position, self.color, self.normal, material)
+    }
+}
+
+pub fn chunk_file(position: [i32; 3]) -> String {
+    format!("chunk_{}_{}_{}.bin", position[0], position[1], position[2])
+}
+
+//material indices are written as they are in the running material list
+pub fn chunk_bytes(voxel_list: &[Instance]) -> Vec<u8> {
+
+    let header = ChunkHeader { magic: CHUNK_MAGIC, version: CHUNK_VERSION, count: voxel_list.len() as u32 };
+    let record_list: Vec<VoxelRecord> = voxel_list.iter().map(VoxelRecord::from_instance).collect();
+
+    let mut bytes = bytemuck::bytes_of(&header).to_vec();
+    bytes.extend_from_slice(bytemuck::cast_slice(&record_list));
+    bytes
+}
+
+//material_map turns saved material indices into indices of the running material list, None keeps them
+pub fn parse_chunk(bytes: &[u8], material_map: Option<&[usize]>) -> Result<Vec<Instance>, String> {
+
+    let header_size = std::mem::size_of::<ChunkHeader>();
+    if bytes.len() < header_size {
+        return Err("Truncated chunk file".to_string());
+    }
+
+    let header: ChunkHeader = bytemuck::pod_read_unaligned(&bytes[..header_size]);
+    if header.magic != CHUNK_MAGIC || header.version != CHUNK_VERSION {
+        return Err("Unknown chunk format".to_string());
+    }
+
+    let record_size = std::mem::size_of::<VoxelRecord>();
+    if bytes.len() != header_size + header.count as usize * record_size {
+        return Err("Truncated chunk file".to_string());
+    }
+
+    Ok(bytes[header_size..]
+        .chunks_exact(record_size)
+        .map(|b| {
+            let record: VoxelRecord = bytemuck::pod_read_unaligned(b);
+            let material = match material_map {
+                Some(material_map) => material_map.get(record.material as usize).copied().unwrap_or(DEFAULT_MATERIAL),
+                None => record.material as usize,
+            };
+            record.to_instance(material)
+        })
+        .collect())
+}
+
+pub fn write_chunk(dir: &Path, chunk: &Chunk) -> Result<(), String> {
+    fs::write(dir.join(chunk_file(chunk.position)), chunk_bytes(&chunk.voxel_data)).map_err(|e| e.to_string())
+}
+
+pub fn read_chunk(dir: &Path, position: [i32; 3], material_map: &[usize]) -> Result<Vec<Instance>, String> {
+    let bytes = fs::read(dir.join(chunk_file(position))).map_err(|e| e.to_string())?;
+    parse_chunk(&bytes, Some(material_map))
+}
+
+pub fn material_map(name_list: &[String], material_list: &MaterialList) -> Vec<usize> {
+    name_list.iter().map(|n| material_list.find(n).unwrap_or(DEFAULT_MATERIAL)).collect()
+}
+
+fn parse_f32(v: &[&str], count: usize) -> Result<Vec<f32>, String> {
+    if v.len() < count {
+        return Err("Insufficient args in ".to_string() + &v.join(" "));
+    }
+    v[..count].iter().map(|s| s.parse::<f32>().map_err(|e| e.to_string())).collect()
+}
+
+fn parse_i32(v: &[&str], count: usize) -> Result<Vec<i32>, String> {
+    if v.len() < count {
+        return Err("Insufficient args in ".to_string() + &v.join(" "));
+    }
+    v[..count].iter().map(|s| s.parse::<i32>().map_err(|e| e.to_string())).collect()
+}
+
+//returns the number of chunks written
+pub fn save(path: &str, chunk_manager: &ChunkManager) -> Result<usize, String> {
+
+    let dir = Path::new(path);
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    let mut text = String::new();
+
+    chunk_manager.material_list.material_list.iter().for_each(|m| {
+        text += &format!("material {}\n", m.name);
+    });
+
+    let light_manager = &chunk_manager.light_manager;
+    let sun = light_manager.sun_color;
+    let ambient = light_manager.ambient;
+    text += &format!("sun {} {} {} {} {}\n", light_manager.sun_azimuth, light_manager.sun_elevation, sun[0], sun[1], sun[2]);
+    text += &format!("ambient {} {} {}\n", ambient[0], ambient[1], ambient[2]);
+
+    //lights owned by voxels come back with the voxels
+    let owned = chunk_manager.owned_lights();
+    light_manager.iter().filter(|(h, _)| !owned.contains(h)).for_each(|(_, l)| {
+        text += &format!(
+            "light {} {} {} {} {} {} {} {}\n",
+            l.position[0], l.position[1], l.position[2], l.color[0], l.color[1], l.color[2], l.color[3], l.radius
+        );
+    });
+
+    //names come from /view and never hold spaces
+    chunk_manager.view_hash.iter().for_each(|(name, view)| {
+        let p = view.position;
+        text += &format!("view {} {} {} {} {} {} {}\n", name, p[0], p[1], p[2], view.yaw, view.pitch, view.zoom);
+    });
+
+    let mut count = 0;
+    for chunk in chunk_manager.chunk_hash.values().map(|i| &chunk_manager.chunk_list[*i]) {
+        if chunk.voxel_data.is_empty() {
+            continue;
+        }
+        write_chunk(dir, chunk)?;
+        text += &format!("chunk {} {} {}\n", chunk.position[0], chunk.position[1], chunk.position[2]);
+        count += 1;
+    }
+
+    //streamed out chunks are already packed in the same format
+    for position in chunk_manager.streamer.evicted_list() {
+        let bytes = chunk_manager.streamer.bytes(position)?;
+        fs::write(dir.join(chunk_file(position)), bytes).map_err(|e| e.to_string())?;
+        text += &format!("chunk {} {} {}\n", position[0], position[1], position[2]);
+        count += 1;
+    }
+
+    let mut file = fs::File::create(dir.join(WORLD_FILE)).map_err(|e| e.to_string())?;
+    file.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+
+    Ok(count)
+}
+
+//replaces the current world, returns the number of chunks read
+//background jobs are only cancelled once the files have been read, a failed load leaves them running
+pub fn load(path: &str, chunk_manager: &mut ChunkManager, job_system: &mut JobSystem, device: &wgpu::Device) -> Result<usize, String> {
+
+    let dir = Path::new(path);
+    let file = fs::File::open(dir.join(WORLD_FILE)).map_err(|e| e.to_string())?;
+
+    let mut material_name_list: Vec<String> = Default::default();
+    let mut light_list: Vec<Light> = Default::default();
+    let mut view_list: Vec<(String, View)> = Default::default();
+    let mut chunk_position_list: Vec<[i32; 3]> = Default::default();
+    let mut sun: Option<Vec<f32>> = None;
+    let mut ambient: Option<Vec<f32>> = None;
+
+    //parse everything before touching the running world
+    for line in BufReader::new(file).lines() {
+
+        let line = line.map_err(|e| e.to_string())?;
+        let v: Vec<&str> = line.split_whitespace().collect();
+
+        match v.first() {
+            Some(&"material") if v.len() > 1 => material_name_list.push(v[1].to_string()),
+            Some(&"sun") => sun = Some(parse_f32(&v[1..], 5)?),
+            Some(&"ambient") => ambient = Some(parse_f32(&v[1..], 3)?),
+            Some(&"light") => {
+                let l = parse_f32(&v[1..], 8)?;
+                light_list.push(Light::point([l[0], l[1], l[2]], [l[3], l[4], l[5]], l[6], l[7]));
+            }
+            Some(&"view") if v.len() > 1 => {
+                let l = parse_f32(&v[2..], 6)?;
+                view_list.push((v[1].to_string(), View { position: [l[0], l[1], l[2]], yaw: l[3], pitch: l[4], zoom: l[5] }));
+            }
+            Some(&"chunk") => {
+                let p = parse_i32(&v[1..], 3)?;
+                chunk_position_list.push([p[0], p[1], p[2]]);
+            }
+            //unknown keys are left for newer versions
+            _ => {}
+        }
+    }
+
+    let material_map = material_map(&material_name_list, &chunk_manager.material_list);
+
+    let mut instance_list: Vec<Instance> = Default::default();
+    for position in chunk_position_list.iter() {
+        instance_list.extend(read_chunk(dir, *position, &material_map)?);
+    }
+
+    chunk_manager.clear_world(job_system);
+    chunk_manager.view_hash.extend(view_list);
+
+    let light_manager = &mut chunk_manager.light_manager;
+    if let Some(sun) = sun {
+        light_manager.sun_azimuth = sun[0];
+        light_manager.sun_elevation = sun[1];
+        light_manager.sun_color = [sun[2], sun[3], sun[4]];
+    }
+    if let Some(ambient) = ambient {
+        light_manager.ambient = [ambient[0], ambient[1], ambient[2]];
+    }
+    light_list.into_iter().for_each(|l| {
+        light_manager.add(l);
+    });
+
+    //light casting voxels claim their lights again while being placed
+    chunk_manager.place_instances(instance_list, device);
+
+    Ok(chunk_position_list.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voxel_list() -> Vec<Instance> {
+        vec![
+            Instance::new([0, 0, 0], [1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0], 0),
+            Instance::new([-3, 7, 12], [0.2, 0.4, 0.6, 0.5], [1.0, 0.0, 0.0], 2),
+        ]
+    }
+
+    #[test]
+    fn chunk_bytes_round_trip() {
+        let voxel_list = voxel_list();
+        let bytes = chunk_bytes(&voxel_list);
+        assert!(parse_chunk(&bytes, None).unwrap() == voxel_list);
+
+        //an empty chunk is just the header
+        assert!(parse_chunk(&chunk_bytes(&[]), None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_and_foreign_data() {
+        let bytes = chunk_bytes(&voxel_list());
+        assert!(parse_chunk(&bytes[..bytes.len() - 1], None).is_err());
+        assert!(parse_chunk(&bytes[..5], None).is_err());
+
+        let mut foreign = bytes.clone();
+        foreign[..4].copy_from_slice(b"PNG\0");
+        assert_eq!(parse_chunk(&foreign, None).err(), Some("Unknown chunk format".to_string()));
+    }
+
+    #[test]
+    fn unknown_materials_fall_back_to_the_default() {
+        let material_list = MaterialList::new();
+        let name_list = vec!["water".to_string(), "unobtainium".to_string()];
+        let map = material_map(&name_list, &material_list);
+        assert_eq!(map, vec![material_list.find("water").unwrap(), DEFAULT_MATERIAL]);
+
+        //saved indices go through the map, indices past its end as well
+        let voxel_list = voxel_list();
+        let loaded = parse_chunk(&chunk_bytes(&voxel_list), Some(&map)).unwrap();
+        assert_eq!(loaded[0].material, map[0]);
+        assert_eq!(loaded[1].material, DEFAULT_MATERIAL);
+    }
+}